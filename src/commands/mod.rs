@@ -0,0 +1,144 @@
+use crate::{
+    config::database::AppState,
+    services::{self, import::Format},
+};
+
+use chrono::Utc;
+use std::{
+    collections::BTreeMap,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+/// The amount of messages the importer commits at a time by default
+const IMPORT_CHUNK: usize = 500;
+
+/// Counts the messages of a chat export
+///
+/// `import <path> [--format json|text|csv] [--source <name>] [--chunk <n>] [--dry-run]`
+///
+/// The source defaults to the file name and is what imported message ids are tracked under.
+fn import(args: &[String], state: &AppState) -> std::io::Result<()> {
+    let mut path = None;
+    let mut format = None;
+    let mut source = None;
+    let mut chunk = IMPORT_CHUNK;
+    let mut dry_run = false;
+
+    let mut args = args.iter();
+    let invalid = |message: &str| Error::new(ErrorKind::InvalidInput, message.to_owned());
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let name = args.next().ok_or_else(|| invalid("Missing format."))?;
+                format = Some(Format::parse(name).ok_or_else(|| invalid("Unknown format."))?);
+            }
+            "--source" => source = Some(args.next().ok_or_else(|| invalid("Missing source."))?),
+            "--chunk" => {
+                chunk = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| invalid("Chunk must be a positive number."))?;
+            }
+            "--dry-run" => dry_run = true,
+            _ => path = Some(Path::new(arg)),
+        }
+    }
+
+    let path = path.ok_or_else(|| invalid("Missing the path of the export."))?;
+    let format = format.unwrap_or(Format::detect(path));
+    let source = match source {
+        Some(source) => source.to_owned(),
+        None => path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| invalid("Missing the path of the export."))?,
+    };
+
+    let input = std::fs::read_to_string(path)?;
+    let messages = services::import::read(&format, &input, Utc::now().naive_utc())
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    let mut conn = state.pool.get().map_err(Error::other)?;
+    let summary = services::import::import(
+        &mut conn,
+        &state.normalizer,
+        &source,
+        &messages,
+        chunk,
+        dry_run,
+    )
+    .map_err(Error::other)?;
+
+    if !dry_run {
+        services::changes::record(&mut conn, &summary.deltas).map_err(Error::other)?;
+
+        let touched = summary.deltas.keys().cloned().collect::<Vec<_>>();
+        let notified = services::milestones::evaluate(&mut conn, &touched).map_err(Error::other)?;
+
+        println!("Reached {notified} milestones! 🏆");
+    }
+
+    if dry_run {
+        for ((username, word), delta) in &summary.deltas {
+            println!("{username}\t{word}\t+{delta}");
+        }
+    }
+
+    println!(
+        "{} {} messages from {source}, skipped {} already imported and {} with invalid usernames! 📥",
+        if dry_run { "Would import" } else { "Imported" },
+        summary.imported,
+        summary.skipped,
+        summary.invalid,
+    );
+
+    Ok(())
+}
+
+/// Runs a maintenance command instead of the server
+///
+/// Commands that write counts record a change, which a running server picks up within a
+/// few seconds.
+///
+/// # Commands
+/// - `renormalize`: Runs every stored word through the normalizer and merges the duplicates
+/// - `reindex`: Rebuilds the n-gram index of the usernames and words
+/// - `import`: Counts the messages of a chat export, see [`import`]
+/// - `snapshot`: Snapshots the user and word leaderboards now, outside the schedule
+pub fn run(args: &[String], state: &AppState) -> std::io::Result<()> {
+    let mut conn = state.pool.get().map_err(Error::other)?;
+
+    match args[0].as_str() {
+        "renormalize" => {
+            let changed = services::counter::renormalize(&mut conn, &state.normalizer)
+                .map_err(Error::other)?;
+            services::changes::record(&mut conn, &BTreeMap::new()).map_err(Error::other)?;
+
+            println!("Renormalized {changed} words! 🧹");
+        }
+        "reindex" => {
+            let indexed = services::index::rebuild(&mut conn).map_err(Error::other)?;
+
+            println!("Indexed {indexed} usernames and words! 🔎");
+        }
+        "import" => import(&args[1..], state)?,
+        "snapshot" => {
+            let stored = services::snapshots::take(&mut conn, Utc::now().naive_utc())
+                .map_err(Error::other)?;
+            services::changes::record(&mut conn, &BTreeMap::new()).map_err(Error::other)?;
+
+            println!("Snapshotted {stored} leaderboard rows! 📸");
+        }
+        command => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown command `{command}`."),
+            ));
+        }
+    }
+
+    Ok(())
+}
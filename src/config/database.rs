@@ -1,24 +1,112 @@
+use crate::{
+    dtos::responses::counter::{StatsData, UserData, WordData},
+    services::{self, anomalies::Guard, stream::Event, vectors::Vectors},
+    utils::{cache::Cache, normalize::Normalizer},
+};
+
 use actix_web::web::Data;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::OpenFlags;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+use tokio::sync::broadcast;
+
+/// A page of leaderboard rows with the total amount of rows
+pub type Page<T> = (Vec<T>, u32);
+
+/// Whether usernames are left unresolved, and the username or `None` for everyone
+pub type StatsKey = (bool, Option<String>);
 
 pub struct AppState {
     pub pool: Pool<SqliteConnectionManager>,
+    pub normalizer: Normalizer,
+
+    /// Bumped whenever counter rows are written so cached data can be rebuilt
+    pub counter_version: AtomicU64,
+    /// Word vectors by whether usernames are left unresolved
+    pub vectors: RwLock<HashMap<bool, Arc<Vectors>>>,
+
+    /// Vocabulary statistics with the version they were built at
+    pub stats: RwLock<HashMap<StatsKey, (u64, StatsData)>>,
+
+    /// Leaderboard pages keyed on their query parameters
+    pub users_cache: Cache<String, Page<UserData>>,
+    pub words_cache: Cache<String, Page<WordData>>,
+
+    /// Live counter events shared by every connected stream
+    pub events: broadcast::Sender<Arc<Event>>,
+
+    /// The limits ingested messages are flagged over
+    pub anomalies: Guard,
+}
+
+impl AppState {
+    pub fn counter_changed(&self) {
+        self.counter_version.fetch_add(1, Ordering::Release);
+        self.users_cache.clear();
+        self.words_cache.clear();
+    }
 }
 
 pub fn init() -> Data<AppState> {
     let db_path: String = std::env::var("DB_PATH").expect("DB_PATH in .env must be set");
 
-    let manager = SqliteConnectionManager::file(db_path).with_flags(
-        OpenFlags::SQLITE_OPEN_READ_WRITE
-            | OpenFlags::SQLITE_OPEN_CREATE
-            | OpenFlags::SQLITE_OPEN_FULL_MUTEX,
-    );
+    let manager = SqliteConnectionManager::file(db_path)
+        .with_flags(
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+                | OpenFlags::SQLITE_OPEN_CREATE
+                | OpenFlags::SQLITE_OPEN_FULL_MUTEX,
+        )
+        .with_init(|conn| services::index::register(conn));
 
     let pool = Pool::new(manager).expect("Failed to created SQLite pool.");
+    let normalizer = Normalizer::from_env();
+
+    let ttl = std::env::var("COUNTER_CACHE_TTL")
+        .ok()
+        .and_then(|ttl| ttl.parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(60));
+
+    let size = std::env::var("COUNTER_CACHE_SIZE")
+        .ok()
+        .and_then(|size| size.parse::<usize>().ok())
+        .filter(|size| *size > 0)
+        .unwrap_or(256);
+
+    let mut conn = pool.get().expect("Failed to get a SQLite connection.");
+
+    // Create the tables the API owns if they don't exist yet
+    conn.execute_batch(include_str!("../database/counter.sql"))
+        .expect("Failed to create the counter tables.");
+
+    // Store the configured stopwords so queries can exclude them
+    services::counter::sync_stopwords(&mut conn, &normalizer)
+        .expect("Failed to store the stopwords.");
+
+    // Index the terms of databases counted before the n-gram index existed
+    services::index::ensure(&mut conn).expect("Failed to build the n-gram index.");
+
+    drop(conn);
 
     println!("Connected to database! 💾");
 
-    Data::new(AppState { pool })
+    Data::new(AppState {
+        pool,
+        normalizer,
+        counter_version: AtomicU64::new(0),
+        vectors: RwLock::new(HashMap::new()),
+        stats: RwLock::new(HashMap::new()),
+        users_cache: Cache::new(ttl, size),
+        words_cache: Cache::new(ttl, size),
+        events: broadcast::channel(services::stream::CAPACITY).0,
+        anomalies: Guard::from_env(),
+    })
 }
@@ -1,15 +1,88 @@
 use crate::{
     config::database::AppState,
     dtos::{
-        requests::counter::{QueryPagination, QueryParams, SetQueryPagination, SetQueryParams},
+        errors,
+        requests::counter::{
+            Message, QueryCompare, QueryDistinctive, QueryLeaderboard, QueryPagination,
+            QueryParams, QueryProfile, QueryRankHistory, QueryRaw, QuerySimilar, QueryStream,
+            QueryTimeline, SetQueryCompare, SetQueryLeaderboard, SetQueryPagination,
+            SetQueryParams, SetQueryRankHistory, SetQueryTimeline, UserPath,
+        },
         responses::counter::{
-            Data, Filters, Links, Meta, Pagination, PartMeta, Response, Sort, UserData,
-            UserResponse, WordData, WordResponse,
+            CacheData, CacheMeta, CacheResponse, CompareData, CompareMeta, CompareResponse,
+            CompareUserData, CountData, Data, DistinctiveData, DistinctiveMeta,
+            DistinctiveResponse, Filters, IngestData, IngestResponse, LeaderboardMeta, Links, Meta,
+            Paginated, Pagination, PartMeta, ProfileData, ProfileLinks, ProfileResponse, Range,
+            RankHistoryData, RankHistoryMeta, RankHistoryResponse, SharedWordData, SimilarUserData,
+            SimilarUserMeta, SimilarUserResponse, SimilarWordData, Sort, StatsMeta, StatsResponse,
+            TimelineData, TimelineMeta, TimelineResponse, UserData, Window, WordData,
+            WordDetailData, WordDetailResponse, WordUserData,
         },
     },
+    services::{
+        self,
+        anomalies::Ingested,
+        export::{self, Format},
+        identity, index, snapshots,
+    },
+    utils::{string, time, vector},
 };
 
-use actix_web::{Error, HttpResponse, error, web};
+use actix_web::{Error, HttpRequest, HttpResponse, error, http::header, web};
+use chrono::{Days, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use rusqlite::{OptionalExtension, ToSql, params, params_from_iter, types::Type};
+use std::{collections::BTreeMap, sync::atomic::Ordering};
+use validator::Validate;
+
+fn stopword_filter(exclude_stopwords: bool) -> &'static str {
+    if exclude_stopwords {
+        "word NOT IN (SELECT word FROM counter_stopwords)"
+    } else {
+        "TRUE"
+    }
+}
+
+/// The counts to rank, summed over the `[start, end)` range of a window when there is one
+///
+/// Ranges of whole days read the daily rollups, anything else reads the hourly history. The
+/// bounds are formatted from dates so they are safe to put in the query.
+pub(crate) fn windowed_source(raw: bool, range: Option<(NaiveDateTime, NaiveDateTime)>) -> String {
+    let Some((start, end)) = range else {
+        return identity::source(raw).to_string();
+    };
+
+    let (source, column, start, end) =
+        match start.time() == NaiveTime::MIN && end.time() == NaiveTime::MIN {
+            true => (
+                identity::daily_source(raw),
+                "day",
+                start.date().to_string(),
+                end.date().to_string(),
+            ),
+            false => (
+                identity::history_source(raw),
+                "hour",
+                time::hour(start),
+                time::hour(end),
+            ),
+        };
+
+    format!(
+        r#"(
+            SELECT username, word, SUM(count) AS count
+            FROM {source}
+            WHERE {column} >= '{start}' AND {column} < '{end}'
+            GROUP BY username, word
+        )"#
+    )
+}
+
+pub(crate) fn rank_function(ranking: &str) -> &'static str {
+    match ranking {
+        "dense" => "DENSE_RANK",
+        _ => "RANK",
+    }
+}
 
 /// Get all counts with filters and pagination
 ///
@@ -22,11 +95,16 @@ use actix_web::{Error, HttpResponse, error, web};
 /// - `order`: The order to return the results (asc|desc). Default `desc`
 /// - `username`: The username to look up with a fuzzy find
 /// - `word`: The word to look up with a fuzzy find
+/// - `format`: Export every row instead of a page (json|csv|ndjson). Also read from `Accept`
+/// - `match`: How `similarity` is scored (dice|levenshtein|damerau|jaro|trigram|phonetic). Default `dice`.
+///   `dice` finds the rows holding every character in order, the others find the rows scoring at least `0.5`
+/// - `exact`: Only get the rows of the exact `username` and `word` instead of a fuzzy find
 ///
 /// # Responses
-/// - `200 Ok`: Returns rows
+/// - `200 Ok`: Returns rows, or streams all rows as `text/csv` or `application/x-ndjson`
 /// - `400 Bad Request`: If invalid parameters
 /// - `500 Internal Server Error`: Server sided error
+/// - `503 Service Unavailable`: If too many exports are running
 ///
 /// # Example Request
 /// `GET /counter?page=2&limit=3&username=di&word=hi`
@@ -70,21 +148,28 @@ use actix_web::{Error, HttpResponse, error, web};
 ///         "sort": {
 ///             "by": "count",
 ///             "order": "desc"
-///         }
+///         },
+///         "match": "dice"
 ///     },
 ///     "links": {
-///         "self": "/counter?page=2&limit=3&order=desc&username=di&word=hi",
-///         "first": "/counter?page=1&limit=3&order=desc&username=di&word=hi",
-///         "last": "/counter?page=36&limit=3&order=desc&username=di&word=hi",
-///         "prev": "/counter?page=1&limit=3&order=desc&username=di&word=hi",
-///         "next": "/counter?page=3&limit=3&order=desc&username=di&word=hi"
+///         "self": "/counter?page=2&limit=3&username=di&word=hi",
+///         "first": "/counter?page=1&limit=3&username=di&word=hi",
+///         "last": "/counter?page=36&limit=3&username=di&word=hi",
+///         "prev": "/counter?page=1&limit=3&username=di&word=hi",
+///         "next": "/counter?page=3&limit=3&username=di&word=hi"
 ///     }
 /// }
 /// ```
 pub async fn get_all(
+    req: HttpRequest,
     query: web::Query<QueryParams>,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
     // Initialize the variables
     let SetQueryParams {
         page,
@@ -92,8 +177,31 @@ pub async fn get_all(
         order,
         username,
         word,
+        format,
+        algorithm,
+        exact,
+        raw,
     } = query.into_inner().into();
 
+    let similarity = string::algorithm(&algorithm)
+        .ok_or_else(|| error::ErrorBadRequest("Unknown match algorithm."))?;
+
+    let source = identity::source(raw);
+
+    // Search for the word the way it is stored, a word that is never stored can't match
+    let word = match word {
+        Some(w) => match state.normalizer.normalize(&w) {
+            Some(w) => Some(w),
+            None => {
+                return Ok(HttpResponse::BadRequest().json(errors::global::Generic {
+                    error: "BadRequest".to_string(),
+                    message: "`word` has nothing in it that is counted.".to_string(),
+                }));
+            }
+        },
+        None => None,
+    };
+
     // Connect to the database
     let conn = state
         .pool
@@ -102,27 +210,35 @@ pub async fn get_all(
 
     // Create the where clause for the queries
     let mut where_clauses = Vec::<String>::new();
+    let mut values = Vec::<String>::new();
 
-    // If there is a username, add username LIKE to the clause
-    if let Some(ref u) = username {
-        let username_like = u
-            .to_string()
-            .split("")
-            .into_iter()
-            .collect::<Vec<&str>>()
-            .join("%");
-        where_clauses.push(format!("username LIKE '{}'", username_like));
-    }
+    // Match the exact username and word when asked, or else narrow them down with the
+    // index and add a subsequence LIKE to the clause, binding every value
+    for (column, value) in [(index::USERNAME, &username), (index::WORD, &word)] {
+        let Some(v) = value else {
+            continue;
+        };
 
-    // If there is a word, add word LIKE to the clause
-    if let Some(ref w) = word {
-        let word_like = w
-            .to_string()
-            .split("")
-            .into_iter()
-            .collect::<Vec<&str>>()
-            .join("%");
-        where_clauses.push(format!("word LIKE '{}'", word_like));
+        if exact {
+            values.push(v.clone());
+            where_clauses.push(format!("{column} = ?{}", values.len()));
+            continue;
+        }
+
+        // Score every row when the algorithm can find what isn't a subsequence
+        if !similarity.subsequence() {
+            let (filter, bound) = index::similarity_filter(column, similarity, v, values.len() + 1);
+            values.extend(bound);
+            where_clauses.push(filter);
+            continue;
+        }
+
+        let (filter, grams) = index::subsequence_filter(column, v, values.len() + 1);
+        values.extend(grams);
+        where_clauses.push(filter);
+
+        values.push(index::subsequence_pattern(v));
+        where_clauses.push(format!("{column} LIKE ?{}", values.len()));
     }
 
     // Join the parts of the clause together
@@ -133,17 +249,45 @@ pub async fn get_all(
         where_clause = "WHERE ".to_owned() + where_clause.as_str();
     }
 
+    // Stream every matching row when exporting
+    let format = Format::negotiate(&req, &format);
+
+    if format != Format::Json {
+        let query = format!(
+            r#"
+                SELECT username, word, count
+                FROM {source}
+                {}
+                ORDER BY count {};
+            "#,
+            &where_clause, &order
+        );
+
+        return Ok(export::stream(
+            state.pool.clone(),
+            query,
+            values,
+            "counter",
+            &["username", "word", "count", "similarity"],
+            format,
+            move |row| Data::from_row(row, &username, &word, similarity),
+        ));
+    }
+
     // Format the query for the main data
     let query = format!(
         r#"
             SELECT username, word, count
-            FROM counter
+            FROM {source}
             {}
             ORDER BY count {}
-            LIMIT ?1
-            OFFSET ?2;
+            LIMIT ?{}
+            OFFSET ?{};
         "#,
-        &where_clause, &order
+        &where_clause,
+        &order,
+        values.len() + 1,
+        values.len() + 2
     );
 
     // Create the statement for the main data
@@ -153,9 +297,15 @@ pub async fn get_all(
 
     // Get the main data
     let mut items = stmt
-        .query_map([&limit, &((page - 1) * limit)], |row| {
-            Data::from_row(row, &username, &word)
-        })
+        .query_map(
+            params_from_iter(
+                values
+                    .iter()
+                    .map(|v| v as &dyn ToSql)
+                    .chain([&limit as &dyn ToSql, &((page - 1) * limit)]),
+            ),
+            |row| Data::from_row(row, &username, &word, similarity),
+        )
         .map_err(|e| error::ErrorInternalServerError(e.to_string() + "78"))?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| error::ErrorInternalServerError(e.to_string() + "80"))?;
@@ -174,7 +324,7 @@ pub async fn get_all(
     let query = format!(
         r#"
             SELECT COUNT(*) AS total_rows
-            FROM counter
+            FROM {source}
             {};
         "#,
         where_clause
@@ -187,63 +337,24 @@ pub async fn get_all(
 
     // Get the meta data
     let total_rows = stmt
-        .query_row([], |row| row.get::<usize, u32>(0))
+        .query_row(params_from_iter(&values), |row| row.get::<usize, u32>(0))
         .map_err(|e| error::ErrorInternalServerError(e.to_string() + "96"))?;
 
     // Format the response
-    let total_pages = total_rows.div_ceil(limit);
-    let has_next = page < total_pages;
-    let has_prev = page > 1;
-
-    let mut filter = String::new();
-
-    if let Some(ref u) = username {
-        filter += &format!("&username={u}");
-    }
-
-    if let Some(ref w) = word {
-        filter += &format!("&word={w}");
-    }
-
-    let links = Links {
-        own: format!("/counter?page={page}&limit={limit}&order={order}{filter}"),
-        first: format!("/counter?page=1&limit={limit}&order={order}{filter}"),
-        last: format!("/counter?page={total_pages}&limit={limit}&order={order}{filter}"),
-        next: if has_next {
-            Some(format!(
-                "/counter?page={}&limit={limit}&order={order}{filter}",
-                page + 1
-            ))
-        } else {
-            None
-        },
-        prev: if has_prev {
-            Some(format!(
-                "/counter?page={}&limit={limit}&order={order}{filter}",
-                page - 1
-            ))
-        } else {
-            None
-        },
-    };
+    let pagination = Pagination::new(page, limit, total_rows);
+    let links = Links::new(&req, &pagination);
 
     let meta = Meta {
-        pagination: Pagination {
-            page,
-            limit,
-            total_rows,
-            total_pages,
-            has_next,
-            has_prev,
-        },
+        pagination,
         filters: Filters { username, word },
         sort: Sort {
             by: "count".to_string(),
             order,
         },
+        algorithm: similarity.name().to_string(),
     };
 
-    Ok(HttpResponse::Ok().json(Response {
+    Ok(HttpResponse::Ok().json(Paginated {
         data: items,
         meta,
         links,
@@ -252,6 +363,9 @@ pub async fn get_all(
 
 /// Get all users with their total count
 ///
+/// Ranks are compared to the latest leaderboard snapshot, `previousRank` and `rankDelta` are
+/// `null` for rows that weren't in it and for windowed, stopword-excluded or raw leaderboards.
+///
 /// # Route
 /// `GET /counter/users`
 ///
@@ -259,11 +373,20 @@ pub async fn get_all(
 /// - `page`: The page number to get
 /// - `limit`: The amount of items to display per page
 /// - `order`: The order to return the results (asc|desc). Default `desc`
+/// - `ranking`: How tied counts are ranked (competition|dense). Default `competition`
+/// - `around`: The username whose page to return, overrides `page`
+/// - `format`: Export every row instead of a page (json|csv|ndjson). Also read from `Accept`
+/// - `excludeStopwords`: Leave the configured stopwords out of the totals (true|false). Default `false`
+/// - `window`: Only count usage within the last day, week, month or a custom range (24h|7d|30d|custom)
+/// - `from`: The first day of a `custom` window (YYYY-MM-DD)
+/// - `to`: The last day of a `custom` window (YYYY-MM-DD). Default today
 ///
 /// # Responses
-/// - `200 Ok`: Returns rows
+/// - `200 Ok`: Returns rows, or streams all rows as `text/csv` or `application/x-ndjson`
 /// - `400 Bad Request`: If invalid parameters
+/// - `404 Not Found`: If the `around` user has no counts
 /// - `500 Internal Server Error`: Server sided error
+/// - `503 Service Unavailable`: If too many exports are running
 ///
 /// # Example Request
 /// `GET /counter/users?page=1&limit=3&order=desc`
@@ -274,15 +397,24 @@ pub async fn get_all(
 ///     "data": [
 ///         {
 ///             "username": "adits87",
-///             "count": 5783
+///             "count": 5783,
+///             "rank": 1,
+///             "previousRank": 1,
+///             "rankDelta": 0
 ///         },
 ///         {
 ///             "username": "qa_z",
-///             "count": 3830
+///             "count": 3830,
+///             "rank": 2,
+///             "previousRank": 3,
+///             "rankDelta": 1
 ///         },
 ///         {
 ///             "username": "lilith_dysnomia",
-///             "count": 2501
+///             "count": 2501,
+///             "rank": 3,
+///             "previousRank": 2,
+///             "rankDelta": -1
 ///         }
 ///     ],
 ///     "meta": {
@@ -297,23 +429,64 @@ pub async fn get_all(
 ///         "sort": {
 ///             "by": "count",
 ///             "order": "desc"
-///         }
+///         },
+///         "window": null
 ///     },
 ///     "links": {
-///         "self": "/counter?page=1&limit=3",
-///         "first": "/counter?page=1&limit=3",
-///         "last": "/counter?page=2&limit=3",
+///         "self": "/counter/users?page=1&limit=3&order=desc",
+///         "first": "/counter/users?page=1&limit=3&order=desc",
+///         "last": "/counter/users?page=2&limit=3&order=desc",
 ///         "prev": null,
-///         "next": "/counter?page=2&limit=3"
+///         "next": "/counter/users?page=2&limit=3&order=desc"
 ///     }
 /// }
 /// ```
 pub async fn get_all_users(
-    query: web::Query<QueryPagination>,
+    req: HttpRequest,
+    query: web::Query<QueryLeaderboard>,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
     // Initialize the variables
-    let SetQueryPagination { page, limit, order } = query.into_inner().into();
+    let SetQueryLeaderboard {
+        mut page,
+        limit,
+        order,
+        ranking,
+        around,
+        format,
+        exclude_stopwords,
+        window,
+        from,
+        to,
+        raw,
+    } = query.into_inner().into();
+
+    // Rank the usage within the window instead of all time when there is one
+    let range = match window {
+        Some(ref w) => match time::window(w, from, to, Utc::now().naive_utc()) {
+            Some(range) => Some(range),
+            None => {
+                return Ok(HttpResponse::BadRequest().json(errors::global::Generic {
+                    error: "BadRequest".to_string(),
+                    message: "`window=custom` needs a `from` that is not after `to`.".to_string(),
+                }));
+            }
+        },
+        None => None,
+    };
+
+    let source = windowed_source(raw, range);
+
+    // Snapshots rank the resolved all-time totals, other leaderboards have nothing to compare to
+    let previous = match window.is_none() && !exclude_stopwords && !raw {
+        true => snapshots::previous_rank(snapshots::USER, "ranked.username", &ranking),
+        false => "NULL".to_string(),
+    };
 
     // Connect to the database
     let conn = state
@@ -321,90 +494,145 @@ pub async fn get_all_users(
         .get()
         .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
 
-    // Format the query for the main data
-    let query = format!(
+    // Rank every user over the whole table, ties are ordered by name so pages are stable
+    let ranked = format!(
         r#"
-            SELECT username, SUM(count) AS total
-            FROM counter
-            GROUP BY username
-            ORDER BY total {}
-            LIMIT ?1
-            OFFSET ?2;
+            WITH ranked AS (
+                SELECT
+                    username,
+                    SUM(count) AS total,
+                    {}() OVER (ORDER BY SUM(count) DESC) AS rank,
+                    ROW_NUMBER() OVER (ORDER BY SUM(count) {}, username ASC) AS position
+                FROM {source}
+                WHERE {}
+                GROUP BY username
+            )
         "#,
-        &order
+        rank_function(&ranking),
+        &order,
+        stopword_filter(exclude_stopwords)
     );
 
-    // Create the statement for the main data
-    let mut stmt = conn
-        .prepare(&query)
-        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+    // Stream the whole leaderboard when exporting
+    let format = Format::negotiate(&req, &format);
 
-    // Get the main data
-    let items = stmt
-        .query_map([limit, (page - 1) * limit], |row| UserData::from_row(row))
-        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+    if format != Format::Json {
+        return Ok(export::stream(
+            state.pool.clone(),
+            format!(
+                "{ranked} SELECT username, total, rank, {previous} AS previous_rank FROM ranked ORDER BY position;"
+            ),
+            Vec::new(),
+            "users",
+            &["rank", "username", "count", "previousRank", "rankDelta"],
+            format,
+            UserData::from_row,
+        ));
+    }
 
-    // Define the query for the meta data
-    let query = r#"
-        SELECT COUNT(DISTINCT(username)) AS total_rows
-        FROM counter;
-    "#;
+    // Jump to the page containing the user, or the identity the username belongs to
+    if let Some(ref a) = around {
+        let a = match raw {
+            true => a.to_owned(),
+            false => identity::resolve(&conn, a)
+                .map_err(|e| error::ErrorInternalServerError(e.to_string()))?,
+        };
 
-    // Create the statement for the meta data
-    let mut stmt = conn
-        .prepare(query)
-        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+        let position = conn
+            .query_row(
+                &format!("{ranked} SELECT position FROM ranked WHERE username = ?1;"),
+                [&a],
+                |row| row.get::<_, u32>("position"),
+            )
+            .optional()
+            .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
 
-    // Get the meta data
-    let total_rows = stmt
-        .query_row([], |row| row.get::<usize, u32>(0))
-        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+        match position {
+            Some(position) => page = (position - 1) / limit + 1,
+            None => {
+                return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+                    error: "NotFound".to_string(),
+                    message: "User not found.".to_string(),
+                }));
+            }
+        }
+    }
 
-    // Format the response
-    let total_pages = total_rows.div_ceil(limit);
-    let has_next = page < total_pages;
-    let has_prev = page > 1;
-
-    let links = Links {
-        own: format!("/counter?page={page}&limit={limit}&order={order}"),
-        first: format!("/counter?page=1&limit={limit}&order={order}"),
-        last: format!("/counter?page={total_pages}&limit={limit}&order={order}"),
-        next: if has_next {
-            Some(format!(
-                "/counter?page={}&limit={limit}&order={order}",
-                page + 1
-            ))
-        } else {
-            None
-        },
-        prev: if has_prev {
-            Some(format!(
-                "/counter?page={}&limit={limit}&order={order}",
-                page - 1
-            ))
-        } else {
-            None
-        },
+    // Get the page from the cache, building it when it is missing or stale
+    let key = format!("{page}:{limit}:{order}:{ranking}:{exclude_stopwords}:{raw}:{range:?}");
+    let version = state.counter_version.load(Ordering::Acquire);
+
+    let (items, total_rows) = match state.users_cache.get(&key, version) {
+        Some(page) => page,
+        None => {
+            // Format the query for the main data
+            let query = format!(
+                r#"
+                    {ranked}
+                    SELECT username, total, rank, {previous} AS previous_rank
+                    FROM ranked
+                    ORDER BY position
+                    LIMIT ?1
+                    OFFSET ?2;
+                "#
+            );
+
+            // Create the statement for the main data
+            let mut stmt = conn
+                .prepare(&query)
+                .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+            // Get the main data
+            let items = stmt
+                .query_map([limit, (page - 1) * limit], UserData::from_row)
+                .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+            // Format the query for the meta data
+            let query = format!(
+                r#"
+                    SELECT COUNT(DISTINCT(username)) AS total_rows
+                    FROM {source}
+                    WHERE {};
+                "#,
+                stopword_filter(exclude_stopwords)
+            );
+
+            // Create the statement for the meta data
+            let mut stmt = conn
+                .prepare(&query)
+                .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+            // Get the meta data
+            let total_rows = stmt
+                .query_row([], |row| row.get::<usize, u32>(0))
+                .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+            state
+                .users_cache
+                .insert(key, version, (items.clone(), total_rows));
+
+            (items, total_rows)
+        }
     };
 
-    let meta = PartMeta {
-        pagination: Pagination {
-            page,
-            limit,
-            total_rows,
-            total_pages,
-            has_next,
-            has_prev,
-        },
+    // Format the response
+    let pagination = Pagination::new(page, limit, total_rows);
+    let links = Links::new(&req, &pagination);
+
+    let meta = LeaderboardMeta {
+        pagination,
         sort: Sort {
             by: "count".to_string(),
             order,
         },
+        window: window
+            .zip(range)
+            .map(|(name, (from, to))| Window { name, from, to }),
     };
 
-    Ok(HttpResponse::Ok().json(UserResponse {
+    Ok(HttpResponse::Ok().json(Paginated {
         data: items,
         meta,
         links,
@@ -413,6 +641,9 @@ pub async fn get_all_users(
 
 /// Get all words with their total count
 ///
+/// Ranks are compared to the latest leaderboard snapshot, `previousRank` and `rankDelta` are
+/// `null` for rows that weren't in it and for windowed, stopword-excluded or raw leaderboards.
+///
 /// # Route
 /// `GET /counter/words`
 ///
@@ -420,14 +651,23 @@ pub async fn get_all_users(
 /// - `page`: The page number to get
 /// - `limit`: The amount of items to display per page
 /// - `order`: The order to return the results (asc|desc). Default `desc`
+/// - `ranking`: How tied counts are ranked (competition|dense). Default `competition`
+/// - `around`: The word whose page to return, overrides `page`
+/// - `format`: Export every row instead of a page (json|csv|ndjson). Also read from `Accept`
+/// - `excludeStopwords`: Leave the configured stopwords out of the totals (true|false). Default `false`
+/// - `window`: Only count usage within the last day, week, month or a custom range (24h|7d|30d|custom)
+/// - `from`: The first day of a `custom` window (YYYY-MM-DD)
+/// - `to`: The last day of a `custom` window (YYYY-MM-DD). Default today
 ///
 /// # Responses
-/// - `200 Ok`: Returns rows
+/// - `200 Ok`: Returns rows, or streams all rows as `text/csv` or `application/x-ndjson`
 /// - `400 Bad Request`: If invalid parameters
+/// - `404 Not Found`: If the `around` word has never been said
 /// - `500 Internal Server Error`: Server sided error
+/// - `503 Service Unavailable`: If too many exports are running
 ///
 /// # Example Request
-/// `GET /counter/words?page=2&limit=3&order=desc`
+/// `GET /counter/words?page=2&limit=3&order=desc&ranking=dense`
 ///
 /// # Example Response 200
 /// ```
@@ -435,15 +675,24 @@ pub async fn get_all_users(
 ///     "data": [
 ///         {
 ///             "word": "u",
-///             "count": 187
+///             "count": 187,
+///             "rank": 4,
+///             "previousRank": 4,
+///             "rankDelta": 0
 ///         },
 ///         {
 ///             "word": "a",
-///             "count": 185
+///             "count": 187,
+///             "rank": 4,
+///             "previousRank": 5,
+///             "rankDelta": 1
 ///         },
 ///         {
 ///             "word": "and",
-///             "count": 146
+///             "count": 146,
+///             "rank": 5,
+///             "previousRank": 4,
+///             "rankDelta": -1
 ///         }
 ///     ],
 ///     "meta": {
@@ -458,115 +707,1776 @@ pub async fn get_all_users(
 ///         "sort": {
 ///             "by": "count",
 ///             "order": "desc"
-///         }
+///         },
+///         "window": null
 ///     },
 ///     "links": {
-///         "self": "/counter?page=2&limit=3",
-///         "first": "/counter?page=1&limit=3",
-///         "last": "/counter?page=2&limit=3",
-///         "prev": "/counter?page=1&limit=3",
+///         "self": "/counter/words?page=2&limit=3&order=desc&ranking=dense",
+///         "first": "/counter/words?page=1&limit=3&order=desc&ranking=dense",
+///         "last": "/counter/words?page=2&limit=3&order=desc&ranking=dense",
+///         "prev": "/counter/words?page=1&limit=3&order=desc&ranking=dense",
 ///         "next": null
 ///     }
 /// }
 /// ```
 pub async fn get_all_words(
-    query: web::Query<QueryPagination>,
+    req: HttpRequest,
+    query: web::Query<QueryLeaderboard>,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
     // Initialize the variables
-    let SetQueryPagination { page, limit, order } = query.into_inner().into();
+    let SetQueryLeaderboard {
+        mut page,
+        limit,
+        order,
+        ranking,
+        around,
+        format,
+        exclude_stopwords,
+        window,
+        from,
+        to,
+        raw,
+    } = query.into_inner().into();
+
+    // Rank the usage within the window instead of all time when there is one
+    let range = match window {
+        Some(ref w) => match time::window(w, from, to, Utc::now().naive_utc()) {
+            Some(range) => Some(range),
+            None => {
+                return Ok(HttpResponse::BadRequest().json(errors::global::Generic {
+                    error: "BadRequest".to_string(),
+                    message: "`window=custom` needs a `from` that is not after `to`.".to_string(),
+                }));
+            }
+        },
+        None => None,
+    };
 
+    let source = windowed_source(raw, range);
+
+    // Snapshots rank the resolved all-time totals, other leaderboards have nothing to compare to
+    let previous = match window.is_none() && !exclude_stopwords && !raw {
+        true => snapshots::previous_rank(snapshots::WORD, "ranked.word", &ranking),
+        false => "NULL".to_string(),
+    };
+
+    // Connect to the database
     let conn = state
         .pool
         .get()
         .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
 
-    // Format the query for the main data
-    let query = format!(
+    // Rank every word over the whole table, ties are ordered by name so pages are stable
+    let ranked = format!(
         r#"
-            SELECT word, SUM(count) AS total
-            FROM counter
-            GROUP BY word
-            ORDER BY total {}
-            LIMIT ?1
-            OFFSET ?2;
+            WITH ranked AS (
+                SELECT
+                    word,
+                    SUM(count) AS total,
+                    {}() OVER (ORDER BY SUM(count) DESC) AS rank,
+                    ROW_NUMBER() OVER (ORDER BY SUM(count) {}, word ASC) AS position
+                FROM {source}
+                WHERE {}
+                GROUP BY word
+            )
         "#,
-        &order
+        rank_function(&ranking),
+        &order,
+        stopword_filter(exclude_stopwords)
     );
 
-    // Create the statement for the main data
-    let mut stmt = conn
-        .prepare(&query)
-        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+    // Stream the whole leaderboard when exporting
+    let format = Format::negotiate(&req, &format);
 
-    // Get the main data
-    let items = stmt
-        .query_map([limit, (page - 1) * limit], |row| WordData::from_row(row))
-        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+    if format != Format::Json {
+        return Ok(export::stream(
+            state.pool.clone(),
+            format!(
+                "{ranked} SELECT word, total, rank, {previous} AS previous_rank FROM ranked ORDER BY position;"
+            ),
+            Vec::new(),
+            "words",
+            &["rank", "word", "count", "previousRank", "rankDelta"],
+            format,
+            WordData::from_row,
+        ));
+    }
 
-    // Define the query for the meta data
-    let query = r#"
-        SELECT COUNT(DISTINCT(username)) AS total_rows
-        FROM counter;
-    "#;
+    // Jump to the page containing the word
+    if let Some(ref a) = around {
+        let position = conn
+            .query_row(
+                &format!("{ranked} SELECT position FROM ranked WHERE word = ?1;"),
+                [a],
+                |row| row.get::<_, u32>("position"),
+            )
+            .optional()
+            .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
 
-    // Create the statement for the meta data
-    let mut stmt = conn
-        .prepare(query)
-        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+        match position {
+            Some(position) => page = (position - 1) / limit + 1,
+            None => {
+                return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+                    error: "NotFound".to_string(),
+                    message: "Word not found.".to_string(),
+                }));
+            }
+        }
+    }
 
-    // Get the meta data
-    let total_rows = stmt
-        .query_row([], |row| row.get::<usize, u32>(0))
-        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+    // Get the page from the cache, building it when it is missing or stale
+    let key = format!("{page}:{limit}:{order}:{ranking}:{exclude_stopwords}:{raw}:{range:?}");
+    let version = state.counter_version.load(Ordering::Acquire);
 
-    // Format the response
-    let total_pages = total_rows.div_ceil(limit);
-    let has_next = page < total_pages;
-    let has_prev = page > 1;
-
-    let links = Links {
-        own: format!("/counter?page={page}&limit={limit}&order={order}"),
-        first: format!("/counter?page=1&limit={limit}&order={order}"),
-        last: format!("/counter?page={total_pages}&limit={limit}&order={order}"),
-        next: if has_next {
-            Some(format!(
-                "/counter?page={}&limit={limit}&order={order}",
-                page + 1
-            ))
-        } else {
-            None
-        },
-        prev: if has_prev {
-            Some(format!(
-                "/counter?page={}&limit={limit}&order={order}",
-                page - 1
-            ))
-        } else {
-            None
-        },
+    let (items, total_rows) = match state.words_cache.get(&key, version) {
+        Some(page) => page,
+        None => {
+            // Format the query for the main data
+            let query = format!(
+                r#"
+                    {ranked}
+                    SELECT word, total, rank, {previous} AS previous_rank
+                    FROM ranked
+                    ORDER BY position
+                    LIMIT ?1
+                    OFFSET ?2;
+                "#
+            );
+
+            // Create the statement for the main data
+            let mut stmt = conn
+                .prepare(&query)
+                .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+            // Get the main data
+            let items = stmt
+                .query_map([limit, (page - 1) * limit], WordData::from_row)
+                .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+            // Format the query for the meta data
+            let query = format!(
+                r#"
+                    SELECT COUNT(DISTINCT(word)) AS total_rows
+                    FROM {source}
+                    WHERE {};
+                "#,
+                stopword_filter(exclude_stopwords)
+            );
+
+            // Create the statement for the meta data
+            let mut stmt = conn
+                .prepare(&query)
+                .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+            // Get the meta data
+            let total_rows = stmt
+                .query_row([], |row| row.get::<usize, u32>(0))
+                .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+            state
+                .words_cache
+                .insert(key, version, (items.clone(), total_rows));
+
+            (items, total_rows)
+        }
     };
 
-    let meta = PartMeta {
-        pagination: Pagination {
-            page,
-            limit,
-            total_rows,
-            total_pages,
-            has_next,
-            has_prev,
-        },
+    // Format the response
+    let pagination = Pagination::new(page, limit, total_rows);
+    let links = Links::new(&req, &pagination);
+
+    let meta = LeaderboardMeta {
+        pagination,
         sort: Sort {
             by: "count".to_string(),
             order,
         },
+        window: window
+            .zip(range)
+            .map(|(name, (from, to))| Window { name, from, to }),
     };
 
-    Ok(HttpResponse::Ok().json(WordResponse {
+    Ok(HttpResponse::Ok().json(Paginated {
         data: items,
         meta,
         links,
     }))
 }
+
+/// Counts the words of a chat message, only admins may record counts
+///
+/// # Route
+/// `POST /counter`
+///
+/// # Request Body
+/// - `username`: The chat username that sent the message (3-32 chars)
+/// - `message`: The message to count the words of (1-2000 chars)
+///
+/// Messages over the anomaly limits are flagged for review, see `COUNTER_ANOMALY_POLICY` for
+/// what is counted of them.
+///
+/// # Responses
+/// - `201 Created`: Returns the counted words, with the id of the anomaly if it was flagged
+/// - `400 Bad Request`: If missing or invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `POST /counter`
+///
+/// # Example Request Body
+/// ```
+/// {
+///     "username": "adits87",
+///     "message": "hello world hello"
+/// }
+/// ```
+///
+/// # Example Response 201
+/// ```
+/// {
+///     "username": "adits87",
+///     "words": [
+///         {
+///             "word": "hello",
+///             "count": 2
+///         },
+///         {
+///             "word": "world",
+///             "count": 1
+///         }
+///     ],
+///     "anomalyId": null
+/// }
+/// ```
+pub async fn create(
+    body: web::Json<Message>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate body
+    body.validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Connect to the database
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Count the words, holding back what goes over the anomaly limits
+    let Ingested { words, anomaly_id } = services::anomalies::ingest(
+        &mut conn,
+        &state.normalizer,
+        &state.anomalies,
+        &body.username,
+        &body.message,
+        Utc::now().naive_utc(),
+    )
+    .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    state.counter_changed();
+
+    // The counts are saved either way, so failed events are only logged and failed
+    // milestone checks are retried by the milestone schedule
+    if let Err(e) = services::stream::publish(&state, &conn, &body.username, &words) {
+        eprintln!("Failed to stream the counts of a message: {e}");
+    }
+
+    let touched = words
+        .iter()
+        .map(|(word, _)| (body.username.to_owned(), word.to_owned()))
+        .collect::<Vec<_>>();
+
+    services::milestones::check(&mut conn, &touched);
+
+    Ok(HttpResponse::Created().json(IngestResponse {
+        username: body.username.as_str().to_owned(),
+        words: words
+            .into_iter()
+            .map(|(word, count)| IngestData { word, count })
+            .collect(),
+        anomaly_id,
+    }))
+}
+
+/// Stream counter changes as they happen, as Server-Sent Events
+///
+/// A `count` event is sent for every word a message adds to, and a `rank` event for every
+/// user whose leaderboard rank it changes. Idle connections get a `: ping` comment every 30
+/// seconds.
+///
+/// # Route
+/// `GET /counter/stream`
+///
+/// # Request Query
+/// - `username`: Only send events of this user. Default all users
+/// - `word`: Only send events of this word, leaving out rank events. Default all words
+///
+/// # Responses
+/// - `200 Ok`: Returns the event stream
+/// - `400 Bad Request`: If invalid parameters
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/stream?username=adits87`
+///
+/// # Example Response 200
+/// ```
+/// event: count
+/// data: {"username":"adits87","word":"hello","delta":2}
+///
+/// event: rank
+/// data: {"username":"adits87","rank":3,"previousRank":4}
+/// ```
+pub async fn get_stream(
+    query: web::Query<QueryStream>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let QueryStream { username, word } = query.into_inner();
+
+    let username = match username {
+        Some(username) => {
+            let conn = state
+                .pool
+                .get()
+                .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+            Some(
+                identity::resolve(&conn, &username)
+                    .map_err(|e| error::ErrorInternalServerError(e.to_string()))?,
+            )
+        }
+        None => None,
+    };
+
+    // Match the word the way it is stored
+    let word = word.map(|word| state.normalizer.normalize(&word).unwrap_or(word));
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(services::stream::subscribe(
+            state.events.subscribe(),
+            username,
+            word,
+        )))
+}
+
+/// Get the usage over time, zero-filled per interval
+///
+/// # Route
+/// `GET /counter/timeline`
+///
+/// # Request Query
+/// - `username`: The exact username to count the usage of. Default all users
+/// - `word`: The exact word to count the usage of. Default all words
+/// - `from`: The first day of the range (YYYY-MM-DD). Default 29 days before `to`
+/// - `to`: The last day of the range (YYYY-MM-DD). Default today
+/// - `interval`: The size of the buckets (day|week|month). Default `day`
+///
+/// # Responses
+/// - `200 Ok`: Returns the buckets
+/// - `400 Bad Request`: If invalid parameters, or more than 366 days, 260 weeks or 120 months
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/timeline?username=adits87&word=hello&from=2025-06-01&to=2025-06-16&interval=week`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": [
+///         {
+///             "bucket": "2025-05-26",
+///             "count": 4
+///         },
+///         {
+///             "bucket": "2025-06-02",
+///             "count": 0
+///         },
+///         {
+///             "bucket": "2025-06-09",
+///             "count": 12
+///         },
+///         {
+///             "bucket": "2025-06-16",
+///             "count": 1
+///         }
+///     ],
+///     "meta": {
+///         "filters": {
+///             "username": "adits87",
+///             "word": "hello"
+///         },
+///         "range": {
+///             "from": "2025-06-01",
+///             "to": "2025-06-16",
+///             "interval": "week"
+///         },
+///         "total": 17
+///     }
+/// }
+/// ```
+pub async fn get_timeline(
+    query: web::Query<QueryTimeline>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let SetQueryTimeline {
+        username,
+        word,
+        from,
+        to,
+        interval,
+        raw,
+    } = query.into_inner().into();
+
+    // Search for the word the way it is stored, a word that is never stored can't match
+    let word = match word {
+        Some(w) => match state.normalizer.normalize(&w) {
+            Some(w) => Some(w),
+            None => {
+                return Ok(HttpResponse::BadRequest().json(errors::global::Generic {
+                    error: "BadRequest".to_string(),
+                    message: "`word` has nothing in it that is counted.".to_string(),
+                }));
+            }
+        },
+        None => None,
+    };
+
+    if from > to {
+        return Ok(HttpResponse::BadRequest().json(errors::global::Generic {
+            error: "BadRequest".to_string(),
+            message: "`from` must not be after `to`.".to_string(),
+        }));
+    }
+
+    if time::bucket_count(from, to, &interval) > time::max_buckets(&interval) {
+        return Ok(HttpResponse::BadRequest().json(errors::global::Generic {
+            error: "BadRequest".to_string(),
+            message: format!(
+                "A timeline can span at most {} buckets of a {interval}.",
+                time::max_buckets(&interval)
+            ),
+        }));
+    }
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Create the where clause for the query, the range is [from, to + 1 day)
+    let mut where_clauses = vec!["hour >= ?1".to_string(), "hour < ?2".to_string()];
+    let mut values = vec![from.to_string(), (to + Days::new(1)).to_string()];
+
+    if let Some(ref u) = username {
+        values.push(match raw {
+            true => u.to_owned(),
+            false => identity::resolve(&conn, u)
+                .map_err(|e| error::ErrorInternalServerError(e.to_string()))?,
+        });
+        where_clauses.push(format!("username = ?{}", values.len()));
+    }
+
+    if let Some(ref w) = word {
+        values.push(w.to_owned());
+        where_clauses.push(format!("word = ?{}", values.len()));
+    }
+
+    // Format the query for the daily totals
+    let query = format!(
+        r#"
+            SELECT date(hour) AS day, SUM(count) AS total
+            FROM {}
+            WHERE {}
+            GROUP BY day;
+        "#,
+        identity::history_source(raw),
+        where_clauses.join(" AND ")
+    );
+
+    // Create the statement for the daily totals
+    let mut stmt = conn
+        .prepare(&query)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Zero-fill the buckets then add the daily totals to the bucket they fall in
+    let mut series: BTreeMap<_, u32> = time::buckets(from, to, &interval)
+        .into_iter()
+        .map(|bucket| (bucket, 0))
+        .collect();
+
+    let days = stmt
+        .query_map(params_from_iter(&values), |row| {
+            let day: String = row.get("day")?;
+            let day = NaiveDate::parse_from_str(&day, "%Y-%m-%d").map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e))
+            })?;
+
+            Ok((day, row.get::<_, u32>("total")?))
+        })
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    for (day, count) in days {
+        *series
+            .entry(time::bucket_start(day, &interval))
+            .or_default() += count;
+    }
+
+    // Format the response
+    let total = series.values().sum();
+
+    Ok(HttpResponse::Ok().json(TimelineResponse {
+        data: series
+            .into_iter()
+            .map(|(bucket, count)| TimelineData { bucket, count })
+            .collect(),
+        meta: TimelineMeta {
+            filters: Filters { username, word },
+            range: Range { from, to, interval },
+            total,
+        },
+    }))
+}
+
+/// Get the counter profile of a user
+///
+/// # Route
+/// `GET /counter/users/{username}`
+///
+/// # Request Query
+/// - `top`: The amount of most used words to return (1-100). Default `10`
+/// - `excludeStopwords`: Leave the configured stopwords out of the totals (true|false). Default `false`
+///
+/// # Responses
+/// - `200 Ok`: Returns the profile
+/// - `400 Bad Request`: If invalid parameters
+/// - `404 Not Found`: If the user has no counts
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/users/qa_z?top=2`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": {
+///         "username": "qa_z",
+///         "count": 3830,
+///         "vocabulary": 412,
+///         "rank": 2,
+///         "percentile": 75.0,
+///         "topWords": [
+///             {
+///                 "word": "the",
+///                 "count": 201,
+///                 "rank": 1,
+///                 "previousRank": null,
+///                 "rankDelta": null
+///             },
+///             {
+///                 "word": "u",
+///                 "count": 187,
+///                 "rank": 2,
+///                 "previousRank": null,
+///                 "rankDelta": null
+///             }
+///         ]
+///     },
+///     "links": {
+///         "self": "/counter/users/qa_z",
+///         "rows": "/counter?username=qa_z&exact=true"
+///     }
+/// }
+/// ```
+pub async fn get_user(
+    path: web::Path<UserPath>,
+    query: web::Query<QueryProfile>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate path and query
+    path.validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let top = query.top.unwrap_or(10);
+    let stopwords = stopword_filter(query.exclude_stopwords.unwrap_or(false));
+    let raw = query.raw.unwrap_or(false);
+    let source = identity::source(raw);
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Look up the identity the username belongs to
+    let username = match raw {
+        true => path.into_inner().username,
+        false => identity::resolve(&conn, &path.into_inner().username)
+            .map_err(|e| error::ErrorInternalServerError(e.to_string()))?,
+    };
+
+    // Get the total, rank and vocabulary of the user
+    let summary = conn
+        .query_row(
+            &format!(
+                r#"
+                WITH totals AS (
+                    SELECT username, SUM(count) AS total
+                    FROM {source}
+                    WHERE {stopwords}
+                    GROUP BY username
+                )
+                SELECT
+                    total,
+                    (SELECT COUNT(*) FROM totals AS t WHERE t.total > totals.total) + 1 AS rank,
+                    (SELECT COUNT(*) FROM totals) AS users,
+                    (
+                        SELECT COUNT(DISTINCT(word))
+                        FROM {source}
+                        WHERE username = ?1 AND count > 0 AND {stopwords}
+                    ) AS vocabulary
+                FROM totals
+                WHERE username = ?1;
+                "#
+            ),
+            [&username],
+            |row| {
+                Ok((
+                    row.get::<_, u32>("total")?,
+                    row.get::<_, u32>("rank")?,
+                    row.get::<_, u32>("users")?,
+                    row.get::<_, u32>("vocabulary")?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let Some((count, rank, users, vocabulary)) = summary else {
+        return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+            error: "NotFound".to_string(),
+            message: "User not found.".to_string(),
+        }));
+    };
+
+    // Create the statement for the most used words
+    let mut stmt = conn
+        .prepare(&format!(
+            r#"
+            SELECT word, count AS total, RANK() OVER (ORDER BY count DESC) AS rank
+            FROM {source}
+            WHERE username = ?1 AND {stopwords}
+            ORDER BY total DESC, word ASC
+            LIMIT ?2;
+            "#
+        ))
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the most used words
+    let top_words = stmt
+        .query_map(params![&username, top], WordData::from_row)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // The share of the other users that are ranked below this user
+    let percentile = if users > 1 {
+        (users - rank) as f32 / (users - 1) as f32 * 100.0
+    } else {
+        100.0
+    };
+
+    Ok(HttpResponse::Ok().json(ProfileResponse {
+        data: ProfileData {
+            username: username.as_str().to_owned(),
+            count,
+            vocabulary,
+            rank,
+            percentile,
+            top_words,
+        },
+        links: ProfileLinks {
+            own: format!("/counter/users/{username}"),
+            rows: match raw {
+                true => format!("/counter?username={username}&exact=true&raw=true"),
+                false => format!("/counter?username={username}&exact=true"),
+            },
+        },
+    }))
+}
+
+/// Get the details of a word with the users that said it the most
+///
+/// # Route
+/// `GET /counter/words/{word}`
+///
+/// # Request Query
+/// - `page`: The page number of users to get
+/// - `limit`: The amount of users to display per page
+/// - `order`: The order to return the users (asc|desc). Default `desc`
+///
+/// # Responses
+/// - `200 Ok`: Returns the word
+/// - `400 Bad Request`: If invalid parameters
+/// - `404 Not Found`: If the word has never been said
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/words/hello?page=1&limit=2`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": {
+///         "word": "hello",
+///         "count": 120,
+///         "users": 4,
+///         "rank": 7,
+///         "topUsers": [
+///             {
+///                 "username": "adits87",
+///                 "count": 60,
+///                 "share": 0.5
+///             },
+///             {
+///                 "username": "qa_z",
+///                 "count": 30,
+///                 "share": 0.25
+///             }
+///         ],
+///         "similar": [
+///             {
+///                 "word": "hell",
+///                 "count": 12,
+///                 "similarity": 0.85714287
+///             }
+///         ]
+///     },
+///     "meta": {
+///         "pagination": {
+///             "page": 1,
+///             "limit": 2,
+///             "totalRows": 4,
+///             "totalPages": 2,
+///             "hasNext": true,
+///             "hasPrev": false
+///         },
+///         "sort": {
+///             "by": "count",
+///             "order": "desc"
+///         }
+///     },
+///     "links": {
+///         "self": "/counter/words/hello?page=1&limit=2",
+///         "first": "/counter/words/hello?page=1&limit=2",
+///         "last": "/counter/words/hello?page=2&limit=2",
+///         "prev": null,
+///         "next": "/counter/words/hello?page=2&limit=2"
+///     }
+/// }
+/// ```
+pub async fn get_word(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<QueryPagination>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let SetQueryPagination {
+        page,
+        limit,
+        order,
+        raw,
+    } = query.into_inner().into();
+
+    let source = identity::source(raw);
+
+    // Look up the word the way it is stored
+    let Some(word) = state.normalizer.normalize(&path.into_inner()) else {
+        return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+            error: "NotFound".to_string(),
+            message: "Word not found.".to_string(),
+        }));
+    };
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the total, user count and rank of the word
+    let summary = conn
+        .query_row(
+            &format!(
+                r#"
+                WITH totals AS (
+                    SELECT word, SUM(count) AS total
+                    FROM {source}
+                    GROUP BY word
+                )
+                SELECT
+                    total,
+                    (SELECT COUNT(*) FROM totals AS t WHERE t.total > totals.total) + 1 AS rank,
+                    (
+                        SELECT COUNT(DISTINCT(username))
+                        FROM {source}
+                        WHERE word = ?1 AND count > 0
+                    ) AS users
+                FROM totals
+                WHERE word = ?1;
+                "#
+            ),
+            [&word],
+            |row| {
+                Ok((
+                    row.get::<_, u32>("total")?,
+                    row.get::<_, u32>("rank")?,
+                    row.get::<_, u32>("users")?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let Some((count, rank, users)) = summary else {
+        return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+            error: "NotFound".to_string(),
+            message: "Word not found.".to_string(),
+        }));
+    };
+
+    // Format the query for the users that said the word
+    let query = format!(
+        r#"
+            SELECT username, SUM(count) AS total
+            FROM {source}
+            WHERE word = ?1 AND count > 0
+            GROUP BY username
+            ORDER BY total {}, username ASC
+            LIMIT ?2
+            OFFSET ?3;
+        "#,
+        &order
+    );
+
+    // Create the statement for the users
+    let mut stmt = conn
+        .prepare(&query)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the users
+    let top_users = stmt
+        .query_map(params![&word, limit, (page - 1) * limit], |row| {
+            WordUserData::from_row(row, count)
+        })
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Create the statement for the words sharing a bigram with the word, as only they score above 0
+    let mut stmt = conn
+        .prepare(&format!(
+            r#"
+            SELECT word, SUM(count) AS total
+            FROM {source}
+            WHERE word != ?1 AND word IN (
+                SELECT term
+                FROM counter_grams
+                WHERE kind = 'word' AND LENGTH(gram) = 2 AND gram IN (
+                    SELECT gram
+                    FROM counter_grams
+                    WHERE kind = 'word' AND LENGTH(gram) = 2 AND term = ?1
+                )
+            )
+            GROUP BY word;
+            "#
+        ))
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Score the vocabulary against the word and keep the closest matches
+    let mut similar = stmt
+        .query_map([&word], |row| {
+            let other: String = row.get("word")?;
+            Ok(SimilarWordData {
+                similarity: string::similarity(&other, &word),
+                word: other,
+                count: row.get("total")?,
+            })
+        })
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .filter(|item| item.as_ref().map_or(true, |item| item.similarity > 0.0))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    similar.sort_by(|a, b| {
+        b.similarity
+            .partial_cmp(&a.similarity)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.count.cmp(&a.count))
+    });
+    similar.truncate(5);
+
+    // Format the response
+    let total_rows = users;
+    let pagination = Pagination::new(page, limit, total_rows);
+    let links = Links::new(&req, &pagination);
+
+    let meta = PartMeta {
+        pagination,
+        sort: Sort {
+            by: "count".to_string(),
+            order,
+        },
+    };
+
+    Ok(HttpResponse::Ok().json(WordDetailResponse {
+        data: WordDetailData {
+            word,
+            count,
+            users,
+            rank,
+            top_users,
+            similar,
+        },
+        meta,
+        links,
+    }))
+}
+
+/// Get the users that talk the most like a user
+///
+/// Users are compared by the cosine similarity of their word counts.
+///
+/// # Route
+/// `GET /counter/users/{username}/similar`
+///
+/// # Request Query
+/// - `limit`: The amount of users to return (1-100). Default `10`
+/// - `weighting`: How words are weighted (count|tfidf). `tfidf` lowers words everyone says. Default `count`
+///
+/// # Responses
+/// - `200 Ok`: Returns the most similar users
+/// - `400 Bad Request`: If invalid parameters
+/// - `404 Not Found`: If the user has no counts
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/users/adits87/similar?limit=2&weighting=tfidf`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": [
+///         {
+///             "username": "urkodi",
+///             "similarity": 0.61237246,
+///             "sharedWords": 154
+///         },
+///         {
+///             "username": "qa_z",
+///             "similarity": 0.4472136,
+///             "sharedWords": 98
+///         }
+///     ],
+///     "meta": {
+///         "username": "adits87",
+///         "weighting": "tfidf"
+///     }
+/// }
+/// ```
+pub async fn get_similar_users(
+    path: web::Path<String>,
+    query: web::Query<QuerySimilar>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let raw = query.raw.unwrap_or(false);
+    let limit = query.limit.unwrap_or(10) as usize;
+    let weighting = query.weighting.clone().unwrap_or("count".to_string());
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Look up the identity the username belongs to
+    let username = match raw {
+        true => path.into_inner(),
+        false => identity::resolve(&conn, &path.into_inner())
+            .map_err(|e| error::ErrorInternalServerError(e.to_string()))?,
+    };
+
+    // Get the word vectors
+    let vectors = services::vectors::get(&state, &conn, raw)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let Some(target) = vectors.vector(&username, &weighting) else {
+        return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+            error: "NotFound".to_string(),
+            message: "User not found.".to_string(),
+        }));
+    };
+
+    // Score every other user against the user
+    let mut items = vectors
+        .users
+        .keys()
+        .filter(|other| **other != username)
+        .filter_map(|other| {
+            let other_vector = vectors.vector(other, &weighting)?;
+
+            Some(SimilarUserData {
+                username: other.to_owned(),
+                similarity: vector::cosine(&target, &other_vector) as f32,
+                shared_words: target
+                    .keys()
+                    .filter(|word| other_vector.contains_key(*word))
+                    .count() as u32,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    items.sort_by(|a, b| {
+        b.similarity
+            .partial_cmp(&a.similarity)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.username.cmp(&b.username))
+    });
+    items.truncate(limit);
+
+    Ok(HttpResponse::Ok().json(SimilarUserResponse {
+        data: items,
+        meta: SimilarUserMeta {
+            username,
+            weighting,
+        },
+    }))
+}
+
+/// Get the words a user says much more often than everyone else
+///
+/// Words are scored by the share of the user's words they make up, divided by their
+/// add-one smoothed share of the words of every other user.
+///
+/// # Route
+/// `GET /counter/users/{username}/distinctive`
+///
+/// # Request Query
+/// - `limit`: The amount of words to return (1-100). Default `10`
+/// - `min`: The least amount of times the user must have said a word. Default `2`
+///
+/// # Responses
+/// - `200 Ok`: Returns the most distinctive words
+/// - `400 Bad Request`: If invalid parameters
+/// - `404 Not Found`: If the user has no counts
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/users/adits87/distinctive?limit=1`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": [
+///         {
+///             "word": "pog",
+///             "count": 42,
+///             "userShare": 0.0072626667,
+///             "populationShare": 0.00010516,
+///             "ratio": 69.06302
+///         }
+///     ],
+///     "meta": {
+///         "username": "adits87",
+///         "min": 2
+///     }
+/// }
+/// ```
+pub async fn get_distinctive_words(
+    path: web::Path<String>,
+    query: web::Query<QueryDistinctive>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let raw = query.raw.unwrap_or(false);
+    let limit = query.limit.unwrap_or(10) as usize;
+    let min = query.min.unwrap_or(2);
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Look up the identity the username belongs to
+    let username = match raw {
+        true => path.into_inner(),
+        false => identity::resolve(&conn, &path.into_inner())
+            .map_err(|e| error::ErrorInternalServerError(e.to_string()))?,
+    };
+
+    // Get the word vectors
+    let vectors = services::vectors::get(&state, &conn, raw)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let Some(counts) = vectors.users.get(&username) else {
+        return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+            error: "NotFound".to_string(),
+            message: "User not found.".to_string(),
+        }));
+    };
+
+    // Compare the user's share of each word with the rest of the users
+    let user_total = counts.values().map(|count| *count as f64).sum::<f64>();
+    let rest_total = vectors.total as f64 - user_total + vectors.words.len() as f64;
+
+    let mut items = counts
+        .iter()
+        .filter(|(_, count)| **count >= min)
+        .map(|(word, count)| {
+            let global = vectors.words.get(word).map_or(*count, |(total, _)| *total);
+            let user_share = *count as f64 / user_total;
+            let population_share = (global - count + 1) as f64 / rest_total;
+
+            DistinctiveData {
+                word: word.to_owned(),
+                count: *count,
+                user_share: user_share as f32,
+                population_share: population_share as f32,
+                ratio: (user_share / population_share) as f32,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    items.sort_by(|a, b| {
+        b.ratio
+            .partial_cmp(&a.ratio)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.count.cmp(&a.count))
+    });
+    items.truncate(limit);
+
+    Ok(HttpResponse::Ok().json(DistinctiveResponse {
+        data: items,
+        meta: DistinctiveMeta { username, min },
+    }))
+}
+
+/// Get the vocabulary statistics of every user together
+///
+/// # Route
+/// `GET /counter/stats`
+///
+/// # Responses
+/// - `200 Ok`: Returns the statistics
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/stats`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": {
+///         "total": 18425,
+///         "distinct": 2210,
+///         "typeTokenRatio": 0.11994573,
+///         "hapaxLegomena": 1187,
+///         "zipf": {
+///             "slope": -1.0832,
+///             "rSquared": 0.9714
+///         },
+///         "lengths": [
+///             {
+///                 "length": 1,
+///                 "words": 24,
+///                 "count": 1530
+///             },
+///             {
+///                 "length": 2,
+///                 "words": 180,
+///                 "count": 4120
+///             }
+///         ]
+///     },
+///     "meta": {
+///         "username": null
+///     }
+/// }
+/// ```
+pub async fn get_stats(
+    query: web::Query<QueryRaw>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the main data
+    let data = services::stats::get(&state, &conn, None, query.raw.unwrap_or(false))
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .ok_or_else(|| error::ErrorInternalServerError("Missing global statistics."))?;
+
+    Ok(HttpResponse::Ok().json(StatsResponse {
+        data,
+        meta: StatsMeta { username: None },
+    }))
+}
+
+/// Get the vocabulary statistics of a user
+///
+/// # Route
+/// `GET /counter/users/{username}/stats`
+///
+/// # Responses
+/// - `200 Ok`: Returns the statistics
+/// - `404 Not Found`: If the user has no counts
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/users/adits87/stats`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": {
+///         "total": 5783,
+///         "distinct": 802,
+///         "typeTokenRatio": 0.13868234,
+///         "hapaxLegomena": 431,
+///         "zipf": {
+///             "slope": -0.9921,
+///             "rSquared": 0.9602
+///         },
+///         "lengths": [
+///             {
+///                 "length": 1,
+///                 "words": 12,
+///                 "count": 420
+///             }
+///         ]
+///     },
+///     "meta": {
+///         "username": "adits87"
+///     }
+/// }
+/// ```
+pub async fn get_user_stats(
+    path: web::Path<String>,
+    query: web::Query<QueryRaw>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Initialize the variables
+    let raw = query.raw.unwrap_or(false);
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Look up the identity the username belongs to
+    let username = match raw {
+        true => path.into_inner(),
+        false => identity::resolve(&conn, &path.into_inner())
+            .map_err(|e| error::ErrorInternalServerError(e.to_string()))?,
+    };
+
+    // Get the main data
+    let Some(data) = services::stats::get(&state, &conn, Some(&username), raw)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+    else {
+        return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+            error: "NotFound".to_string(),
+            message: "User not found.".to_string(),
+        }));
+    };
+
+    Ok(HttpResponse::Ok().json(StatsResponse {
+        data,
+        meta: StatsMeta {
+            username: Some(username),
+        },
+    }))
+}
+
+/// Get the rank of a user in every leaderboard snapshot
+///
+/// # Route
+/// `GET /counter/users/{username}/history`
+///
+/// # Request Query
+/// - `from`: The first day to get snapshots of (YYYY-MM-DD). Default a year before `to`
+/// - `to`: The last day to get snapshots of (YYYY-MM-DD). Default today
+/// - `ranking`: How tied counts are ranked (competition|dense). Default `competition`
+///
+/// # Responses
+/// - `200 Ok`: Returns the snapshots the user was in, oldest first
+/// - `400 Bad Request`: If invalid parameters
+/// - `404 Not Found`: If the user has no counts
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/users/qa_z/history?from=2025-06-01&to=2025-06-02`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": [
+///         {
+///             "takenAt": "2025-06-01 00:00:12",
+///             "count": 3790,
+///             "rank": 3
+///         },
+///         {
+///             "takenAt": "2025-06-02 00:00:41",
+///             "count": 3830,
+///             "rank": 2
+///         }
+///     ],
+///     "meta": {
+///         "username": "qa_z",
+///         "from": "2025-06-01",
+///         "to": "2025-06-02",
+///         "ranking": "competition"
+///     }
+/// }
+/// ```
+pub async fn get_rank_history(
+    path: web::Path<String>,
+    query: web::Query<QueryRankHistory>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let SetQueryRankHistory { from, to, ranking } = query.into_inner().into();
+
+    if from > to {
+        return Ok(HttpResponse::BadRequest().json(errors::global::Generic {
+            error: "BadRequest".to_string(),
+            message: "`from` must not be after `to`.".to_string(),
+        }));
+    }
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Look up the identity the username belongs to
+    let username = identity::resolve(&conn, &path.into_inner())
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Only users that are shown now have their history shown
+    let shown = conn
+        .prepare_cached("SELECT 1 FROM counter_resolved WHERE username = ?1;")
+        .and_then(|mut stmt| stmt.exists([&username]))
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    if !shown {
+        return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+            error: "NotFound".to_string(),
+            message: "User not found.".to_string(),
+        }));
+    }
+
+    // Format the query for the main data, the range is [from, to + 1 day)
+    let query = format!(
+        r#"
+            SELECT taken_at, count, {} AS rank
+            FROM counter_snapshots
+            WHERE kind = ?1 AND name = ?2 AND taken_at >= ?3 AND taken_at < ?4
+            ORDER BY taken_at;
+        "#,
+        match ranking.as_str() {
+            "dense" => "dense_rank",
+            _ => "rank",
+        }
+    );
+
+    // Create the statement for the main data
+    let mut stmt = conn
+        .prepare(&query)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the main data
+    let data = stmt
+        .query_map(
+            params![
+                snapshots::USER,
+                &username,
+                from.to_string(),
+                (to + Days::new(1)).to_string()
+            ],
+            RankHistoryData::from_row,
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Format the response
+    Ok(HttpResponse::Ok().json(RankHistoryResponse {
+        data,
+        meta: RankHistoryMeta {
+            username,
+            from,
+            to,
+            ranking,
+        },
+    }))
+}
+
+/// Compare the words of two or three users head to head
+///
+/// The overlap coefficient is the amount of words every user has said, divided by the
+/// vocabulary of the user with the fewest distinct words.
+///
+/// # Route
+/// `GET /counter/compare`
+///
+/// # Request Query
+/// - `users`: The comma separated users to compare (2-3 users, 3-32 chars each)
+/// - `limit`: The amount of shared and unique words to return (1-100). Default `10`
+/// - `excludeStopwords`: Whether to leave out the configured stopwords. Default `false`
+///
+/// # Responses
+/// - `200 Ok`: Returns the comparison
+/// - `400 Bad Request`: If missing or invalid parameters
+/// - `404 Not Found`: If a user has no counts
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/compare?users=adits87,urkodi&limit=1`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": {
+///         "users": [
+///             {
+///                 "username": "adits87",
+///                 "total": 5783,
+///                 "vocabulary": 802,
+///                 "unique": [
+///                     {
+///                         "word": "pog",
+///                         "count": 42
+///                     }
+///                 ]
+///             },
+///             {
+///                 "username": "urkodi",
+///                 "total": 3120,
+///                 "vocabulary": 515,
+///                 "unique": [
+///                     {
+///                         "word": "bruh",
+///                         "count": 17
+///                     }
+///                 ]
+///             }
+///         ],
+///         "shared": [
+///             {
+///                 "word": "the",
+///                 "counts": {
+///                     "adits87": 212,
+///                     "urkodi": 148
+///                 }
+///             }
+///         ],
+///         "overlap": 0.4213592
+///     },
+///     "meta": {
+///         "users": ["adits87", "urkodi"],
+///         "shared": 217,
+///         "limit": 1
+///     }
+/// }
+/// ```
+pub async fn get_compare(
+    query: web::Query<QueryCompare>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let mut query: SetQueryCompare = query.into_inner().into();
+    let limit = query.limit as usize;
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Look up the identities the usernames belong to
+    if !query.raw {
+        query.users = query
+            .users
+            .iter()
+            .map(|u| identity::resolve(&conn, u))
+            .collect::<Result<_, _>>()
+            .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+    }
+
+    if (1..query.users.len()).any(|i| query.users[..i].contains(&query.users[i])) {
+        return Ok(HttpResponse::BadRequest().json(errors::global::Generic {
+            error: "BadRequest".to_string(),
+            message: "Users must be different.".to_string(),
+        }));
+    }
+
+    // Format the query for the main data
+    let placeholders = (1..=query.users.len())
+        .map(|i| format!("?{i}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let main_query = format!(
+        r#"
+        SELECT username, word, count
+        FROM {}
+        WHERE username IN ({placeholders}) AND count > 0 AND {};
+        "#,
+        identity::source(query.raw),
+        stopword_filter(query.exclude_stopwords),
+    );
+
+    // Get the main data
+    let mut counts: Vec<BTreeMap<String, u32>> = vec![BTreeMap::new(); query.users.len()];
+
+    conn.prepare(&main_query)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .query_map(params_from_iter(&query.users), |row| {
+            Ok((
+                row.get::<_, String>("username")?,
+                row.get::<_, String>("word")?,
+                row.get::<_, u32>("count")?,
+            ))
+        })
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .into_iter()
+        .for_each(|(username, word, count)| {
+            if let Some(i) = query.users.iter().position(|u| *u == username) {
+                counts[i].insert(word, count);
+            }
+        });
+
+    if counts.iter().any(BTreeMap::is_empty) {
+        return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+            error: "NotFound".to_string(),
+            message: "User not found.".to_string(),
+        }));
+    }
+
+    // Find the words every user has said, most used together first
+    let mut shared = counts[0]
+        .keys()
+        .filter(|word| counts.iter().all(|c| c.contains_key(*word)))
+        .map(|word| SharedWordData {
+            word: word.to_owned(),
+            counts: query
+                .users
+                .iter()
+                .zip(&counts)
+                .map(|(username, c)| (username.to_owned(), c[word]))
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+
+    let shared_total = shared.len() as u32;
+    let smallest = counts.iter().map(BTreeMap::len).min().unwrap_or(0);
+
+    shared.sort_by(|a, b| {
+        b.counts
+            .values()
+            .sum::<u32>()
+            .cmp(&a.counts.values().sum::<u32>())
+            .then(a.word.cmp(&b.word))
+    });
+    shared.truncate(limit);
+
+    // Find the words only one of the users has said
+    let users = query
+        .users
+        .iter()
+        .enumerate()
+        .map(|(i, username)| {
+            let mut unique = counts[i]
+                .iter()
+                .filter(|(word, _)| {
+                    counts
+                        .iter()
+                        .enumerate()
+                        .all(|(j, c)| i == j || !c.contains_key(*word))
+                })
+                .map(|(word, count)| CountData {
+                    word: word.to_owned(),
+                    count: *count,
+                })
+                .collect::<Vec<_>>();
+
+            unique.sort_by(|a, b| b.count.cmp(&a.count).then(a.word.cmp(&b.word)));
+            unique.truncate(limit);
+
+            CompareUserData {
+                username: username.to_owned(),
+                total: counts[i].values().sum(),
+                vocabulary: counts[i].len() as u32,
+                unique,
+            }
+        })
+        .collect();
+
+    // Format the response
+    Ok(HttpResponse::Ok().json(CompareResponse {
+        data: CompareData {
+            users,
+            shared,
+            overlap: shared_total as f32 / smallest as f32,
+        },
+        meta: CompareMeta {
+            users: query.users,
+            shared: shared_total,
+            limit: query.limit,
+        },
+    }))
+}
+
+/// Get the hit and miss counters of the leaderboard caches
+///
+/// # Route
+/// `GET /counter/admin/cache`
+///
+/// # Responses
+/// - `200 Ok`: Returns the cache counters
+/// - `403 Forbidden`: If the user is not an admin
+///
+/// # Example Request
+/// `GET /counter/admin/cache`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": [
+///         {
+///             "name": "users",
+///             "entries": 3,
+///             "hits": 120,
+///             "misses": 8,
+///             "hitRate": 0.9375
+///         },
+///         {
+///             "name": "words",
+///             "entries": 1,
+///             "hits": 14,
+///             "misses": 6,
+///             "hitRate": 0.7
+///         }
+///     ],
+///     "meta": {
+///         "ttl": 60,
+///         "capacity": 256,
+///         "version": 42
+///     }
+/// }
+/// ```
+pub async fn get_cache(state: web::Data<AppState>) -> Result<HttpResponse, Error> {
+    let data = [
+        (
+            "users",
+            state.users_cache.len(),
+            state.users_cache.hits(),
+            state.users_cache.misses(),
+        ),
+        (
+            "words",
+            state.words_cache.len(),
+            state.words_cache.hits(),
+            state.words_cache.misses(),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, entries, hits, misses)| CacheData {
+        name: name.to_string(),
+        entries: entries as u32,
+        hits,
+        misses,
+        hit_rate: if hits + misses > 0 {
+            hits as f32 / (hits + misses) as f32
+        } else {
+            0.0
+        },
+    })
+    .collect();
+
+    Ok(HttpResponse::Ok().json(CacheResponse {
+        data,
+        meta: CacheMeta {
+            ttl: state.users_cache.ttl().as_secs(),
+            capacity: state.users_cache.capacity() as u32,
+            version: state.counter_version.load(Ordering::Acquire),
+        },
+    }))
+}
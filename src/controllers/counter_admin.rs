@@ -0,0 +1,535 @@
+use crate::{
+    config::database::AppState,
+    controllers::counter_privacy::get_privacy,
+    dtos::{
+        errors,
+        requests::counter::{Alias, Identity, Name, Private, QueryPagination, SetQueryPagination},
+        responses::counter::{
+            AliasData, IdentityData, Links, MergeData, Paginated, Pagination, PartMeta, Sort,
+        },
+    },
+    services::{self, index},
+};
+
+use actix_web::{Error, HttpRequest, HttpResponse, error, web};
+use rusqlite::{Connection, OptionalExtension, params};
+use validator::Validate;
+
+fn get_identity(conn: &Connection, name: &str) -> Result<Option<IdentityData>, rusqlite::Error> {
+    conn.query_row(
+        r#"
+        SELECT
+            name,
+            user_id,
+            (
+                SELECT GROUP_CONCAT(alias)
+                FROM (SELECT alias FROM counter_aliases WHERE identity = name ORDER BY alias)
+            ) AS aliases
+        FROM counter_identities
+        WHERE name = ?1;
+        "#,
+        [name],
+        IdentityData::from_row,
+    )
+    .optional()
+}
+
+/// Get the identities that counter usernames are grouped under
+///
+/// # Route
+/// `GET /counter/admin/identities`
+///
+/// # Request Query
+/// - `page`: The page number to get
+/// - `limit`: The amount of items to display per page
+/// - `order`: The order of the names (asc|desc). Default `desc`
+///
+/// # Responses
+/// - `200 Ok`: Returns the identities with their aliases
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/admin/identities?limit=1&order=asc`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": [
+///         {
+///             "name": "adits87",
+///             "userId": 3,
+///             "aliases": ["adits", "adits_87"]
+///         }
+///     ],
+///     "meta": {
+///         "pagination": {
+///             "page": 1,
+///             "limit": 1,
+///             "totalRows": 2,
+///             "totalPages": 2,
+///             "hasNext": true,
+///             "hasPrev": false
+///         },
+///         "sort": {
+///             "by": "name",
+///             "order": "asc"
+///         }
+///     },
+///     "links": {
+///         "self": "/counter/admin/identities?page=1&limit=1&order=asc",
+///         "first": "/counter/admin/identities?page=1&limit=1&order=asc",
+///         "last": "/counter/admin/identities?page=2&limit=1&order=asc",
+///         "prev": null,
+///         "next": "/counter/admin/identities?page=2&limit=1&order=asc"
+///     }
+/// }
+/// ```
+pub async fn get_identities(
+    req: HttpRequest,
+    query: web::Query<QueryPagination>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let SetQueryPagination {
+        page, limit, order, ..
+    } = query.into_inner().into();
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Format the query for the main data
+    let query = format!(
+        r#"
+            SELECT
+                name,
+                user_id,
+                (
+                    SELECT GROUP_CONCAT(alias)
+                    FROM (SELECT alias FROM counter_aliases WHERE identity = name ORDER BY alias)
+                ) AS aliases
+            FROM counter_identities
+            ORDER BY name {}
+            LIMIT ?1
+            OFFSET ?2;
+        "#,
+        &order
+    );
+
+    // Get the main data
+    let items = conn
+        .prepare(&query)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .query_map([limit, (page - 1) * limit], IdentityData::from_row)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the meta data
+    let total_rows = conn
+        .query_row("SELECT COUNT(*) FROM counter_identities;", [], |row| {
+            row.get::<usize, u32>(0)
+        })
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Format the response
+    let pagination = Pagination::new(page, limit, total_rows);
+    let links = Links::new(&req, &pagination);
+
+    Ok(HttpResponse::Ok().json(Paginated {
+        data: items,
+        meta: PartMeta {
+            pagination,
+            sort: Sort {
+                by: "name".to_string(),
+                order,
+            },
+        },
+        links,
+    }))
+}
+
+/// Creates an identity, or changes the account it is linked to
+///
+/// # Route
+/// `PUT /counter/admin/identities/{name}`
+///
+/// # Request Body
+/// - `userId`: The id of the account the identity belongs to, if any
+///
+/// # Responses
+/// - `200 Ok`: Returns the identity
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `404 Not Found`: If the account doesn't exist
+/// - `409 Conflict`: If the account is linked to another identity
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `PUT /counter/admin/identities/adits87`
+///
+/// # Example Request Body
+/// ```
+/// {
+///     "userId": 3
+/// }
+/// ```
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "name": "adits87",
+///     "userId": 3,
+///     "aliases": []
+/// }
+/// ```
+pub async fn put_identity(
+    path: web::Path<Name>,
+    body: web::Json<Identity>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate path
+    path.validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    if let Some(user_id) = body.user_id {
+        // Check the account exists and isn't linked elsewhere
+        let user_exists = conn
+            .query_row("SELECT 1 FROM users WHERE id = ?1;", [user_id], |row| {
+                row.get::<usize, i64>(0)
+            })
+            .optional()
+            .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+            .is_some();
+
+        if !user_exists {
+            return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+                error: "NotFound".to_string(),
+                message: "User not found.".to_string(),
+            }));
+        }
+
+        let linked = conn
+            .query_row(
+                "SELECT 1 FROM counter_identities WHERE user_id = ?1 AND name != ?2;",
+                params![user_id, &path.name],
+                |row| row.get::<usize, i64>(0),
+            )
+            .optional()
+            .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+            .is_some();
+
+        if linked {
+            return Ok(HttpResponse::Conflict().json(errors::global::Generic {
+                error: "Conflict".to_string(),
+                message: "User is linked to another identity.".to_string(),
+            }));
+        }
+    }
+
+    conn.execute(
+        r#"
+        INSERT INTO counter_identities(name, user_id)
+        VALUES (?1, ?2)
+        ON CONFLICT(name) DO UPDATE SET user_id = excluded.user_id;
+        "#,
+        params![&path.name, body.user_id],
+    )
+    .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let identity = get_identity(&conn, &path.name)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .ok_or_else(|| error::ErrorInternalServerError("Missing identity."))?;
+
+    Ok(HttpResponse::Ok().json(identity))
+}
+
+/// Groups a counter username under an identity, creating the identity if needed
+///
+/// # Route
+/// `PUT /counter/admin/aliases/{name}`
+///
+/// # Request Body
+/// - `identity`: The name of the identity (3-32 chars)
+///
+/// # Responses
+/// - `200 Ok`: Returns the alias
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `409 Conflict`: If the alias would be chained to another alias
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `PUT /counter/admin/aliases/adits_87`
+///
+/// # Example Request Body
+/// ```
+/// {
+///     "identity": "adits87"
+/// }
+/// ```
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "alias": "adits_87",
+///     "identity": "adits87"
+/// }
+/// ```
+pub async fn put_alias(
+    path: web::Path<Name>,
+    body: web::Json<Alias>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate path and body
+    path.validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    body.validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Connect to the database
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Aliases resolve in one step, so an identity can't be an alias and an alias can't have aliases
+    let chained = conn
+        .query_row(
+            r#"
+            SELECT 1
+            FROM counter_aliases
+            WHERE (alias = ?2 AND identity != ?2) OR (identity = ?1 AND alias != ?1);
+            "#,
+            params![&path.name, &body.identity],
+            |row| row.get::<usize, i64>(0),
+        )
+        .optional()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .is_some();
+
+    if chained && path.name != body.identity {
+        return Ok(HttpResponse::Conflict().json(errors::global::Generic {
+            error: "Conflict".to_string(),
+            message: "Aliases can't be chained.".to_string(),
+        }));
+    }
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    tx.execute(
+        "INSERT OR IGNORE INTO counter_identities(name) VALUES (?1);",
+        [&body.identity],
+    )
+    .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    tx.execute(
+        r#"
+        INSERT INTO counter_aliases(alias, identity)
+        VALUES (?1, ?2)
+        ON CONFLICT(alias) DO UPDATE SET identity = excluded.identity;
+        "#,
+        params![&path.name, &body.identity],
+    )
+    .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Let the fuzzy search find the identity by name
+    index::add(&tx, index::USERNAME, &body.identity)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    tx.commit()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    state.counter_changed();
+
+    Ok(HttpResponse::Ok().json(AliasData {
+        alias: path.into_inner().name,
+        identity: body.into_inner().identity,
+    }))
+}
+
+/// Stops grouping a counter username under its identity
+///
+/// # Route
+/// `DELETE /counter/admin/aliases/{name}`
+///
+/// # Responses
+/// - `204 No Content`: If the alias was removed
+/// - `403 Forbidden`: If the user is not an admin
+/// - `404 Not Found`: If the username is not an alias
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `DELETE /counter/admin/aliases/adits_87`
+pub async fn delete_alias(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let deleted = conn
+        .execute(
+            "DELETE FROM counter_aliases WHERE alias = ?1;",
+            [path.into_inner()],
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    if deleted == 0 {
+        return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+            error: "NotFound".to_string(),
+            message: "Alias not found.".to_string(),
+        }));
+    }
+
+    state.counter_changed();
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Moves the counts of every alias of an identity onto the identity's own username
+///
+/// The rows are rewritten in a single transaction and the aliases are kept, so new
+/// counts under an old name still resolve.
+///
+/// # Route
+/// `POST /counter/admin/identities/{name}/merge`
+///
+/// # Responses
+/// - `200 Ok`: Returns the amount of rows that were moved
+/// - `403 Forbidden`: If the user is not an admin
+/// - `404 Not Found`: If the identity doesn't exist
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `POST /counter/admin/identities/adits87/merge`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "identity": "adits87",
+///     "moved": 214
+/// }
+/// ```
+pub async fn merge_identity(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Initialize the variables
+    let name = path.into_inner();
+
+    // Connect to the database
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let exists = get_identity(&conn, &name)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .is_some();
+
+    if !exists {
+        return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+            error: "NotFound".to_string(),
+            message: "Identity not found.".to_string(),
+        }));
+    }
+
+    let moved = services::identity::merge(&mut conn, &name)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    state.counter_changed();
+
+    Ok(HttpResponse::Ok().json(MergeData {
+        identity: name,
+        moved: moved as u32,
+    }))
+}
+
+/// Marks a counter username private, leaving it out of every count
+///
+/// # Route
+/// `PUT /counter/admin/privacy/{name}`
+///
+/// # Request Body
+/// - `private`: Whether the username is private
+///
+/// # Responses
+/// - `200 Ok`: Returns the privacy settings of the username
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `PUT /counter/admin/privacy/adits_87`
+///
+/// # Example Request Body
+/// ```
+/// {
+///     "private": true
+/// }
+/// ```
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "identity": "adits_87",
+///     "visibility": "visible",
+///     "label": null,
+///     "private": true,
+///     "hiddenWords": []
+/// }
+/// ```
+pub async fn put_private(
+    path: web::Path<Name>,
+    body: web::Json<Private>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate path
+    path.validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    conn.execute(
+        r#"
+        INSERT INTO counter_privacy(username, private)
+        VALUES (?1, ?2)
+        ON CONFLICT(username) DO UPDATE SET private = excluded.private;
+        "#,
+        params![&path.name, body.private],
+    )
+    .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    state.counter_changed();
+
+    let privacy = get_privacy(&conn, &path.name)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(privacy))
+}
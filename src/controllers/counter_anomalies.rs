@@ -0,0 +1,411 @@
+use crate::{
+    config::database::AppState,
+    controllers::counter_moderation::{actor, settle},
+    dtos::{
+        errors,
+        requests::counter::{QueryAnomalies, QueryReason, SetQueryAnomalies},
+        responses::counter::{AnomalyData, Links, Paginated, Pagination, PartMeta, Sort},
+    },
+    services::{anomalies, moderation::Action},
+};
+
+use actix_web::{Error, HttpRequest, HttpResponse, error, web};
+use rusqlite::{Connection, OptionalExtension, params};
+use validator::Validate;
+
+const ANOMALY_COLUMNS: &str = r#"
+    id,
+    username,
+    kinds,
+    details,
+    policy,
+    status,
+    hour,
+    created_at,
+    reviewed_by,
+    reviewed_at,
+    audit_id,
+    (
+        SELECT json_group_array(json_object('word', word, 'count', count))
+        FROM counter_anomaly_words
+        WHERE anomaly_id = counter_anomalies.id
+    ) AS words
+"#;
+
+fn get_anomaly(conn: &Connection, id: i64) -> Result<Option<AnomalyData>, rusqlite::Error> {
+    conn.query_row(
+        &format!("SELECT {ANOMALY_COLUMNS} FROM counter_anomalies WHERE id = ?1;"),
+        [id],
+        AnomalyData::from_row,
+    )
+    .optional()
+}
+
+fn anomaly_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(errors::global::Generic {
+        error: "NotFound".to_string(),
+        message: "Anomaly not found.".to_string(),
+    })
+}
+
+/// Approves or rejects a pending anomaly and responds with it
+async fn review(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    query: web::Query<QueryReason>,
+    state: web::Data<AppState>,
+    approve: bool,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Connect to the database
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let id = path.into_inner();
+
+    let Some(anomaly) =
+        get_anomaly(&conn, id).map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+    else {
+        return Ok(anomaly_not_found());
+    };
+
+    if anomaly.status != anomalies::PENDING {
+        return Ok(HttpResponse::Conflict().json(errors::global::Generic {
+            error: "Conflict".to_string(),
+            message: "Anomaly was already reviewed.".to_string(),
+        }));
+    }
+
+    let actor = actor(&req)?;
+    let action = Action {
+        name: if approve { "approve" } else { "reject" },
+        username: Some(&anomaly.username),
+        word: None,
+        reason: query.reason.as_deref(),
+        actor: &actor,
+    };
+
+    let audit_id = anomalies::review(&mut conn, &action, id, approve)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    if let Some(audit_id) = audit_id {
+        settle(&state, &mut conn, audit_id)?;
+    }
+
+    match get_anomaly(&conn, id).map_err(|e| error::ErrorInternalServerError(e.to_string()))? {
+        Some(anomaly) => Ok(HttpResponse::Ok().json(anomaly)),
+        None => Ok(anomaly_not_found()),
+    }
+}
+
+/// Get the messages ingest flagged as anomalies, newest first by default
+///
+/// # Route
+/// `GET /counter/admin/anomalies`
+///
+/// # Request Query
+/// - `page`: The page number to get
+/// - `limit`: The amount of items to display per page
+/// - `order`: The order of the ids (asc|desc). Default `desc`
+/// - `status`: The review status of the anomalies (pending|approved|rejected). Default `pending`
+/// - `username`: Only get the anomalies of this user
+///
+/// # Responses
+/// - `200 Ok`: Returns the anomalies with the usage they held back or logged
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/admin/anomalies?limit=1`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": [
+///         {
+///             "id": 7,
+///             "username": "adits87",
+///             "kinds": ["repeat"],
+///             "details": "\"pog\" was repeated 500 times, over the limit of 20",
+///             "policy": "cap",
+///             "status": "pending",
+///             "hour": "2025-06-04 13:00:00",
+///             "createdAt": "2025-06-04 13:45:12",
+///             "reviewedBy": null,
+///             "reviewedAt": null,
+///             "auditId": null,
+///             "words": [
+///                 {
+///                     "word": "pog",
+///                     "count": 480
+///                 }
+///             ]
+///         }
+///     ],
+///     "meta": {
+///         "pagination": {
+///             "page": 1,
+///             "limit": 1,
+///             "totalRows": 3,
+///             "totalPages": 3,
+///             "hasNext": true,
+///             "hasPrev": false
+///         },
+///         "sort": {
+///             "by": "id",
+///             "order": "desc"
+///         }
+///     },
+///     "links": {
+///         "self": "/counter/admin/anomalies?page=1&limit=1",
+///         "first": "/counter/admin/anomalies?page=1&limit=1",
+///         "last": "/counter/admin/anomalies?page=3&limit=1",
+///         "prev": null,
+///         "next": "/counter/admin/anomalies?page=2&limit=1"
+///     }
+/// }
+/// ```
+pub async fn get_anomalies(
+    req: HttpRequest,
+    query: web::Query<QueryAnomalies>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let SetQueryAnomalies {
+        page,
+        limit,
+        order,
+        status,
+        username,
+    } = query.into_inner().into();
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Format the query for the main data
+    let query = format!(
+        r#"
+            SELECT {ANOMALY_COLUMNS}
+            FROM counter_anomalies
+            WHERE status = ?1 AND (?2 IS NULL OR username = ?2)
+            ORDER BY id {}
+            LIMIT ?3
+            OFFSET ?4;
+        "#,
+        &order
+    );
+
+    // Get the main data
+    let items = conn
+        .prepare(&query)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .query_map(
+            params![&status, &username, limit, (page - 1) * limit],
+            AnomalyData::from_row,
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the meta data
+    let total_rows = conn
+        .query_row(
+            r#"
+            SELECT COUNT(*)
+            FROM counter_anomalies
+            WHERE status = ?1 AND (?2 IS NULL OR username = ?2);
+            "#,
+            params![&status, &username],
+            |row| row.get::<usize, u32>(0),
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Format the response
+    let pagination = Pagination::new(page, limit, total_rows);
+    let links = Links::new(&req, &pagination);
+
+    Ok(HttpResponse::Ok().json(Paginated {
+        data: items,
+        meta: PartMeta {
+            pagination,
+            sort: Sort {
+                by: "id".to_string(),
+                order,
+            },
+        },
+        links,
+    }))
+}
+
+/// Get an anomaly with the usage it held back or logged
+///
+/// # Route
+/// `GET /counter/admin/anomalies/{id}`
+///
+/// # Responses
+/// - `200 Ok`: Returns the anomaly
+/// - `403 Forbidden`: If the user is not an admin
+/// - `404 Not Found`: If the anomaly doesn't exist
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/admin/anomalies/7`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "id": 7,
+///     "username": "adits87",
+///     "kinds": ["repeat"],
+///     "details": "\"pog\" was repeated 500 times, over the limit of 20",
+///     "policy": "cap",
+///     "status": "pending",
+///     "hour": "2025-06-04 13:00:00",
+///     "createdAt": "2025-06-04 13:45:12",
+///     "reviewedBy": null,
+///     "reviewedAt": null,
+///     "auditId": null,
+///     "words": [
+///         {
+///             "word": "pog",
+///             "count": 480
+///         }
+///     ]
+/// }
+/// ```
+pub async fn get_anomaly_entry(
+    path: web::Path<i64>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    match get_anomaly(&conn, path.into_inner())
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+    {
+        Some(anomaly) => Ok(HttpResponse::Ok().json(anomaly)),
+        None => Ok(anomaly_not_found()),
+    }
+}
+
+/// Approves an anomaly, counting the usage it held back at the hour it was used
+///
+/// Logged usage was counted already, so approving it only closes the review.
+///
+/// # Route
+/// `POST /counter/admin/anomalies/{id}/approve`
+///
+/// # Request Query
+/// - `reason`: Why the anomaly is approved (1-256 chars)
+///
+/// # Responses
+/// - `200 Ok`: Returns the reviewed anomaly with the audit entry of the counts it added
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `404 Not Found`: If the anomaly doesn't exist
+/// - `409 Conflict`: If the anomaly was already reviewed
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `POST /counter/admin/anomalies/7/approve?reason=Hype`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "id": 7,
+///     "username": "adits87",
+///     "kinds": ["repeat"],
+///     "details": "\"pog\" was repeated 500 times, over the limit of 20",
+///     "policy": "cap",
+///     "status": "approved",
+///     "hour": "2025-06-04 13:00:00",
+///     "createdAt": "2025-06-04 13:45:12",
+///     "reviewedBy": "admin",
+///     "reviewedAt": "2025-06-04 14:02:40",
+///     "auditId": 12,
+///     "words": [
+///         {
+///             "word": "pog",
+///             "count": 480
+///         }
+///     ]
+/// }
+/// ```
+pub async fn approve_anomaly(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    query: web::Query<QueryReason>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    review(req, path, query, state, true).await
+}
+
+/// Rejects an anomaly, taking back the usage that was only logged
+///
+/// Held back usage was never counted, so rejecting it only closes the review.
+///
+/// # Route
+/// `POST /counter/admin/anomalies/{id}/reject`
+///
+/// # Request Query
+/// - `reason`: Why the anomaly is rejected (1-256 chars)
+///
+/// # Responses
+/// - `200 Ok`: Returns the reviewed anomaly with the audit entry of the counts it took back
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `404 Not Found`: If the anomaly doesn't exist
+/// - `409 Conflict`: If the anomaly was already reviewed
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `POST /counter/admin/anomalies/7/reject?reason=Spam`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "id": 7,
+///     "username": "adits87",
+///     "kinds": ["repeat"],
+///     "details": "\"pog\" was repeated 500 times, over the limit of 20",
+///     "policy": "cap",
+///     "status": "rejected",
+///     "hour": "2025-06-04 13:00:00",
+///     "createdAt": "2025-06-04 13:45:12",
+///     "reviewedBy": "admin",
+///     "reviewedAt": "2025-06-04 14:02:40",
+///     "auditId": null,
+///     "words": [
+///         {
+///             "word": "pog",
+///             "count": 480
+///         }
+///     ]
+/// }
+/// ```
+pub async fn reject_anomaly(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    query: web::Query<QueryReason>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    review(req, path, query, state, false).await
+}
@@ -0,0 +1,319 @@
+use crate::{
+    config::database::AppState,
+    controllers::counter::{rank_function, windowed_source},
+    dtos::{
+        errors,
+        requests::counter::{QueryEmoji, SetQueryEmoji},
+        responses::counter::{
+            EmojiData, LeaderboardMeta, Links, Paginated, Pagination, Sort, Window,
+        },
+    },
+    services::identity,
+    utils::time,
+};
+
+use actix_web::{Error, HttpRequest, HttpResponse, error, web};
+use chrono::Utc;
+use rusqlite::params;
+use validator::Validate;
+
+/// Ranks the emoji of everyone, or of one user when there is a username
+fn leaderboard(
+    req: HttpRequest,
+    query: web::Query<QueryEmoji>,
+    state: web::Data<AppState>,
+    username: Option<String>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let SetQueryEmoji {
+        page,
+        limit,
+        order,
+        ranking,
+        custom,
+        window,
+        from,
+        to,
+        raw,
+    } = query.into_inner().into();
+
+    // Rank the usage within the window instead of all time when there is one
+    let range = match window {
+        Some(ref w) => match time::window(w, from, to, Utc::now().naive_utc()) {
+            Some(range) => Some(range),
+            None => {
+                return Ok(HttpResponse::BadRequest().json(errors::global::Generic {
+                    error: "BadRequest".to_string(),
+                    message: "`window=custom` needs a `from` that is not after `to`.".to_string(),
+                }));
+            }
+        },
+        None => None,
+    };
+
+    let source = windowed_source(raw, range);
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Look up the identity the username belongs to
+    let username = match (username, raw) {
+        (Some(u), false) => Some(
+            identity::resolve(&conn, &u)
+                .map_err(|e| error::ErrorInternalServerError(e.to_string()))?,
+        ),
+        (username, _) => username,
+    };
+
+    // Only users that are shown have their emoji shown
+    if let Some(ref u) = username {
+        let shown = conn
+            .prepare_cached(&format!(
+                "SELECT 1 FROM {} WHERE username = ?1;",
+                identity::source(raw)
+            ))
+            .and_then(|mut stmt| stmt.exists([u]))
+            .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+        if !shown {
+            return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+                error: "NotFound".to_string(),
+                message: "User not found.".to_string(),
+            }));
+        }
+    }
+
+    // Rank every emoji over the whole table, ties are ordered by name so pages are stable
+    let ranked = format!(
+        r#"
+            WITH ranked AS (
+                SELECT
+                    word,
+                    SUM(count) AS total,
+                    {}() OVER (ORDER BY SUM(count) DESC) AS rank,
+                    ROW_NUMBER() OVER (ORDER BY SUM(count) {}, word ASC) AS position
+                FROM {source}
+                WHERE word IN (SELECT word FROM counter_emoji)
+                    AND (?1 IS NULL OR username = ?1)
+                    AND (?2 IS NULL OR (word LIKE '<%') = ?2)
+                GROUP BY word
+            )
+        "#,
+        rank_function(&ranking),
+        &order
+    );
+
+    // Get the meta data, with the usage every share is taken of
+    let (total_rows, emoji_total) = conn
+        .query_row(
+            &format!("{ranked} SELECT COUNT(*), COALESCE(SUM(total), 0) FROM ranked;"),
+            params![&username, custom],
+            |row| Ok((row.get::<usize, u32>(0)?, row.get::<usize, u32>(1)?)),
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Format the query for the main data
+    let query = format!(
+        r#"
+            {ranked}
+            SELECT word, total, rank
+            FROM ranked
+            ORDER BY position
+            LIMIT ?3
+            OFFSET ?4;
+        "#
+    );
+
+    // Create the statement for the main data
+    let mut stmt = conn
+        .prepare(&query)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the main data
+    let items = stmt
+        .query_map(
+            params![&username, custom, limit, (page - 1) * limit],
+            |row| EmojiData::from_row(row, emoji_total),
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Format the response
+    let pagination = Pagination::new(page, limit, total_rows);
+    let links = Links::new(&req, &pagination);
+
+    let meta = LeaderboardMeta {
+        pagination,
+        sort: Sort {
+            by: "count".to_string(),
+            order,
+        },
+        window: window
+            .zip(range)
+            .map(|(name, (from, to))| Window { name, from, to }),
+    };
+
+    Ok(HttpResponse::Ok().json(Paginated {
+        data: items,
+        meta,
+        links,
+    }))
+}
+
+/// Get all emoji and custom emotes with their total count
+///
+/// Emoji are counted as words too, this leaves everything else out.
+///
+/// # Route
+/// `GET /counter/emoji`
+///
+/// # Request Query
+/// - `page`: The page number to get
+/// - `limit`: The amount of items to display per page
+/// - `order`: The order to return the results (asc|desc). Default `desc`
+/// - `ranking`: How tied counts are ranked (competition|dense). Default `competition`
+/// - `custom`: Only get custom emotes, or only Unicode emoji (true|false)
+/// - `window`: Only count usage within the last day, week, month or a custom range (24h|7d|30d|custom)
+/// - `from`: The first day of a `custom` window (YYYY-MM-DD)
+/// - `to`: The last day of a `custom` window (YYYY-MM-DD). Default today
+///
+/// # Responses
+/// - `200 Ok`: Returns rows
+/// - `400 Bad Request`: If invalid parameters
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/emoji?limit=2`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": [
+///         {
+///             "emoji": "😂",
+///             "custom": false,
+///             "count": 214,
+///             "rank": 1,
+///             "share": 0.42885772
+///         },
+///         {
+///             "emoji": "<:pog:889203418823671808>",
+///             "custom": true,
+///             "count": 97,
+///             "rank": 2,
+///             "share": 0.19438878
+///         }
+///     ],
+///     "meta": {
+///         "pagination": {
+///             "page": 1,
+///             "limit": 2,
+///             "totalRows": 23,
+///             "totalPages": 12,
+///             "hasNext": true,
+///             "hasPrev": false
+///         },
+///         "sort": {
+///             "by": "count",
+///             "order": "desc"
+///         },
+///         "window": null
+///     },
+///     "links": {
+///         "self": "/counter/emoji?page=1&limit=2",
+///         "first": "/counter/emoji?page=1&limit=2",
+///         "last": "/counter/emoji?page=12&limit=2",
+///         "prev": null,
+///         "next": "/counter/emoji?page=2&limit=2"
+///     }
+/// }
+/// ```
+pub async fn get_all_emoji(
+    req: HttpRequest,
+    query: web::Query<QueryEmoji>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    leaderboard(req, query, state, None)
+}
+
+/// Get the emoji and custom emotes a user used with their count
+///
+/// # Route
+/// `GET /counter/users/{username}/emoji`
+///
+/// # Request Query
+/// - `page`: The page number to get
+/// - `limit`: The amount of items to display per page
+/// - `order`: The order to return the results (asc|desc). Default `desc`
+/// - `ranking`: How tied counts are ranked (competition|dense). Default `competition`
+/// - `custom`: Only get custom emotes, or only Unicode emoji (true|false)
+/// - `window`: Only count usage within the last day, week, month or a custom range (24h|7d|30d|custom)
+/// - `from`: The first day of a `custom` window (YYYY-MM-DD)
+/// - `to`: The last day of a `custom` window (YYYY-MM-DD). Default today
+///
+/// # Responses
+/// - `200 Ok`: Returns rows, with the share of the user's emoji each makes up
+/// - `400 Bad Request`: If invalid parameters
+/// - `404 Not Found`: If the user has no counts
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/users/adits87/emoji?limit=1&window=7d`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": [
+///         {
+///             "emoji": "👍🏽",
+///             "custom": false,
+///             "count": 31,
+///             "rank": 1,
+///             "share": 0.5535714
+///         }
+///     ],
+///     "meta": {
+///         "pagination": {
+///             "page": 1,
+///             "limit": 1,
+///             "totalRows": 6,
+///             "totalPages": 6,
+///             "hasNext": true,
+///             "hasPrev": false
+///         },
+///         "sort": {
+///             "by": "count",
+///             "order": "desc"
+///         },
+///         "window": {
+///             "name": "7d",
+///             "from": "2025-05-29T00:00:00",
+///             "to": "2025-06-05T00:00:00"
+///         }
+///     },
+///     "links": {
+///         "self": "/counter/users/adits87/emoji?page=1&limit=1&window=7d",
+///         "first": "/counter/users/adits87/emoji?page=1&limit=1&window=7d",
+///         "last": "/counter/users/adits87/emoji?page=6&limit=1&window=7d",
+///         "prev": null,
+///         "next": "/counter/users/adits87/emoji?page=2&limit=1&window=7d"
+///     }
+/// }
+/// ```
+pub async fn get_user_emoji(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<QueryEmoji>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    leaderboard(req, query, state, Some(path.into_inner()))
+}
@@ -0,0 +1,1010 @@
+use crate::{
+    config::database::AppState,
+    dtos::{
+        errors,
+        requests::counter::{
+            Adjustment, Blocklist, CountPath, Moderation, QueryPagination, QueryReason,
+            SetQueryPagination,
+        },
+        responses::counter::{
+            AuditData, AuditDetailData, ChangeData, Links, Paginated, Pagination, PartMeta, Sort,
+        },
+    },
+    middleware::authentication::Claims,
+    services::{
+        milestones,
+        moderation::{self, Action},
+        stream,
+    },
+};
+
+use actix_web::{Error, HttpMessage, HttpRequest, HttpResponse, error, web};
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::BTreeMap;
+use validator::Validate;
+
+const AUDIT_COLUMNS: &str = r#"
+    id,
+    action,
+    username,
+    word,
+    reason,
+    actor,
+    created_at,
+    undone_by,
+    (SELECT COUNT(*) FROM counter_audit_changes WHERE audit_id = counter_audit.id) AS changed
+"#;
+
+fn get_audit(conn: &Connection, id: i64) -> Result<Option<AuditDetailData>, rusqlite::Error> {
+    let Some(audit) = conn
+        .query_row(
+            &format!("SELECT {AUDIT_COLUMNS} FROM counter_audit WHERE id = ?1;"),
+            [id],
+            AuditData::from_row,
+        )
+        .optional()?
+    else {
+        return Ok(None);
+    };
+
+    let changes = conn
+        .prepare(
+            r#"
+            SELECT target, username, word, hour, before, after
+            FROM counter_audit_changes
+            WHERE audit_id = ?1
+            ORDER BY rowid;
+            "#,
+        )?
+        .query_map([id], ChangeData::from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Some(AuditDetailData { audit, changes }))
+}
+
+/// Gets the username of the admin making the request
+pub(crate) fn actor(req: &HttpRequest) -> Result<String, Error> {
+    req.extensions()
+        .get::<Claims>()
+        .map(|claims| claims.username.to_owned())
+        .ok_or_else(|| error::ErrorUnauthorized("No token."))
+}
+
+/// Refreshes what an audited action changed the counts under
+///
+/// Cached data is rebuilt, raised counts are streamed and can reach milestones while
+/// lowered ones can change the leader.
+pub(crate) fn settle(state: &AppState, conn: &mut Connection, id: i64) -> Result<(), Error> {
+    state.counter_changed();
+
+    let raised = conn
+        .prepare(
+            r#"
+            SELECT username, word, SUM(after - before) AS delta
+            FROM counter_audit_changes
+            WHERE audit_id = ?1 AND target = 'counter' AND after > before
+            GROUP BY username, word;
+            "#,
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .query_map([id], |row| {
+            Ok((
+                row.get::<_, String>("username")?,
+                row.get::<_, String>("word")?,
+                row.get::<_, u32>("delta")?,
+            ))
+        })
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let mut words = BTreeMap::<&str, Vec<(String, u32)>>::new();
+
+    for (username, word, delta) in &raised {
+        words
+            .entry(username)
+            .or_default()
+            .push((word.to_owned(), *delta));
+    }
+
+    // The counts are saved either way, so failed events are only logged
+    for (username, words) in &words {
+        if let Err(e) = stream::publish(state, conn, username, words) {
+            eprintln!("Failed to stream the counts of an audited action: {e}");
+        }
+    }
+
+    let touched = raised
+        .into_iter()
+        .map(|(username, word, _)| (username, word))
+        .collect::<Vec<_>>();
+
+    milestones::check(conn, &touched);
+
+    Ok(())
+}
+
+/// Responds with the audit entry of an action, or a 404 if the action changed nothing
+fn audited(
+    state: &AppState,
+    conn: &mut Connection,
+    id: Option<i64>,
+    message: &str,
+) -> Result<HttpResponse, Error> {
+    let Some(id) = id else {
+        return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+            error: "NotFound".to_string(),
+            message: message.to_string(),
+        }));
+    };
+
+    settle(state, conn, id)?;
+
+    let audit = get_audit(conn, id)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .ok_or_else(|| error::ErrorInternalServerError("Missing audit entry."))?;
+
+    Ok(HttpResponse::Ok().json(audit))
+}
+
+/// Get the audit log of the moderation actions, newest first by default
+///
+/// # Route
+/// `GET /counter/admin/audit`
+///
+/// # Request Query
+/// - `page`: The page number to get
+/// - `limit`: The amount of items to display per page
+/// - `order`: The order of the ids (asc|desc). Default `desc`
+///
+/// # Responses
+/// - `200 Ok`: Returns the audit entries with the amount of rows they changed
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/admin/audit?limit=1`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": [
+///         {
+///             "id": 4,
+///             "action": "delete",
+///             "username": "adits87",
+///             "word": "pineapple",
+///             "reason": "Spam",
+///             "actor": "admin",
+///             "createdAt": "2025-06-04 13:45:12",
+///             "undoneBy": null,
+///             "changed": 3
+///         }
+///     ],
+///     "meta": {
+///         "pagination": {
+///             "page": 1,
+///             "limit": 1,
+///             "totalRows": 4,
+///             "totalPages": 4,
+///             "hasNext": true,
+///             "hasPrev": false
+///         },
+///         "sort": {
+///             "by": "id",
+///             "order": "desc"
+///         }
+///     },
+///     "links": {
+///         "self": "/counter/admin/audit?page=1&limit=1",
+///         "first": "/counter/admin/audit?page=1&limit=1",
+///         "last": "/counter/admin/audit?page=4&limit=1",
+///         "prev": null,
+///         "next": "/counter/admin/audit?page=2&limit=1"
+///     }
+/// }
+/// ```
+pub async fn get_audit_log(
+    req: HttpRequest,
+    query: web::Query<QueryPagination>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let SetQueryPagination {
+        page, limit, order, ..
+    } = query.into_inner().into();
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Format the query for the main data
+    let query = format!(
+        r#"
+            SELECT {AUDIT_COLUMNS}
+            FROM counter_audit
+            ORDER BY id {}
+            LIMIT ?1
+            OFFSET ?2;
+        "#,
+        &order
+    );
+
+    // Get the main data
+    let items = conn
+        .prepare(&query)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .query_map([limit, (page - 1) * limit], AuditData::from_row)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the meta data
+    let total_rows = conn
+        .query_row("SELECT COUNT(*) FROM counter_audit;", [], |row| {
+            row.get::<usize, u32>(0)
+        })
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Format the response
+    let pagination = Pagination::new(page, limit, total_rows);
+    let links = Links::new(&req, &pagination);
+
+    Ok(HttpResponse::Ok().json(Paginated {
+        data: items,
+        meta: PartMeta {
+            pagination,
+            sort: Sort {
+                by: "id".to_string(),
+                order,
+            },
+        },
+        links,
+    }))
+}
+
+/// Get a moderation action with every row it changed
+///
+/// # Route
+/// `GET /counter/admin/audit/{id}`
+///
+/// # Responses
+/// - `200 Ok`: Returns the audit entry with its changes
+/// - `403 Forbidden`: If the user is not an admin
+/// - `404 Not Found`: If the audit entry doesn't exist
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/admin/audit/4`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "id": 4,
+///     "action": "delete",
+///     "username": "adits87",
+///     "word": "pineapple",
+///     "reason": "Spam",
+///     "actor": "admin",
+///     "createdAt": "2025-06-04 13:45:12",
+///     "undoneBy": null,
+///     "changed": 2,
+///     "changes": [
+///         {
+///             "target": "counter",
+///             "username": "adits87",
+///             "word": "pineapple",
+///             "hour": null,
+///             "before": 12,
+///             "after": 0
+///         },
+///         {
+///             "target": "counter_history",
+///             "username": "adits87",
+///             "word": "pineapple",
+///             "hour": "2025-06-04 13:00:00",
+///             "before": 12,
+///             "after": 0
+///         }
+///     ]
+/// }
+/// ```
+pub async fn get_audit_entry(
+    path: web::Path<i64>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    match get_audit(&conn, path.into_inner())
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+    {
+        Some(audit) => Ok(HttpResponse::Ok().json(audit)),
+        None => Ok(HttpResponse::NotFound().json(errors::global::Generic {
+            error: "NotFound".to_string(),
+            message: "Audit entry not found.".to_string(),
+        })),
+    }
+}
+
+/// Reverts a moderation action
+///
+/// Only what the action added or removed is taken back, so counts made since are kept.
+///
+/// # Route
+/// `POST /counter/admin/audit/{id}/undo`
+///
+/// # Request Query
+/// - `reason`: Why the action is undone (1-256 chars)
+///
+/// # Responses
+/// - `200 Ok`: Returns the audit entry of the undo
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `404 Not Found`: If the audit entry doesn't exist or there is nothing left to undo
+/// - `409 Conflict`: If the action was already undone
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `POST /counter/admin/audit/4/undo`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "id": 5,
+///     "action": "undo",
+///     "username": "adits87",
+///     "word": "pineapple",
+///     "reason": null,
+///     "actor": "admin",
+///     "createdAt": "2025-06-04 13:50:02",
+///     "undoneBy": null,
+///     "changed": 1,
+///     "changes": [
+///         {
+///             "target": "counter",
+///             "username": "adits87",
+///             "word": "pineapple",
+///             "hour": null,
+///             "before": 0,
+///             "after": 12
+///         }
+///     ]
+/// }
+/// ```
+pub async fn undo_audit(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    query: web::Query<QueryReason>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Connect to the database
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let id = path.into_inner();
+
+    let Some(audit) = get_audit(&conn, id)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .map(|audit| audit.audit)
+    else {
+        return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+            error: "NotFound".to_string(),
+            message: "Audit entry not found.".to_string(),
+        }));
+    };
+
+    if audit.undone_by.is_some() {
+        return Ok(HttpResponse::Conflict().json(errors::global::Generic {
+            error: "Conflict".to_string(),
+            message: "Audit entry was already undone.".to_string(),
+        }));
+    }
+
+    let actor = actor(&req)?;
+    let action = Action {
+        name: "undo",
+        username: audit.username.as_deref(),
+        word: audit.word.as_deref(),
+        reason: query.reason.as_deref(),
+        actor: &actor,
+    };
+
+    let undone = moderation::undo(&mut conn, &action, id)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    audited(&state, &mut conn, undone, "Nothing left to undo.")
+}
+
+/// Deletes the counts of a word and the phrases with it for a user or for everyone
+///
+/// # Route
+/// `DELETE /counter/admin/words/{word}`
+///
+/// # Request Query
+/// - `username`: The identity to delete the word for, with its aliases. Default everyone
+/// - `reason`: Why the word is deleted (1-256 chars)
+///
+/// # Responses
+/// - `200 Ok`: Returns the audit entry of the deletion
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `404 Not Found`: If the word has no counts to delete
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `DELETE /counter/admin/words/pineapple?username=adits87&reason=Spam`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "id": 4,
+///     "action": "delete",
+///     "username": "adits87",
+///     "word": "pineapple",
+///     "reason": "Spam",
+///     "actor": "admin",
+///     "createdAt": "2025-06-04 13:45:12",
+///     "undoneBy": null,
+///     "changed": 1,
+///     "changes": [
+///         {
+///             "target": "counter",
+///             "username": "adits87",
+///             "word": "pineapple",
+///             "hour": null,
+///             "before": 12,
+///             "after": 0
+///         }
+///     ]
+/// }
+/// ```
+pub async fn delete_word(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<Moderation>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Connect to the database
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Look up the word the way it is stored
+    let word = state.normalizer.normalize(&path.into_inner());
+    let actor = actor(&req)?;
+
+    let deleted = match &word {
+        Some(word) => moderation::delete(
+            &mut conn,
+            &Action {
+                name: "delete",
+                username: query.username.as_deref(),
+                word: Some(word),
+                reason: query.reason.as_deref(),
+                actor: &actor,
+            },
+            word,
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?,
+        None => None,
+    };
+
+    audited(&state, &mut conn, deleted, "Word not found.")
+}
+
+/// Replaces a word with `[redacted]` in its counts and phrases for a user or for everyone,
+/// keeping the totals
+///
+/// # Route
+/// `POST /counter/admin/words/{word}/redact`
+///
+/// # Request Body
+/// - `username`: The identity to redact the word for, with its aliases. Default everyone
+/// - `reason`: Why the word is redacted (1-256 chars)
+///
+/// # Responses
+/// - `200 Ok`: Returns the audit entry of the redaction
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `404 Not Found`: If the word has no counts to redact
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `POST /counter/admin/words/pineapple/redact`
+///
+/// # Example Request Body
+/// ```
+/// {
+///     "reason": "Personal information"
+/// }
+/// ```
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "id": 6,
+///     "action": "redact",
+///     "username": null,
+///     "word": "pineapple",
+///     "reason": "Personal information",
+///     "actor": "admin",
+///     "createdAt": "2025-06-04 14:02:40",
+///     "undoneBy": null,
+///     "changed": 2,
+///     "changes": [
+///         {
+///             "target": "counter",
+///             "username": "adits87",
+///             "word": "[redacted]",
+///             "hour": null,
+///             "before": 0,
+///             "after": 12
+///         },
+///         {
+///             "target": "counter",
+///             "username": "adits87",
+///             "word": "pineapple",
+///             "hour": null,
+///             "before": 12,
+///             "after": 0
+///         }
+///     ]
+/// }
+/// ```
+pub async fn redact_word(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<Moderation>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate body
+    body.validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Connect to the database
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Look up the word the way it is stored
+    let word = state.normalizer.normalize(&path.into_inner());
+    let actor = actor(&req)?;
+
+    let redacted = match &word {
+        Some(word) => moderation::redact(
+            &mut conn,
+            &Action {
+                name: "redact",
+                username: body.username.as_deref(),
+                word: Some(word),
+                reason: body.reason.as_deref(),
+                actor: &actor,
+            },
+            word,
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?,
+        None => None,
+    };
+
+    audited(&state, &mut conn, redacted, "Word not found.")
+}
+
+/// Adds to or takes from the count of a word for exactly one counter username, in total and
+/// in the current hour
+///
+/// # Route
+/// `PATCH /counter/admin/counts/{username}/{word}`
+///
+/// # Request Body
+/// - `delta`: The amount to add, negative to take away. Counts never go below zero
+/// - `reason`: Why the count is adjusted (1-256 chars)
+///
+/// # Responses
+/// - `200 Ok`: Returns the audit entry of the adjustment
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `404 Not Found`: If the adjustment changes nothing
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `PATCH /counter/admin/counts/adits87/hello`
+///
+/// # Example Request Body
+/// ```
+/// {
+///     "delta": -40,
+///     "reason": "Counted a pasted wall of text"
+/// }
+/// ```
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "id": 7,
+///     "action": "adjust",
+///     "username": "adits87",
+///     "word": "hello",
+///     "reason": "Counted a pasted wall of text",
+///     "actor": "admin",
+///     "createdAt": "2025-06-04 14:10:00",
+///     "undoneBy": null,
+///     "changed": 2,
+///     "changes": [
+///         {
+///             "target": "counter",
+///             "username": "adits87",
+///             "word": "hello",
+///             "hour": null,
+///             "before": 52,
+///             "after": 12
+///         },
+///         {
+///             "target": "counter_history",
+///             "username": "adits87",
+///             "word": "hello",
+///             "hour": "2025-06-04 14:00:00",
+///             "before": 45,
+///             "after": 5
+///         }
+///     ]
+/// }
+/// ```
+pub async fn adjust_count(
+    req: HttpRequest,
+    path: web::Path<CountPath>,
+    body: web::Json<Adjustment>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate path and body
+    path.validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    body.validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Adjust the word the way it is stored
+    let word = state
+        .normalizer
+        .normalize(&path.word)
+        .ok_or_else(|| error::ErrorBadRequest("Word is empty."))?;
+
+    // Connect to the database
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let actor = actor(&req)?;
+    let action = Action {
+        name: "adjust",
+        username: Some(&path.username),
+        word: Some(&word),
+        reason: Some(&body.reason),
+        actor: &actor,
+    };
+
+    let adjusted = moderation::adjust(
+        &mut conn,
+        &action,
+        &path.username,
+        &word,
+        body.delta,
+        Utc::now().naive_utc(),
+    )
+    .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    audited(&state, &mut conn, adjusted, "Count not found.")
+}
+
+/// Get the words that ingest skips
+///
+/// # Route
+/// `GET /counter/admin/blocklist`
+///
+/// # Request Query
+/// - `page`: The page number to get
+/// - `limit`: The amount of items to display per page
+/// - `order`: The order of the words (asc|desc). Default `desc`
+///
+/// # Responses
+/// - `200 Ok`: Returns the blocked words
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/admin/blocklist?order=asc`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": ["pineapple", "spam"],
+///     "meta": {
+///         "pagination": {
+///             "page": 1,
+///             "limit": 10,
+///             "totalRows": 2,
+///             "totalPages": 1,
+///             "hasNext": false,
+///             "hasPrev": false
+///         },
+///         "sort": {
+///             "by": "word",
+///             "order": "asc"
+///         }
+///     },
+///     "links": {
+///         "self": "/counter/admin/blocklist?page=1&limit=10&order=asc",
+///         "first": "/counter/admin/blocklist?page=1&limit=10&order=asc",
+///         "last": "/counter/admin/blocklist?page=1&limit=10&order=asc",
+///         "prev": null,
+///         "next": null
+///     }
+/// }
+/// ```
+pub async fn get_blocklist(
+    req: HttpRequest,
+    query: web::Query<QueryPagination>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let SetQueryPagination {
+        page, limit, order, ..
+    } = query.into_inner().into();
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the main data
+    let items = conn
+        .prepare(&format!(
+            "SELECT word FROM counter_blocklist ORDER BY word {order} LIMIT ?1 OFFSET ?2;"
+        ))
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .query_map([limit, (page - 1) * limit], |row| row.get::<_, String>(0))
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the meta data
+    let total_rows = conn
+        .query_row("SELECT COUNT(*) FROM counter_blocklist;", [], |row| {
+            row.get::<usize, u32>(0)
+        })
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Format the response
+    let pagination = Pagination::new(page, limit, total_rows);
+    let links = Links::new(&req, &pagination);
+
+    Ok(HttpResponse::Ok().json(Paginated {
+        data: items,
+        meta: PartMeta {
+            pagination,
+            sort: Sort {
+                by: "word".to_string(),
+                order,
+            },
+        },
+        links,
+    }))
+}
+
+/// Adds words to the blocklist so ingest skips them
+///
+/// Counts already made are kept, delete or redact the words to remove them.
+///
+/// # Route
+/// `PUT /counter/admin/blocklist`
+///
+/// # Request Body
+/// - `words`: The words to block (1-1000 words)
+/// - `reason`: Why the words are blocked (1-256 chars)
+///
+/// # Responses
+/// - `200 Ok`: Returns the audit entry of the newly blocked words
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `409 Conflict`: If every word is already blocked
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `PUT /counter/admin/blocklist`
+///
+/// # Example Request Body
+/// ```
+/// {
+///     "words": ["spam", "Pineapple!"],
+///     "reason": "Bot messages"
+/// }
+/// ```
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "id": 8,
+///     "action": "block",
+///     "username": null,
+///     "word": null,
+///     "reason": "Bot messages",
+///     "actor": "admin",
+///     "createdAt": "2025-06-04 14:20:00",
+///     "undoneBy": null,
+///     "changed": 2,
+///     "changes": [
+///         {
+///             "target": "counter_blocklist",
+///             "username": null,
+///             "word": "spam",
+///             "hour": null,
+///             "before": 0,
+///             "after": 1
+///         },
+///         {
+///             "target": "counter_blocklist",
+///             "username": null,
+///             "word": "pineapple",
+///             "hour": null,
+///             "before": 0,
+///             "after": 1
+///         }
+///     ]
+/// }
+/// ```
+pub async fn put_blocklist(
+    req: HttpRequest,
+    body: web::Json<Blocklist>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate body
+    body.validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Block the words the way they are stored
+    let words = body
+        .words
+        .iter()
+        .filter_map(|word| state.normalizer.normalize(word))
+        .collect::<Vec<_>>();
+
+    // Connect to the database
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let actor = actor(&req)?;
+    let action = Action {
+        name: "block",
+        username: None,
+        word: None,
+        reason: body.reason.as_deref(),
+        actor: &actor,
+    };
+
+    let Some(blocked) = moderation::block(&mut conn, &action, &words, true)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+    else {
+        return Ok(HttpResponse::Conflict().json(errors::global::Generic {
+            error: "Conflict".to_string(),
+            message: "Words are already blocked.".to_string(),
+        }));
+    };
+
+    audited(&state, &mut conn, Some(blocked), "Audit entry not found.")
+}
+
+/// Removes a word from the blocklist so ingest counts it again
+///
+/// # Route
+/// `DELETE /counter/admin/blocklist/{word}`
+///
+/// # Request Query
+/// - `reason`: Why the word is unblocked (1-256 chars)
+///
+/// # Responses
+/// - `200 Ok`: Returns the audit entry of the removal
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `404 Not Found`: If the word isn't blocked
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `DELETE /counter/admin/blocklist/spam`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "id": 9,
+///     "action": "unblock",
+///     "username": null,
+///     "word": "spam",
+///     "reason": null,
+///     "actor": "admin",
+///     "createdAt": "2025-06-04 14:25:00",
+///     "undoneBy": null,
+///     "changed": 1,
+///     "changes": [
+///         {
+///             "target": "counter_blocklist",
+///             "username": null,
+///             "word": "spam",
+///             "hour": null,
+///             "before": 1,
+///             "after": 0
+///         }
+///     ]
+/// }
+/// ```
+pub async fn delete_blocklist_word(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<QueryReason>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Connect to the database
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let words = state
+        .normalizer
+        .normalize(&path.into_inner())
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    let actor = actor(&req)?;
+    let action = Action {
+        name: "unblock",
+        username: None,
+        word: words.first().map(String::as_str),
+        reason: query.reason.as_deref(),
+        actor: &actor,
+    };
+
+    let unblocked = moderation::block(&mut conn, &action, &words, false)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    audited(&state, &mut conn, unblocked, "Word is not blocked.")
+}
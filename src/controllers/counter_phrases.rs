@@ -0,0 +1,470 @@
+use crate::{
+    config::database::AppState,
+    controllers::counter::rank_function,
+    dtos::{
+        errors,
+        requests::counter::{QueryPagination, QueryPhrases, SetQueryPagination, SetQueryPhrases},
+        responses::counter::{
+            Links, Paginated, Pagination, PartMeta, PhraseData, PhraseDetailData,
+            PhraseDetailResponse, PhraseFilters, PhraseMeta, Sort, WordUserData,
+        },
+    },
+    services::{identity, index},
+    utils::{normalize::Normalizer, string},
+};
+
+use actix_web::{Error, HttpRequest, HttpResponse, error, web};
+use rusqlite::{OptionalExtension, ToSql, params, params_from_iter};
+use validator::Validate;
+
+/// Normalizes every word of a phrase the way ingest stores it
+///
+/// Returns `None` when a word normalizes to nothing, as no stored phrase can hold it
+fn normalize_phrase(normalizer: &Normalizer, phrase: &str) -> Option<String> {
+    let words = phrase
+        .split_whitespace()
+        .map(|word| normalizer.normalize(word))
+        .collect::<Option<Vec<_>>>()?;
+
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+fn phrase_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(errors::global::Generic {
+        error: "NotFound".to_string(),
+        message: "Phrase not found.".to_string(),
+    })
+}
+
+/// Get all phrases with their total count
+///
+/// Phrases are only counted when `COUNTER_PHRASE_LENGTH` turns them on. Ranks are taken over
+/// every phrase of the `length` and `username`, before the fuzzy `phrase` filter.
+///
+/// # Route
+/// `GET /counter/phrases`
+///
+/// # Request Query
+/// - `page`: The page number to get
+/// - `limit`: The amount of items to display per page
+/// - `order`: The order to return the results (asc|desc). Default `desc`
+/// - `ranking`: How tied counts are ranked (competition|dense). Default `competition`
+/// - `phrase`: The phrase to look up with a fuzzy find
+/// - `match`: How `similarity` is scored (dice|levenshtein|damerau|jaro|trigram|phonetic). Default `dice`.
+///   `dice` finds the phrases holding every character in order, the others find the phrases scoring at least `0.5`
+/// - `length`: Only get the phrases of this many words (2-3)
+/// - `username`: Only count the phrases of this user
+///
+/// # Responses
+/// - `200 Ok`: Returns rows
+/// - `400 Bad Request`: If invalid parameters
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/phrases?limit=2&phrase=good%20mor`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": [
+///         {
+///             "phrase": "good morning",
+///             "length": 2,
+///             "count": 41,
+///             "rank": 3,
+///             "similarity": 0.8
+///         },
+///         {
+///             "phrase": "good morning chat",
+///             "length": 3,
+///             "count": 12,
+///             "rank": 17,
+///             "similarity": 0.6666667
+///         }
+///     ],
+///     "meta": {
+///         "pagination": {
+///             "page": 1,
+///             "limit": 2,
+///             "totalRows": 2,
+///             "totalPages": 1,
+///             "hasNext": false,
+///             "hasPrev": false
+///         },
+///         "filters": {
+///             "phrase": "good mor",
+///             "length": null,
+///             "username": null
+///         },
+///         "sort": {
+///             "by": "count",
+///             "order": "desc"
+///         },
+///         "match": "dice"
+///     },
+///     "links": {
+///         "self": "/counter/phrases?page=1&limit=2&phrase=good%20mor",
+///         "first": "/counter/phrases?page=1&limit=2&phrase=good%20mor",
+///         "last": "/counter/phrases?page=1&limit=2&phrase=good%20mor",
+///         "prev": null,
+///         "next": null
+///     }
+/// }
+/// ```
+pub async fn get_all_phrases(
+    req: HttpRequest,
+    query: web::Query<QueryPhrases>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let SetQueryPhrases {
+        page,
+        limit,
+        order,
+        ranking,
+        phrase,
+        algorithm,
+        length,
+        username,
+        raw,
+    } = query.into_inner().into();
+
+    let similarity = string::algorithm(&algorithm)
+        .ok_or_else(|| error::ErrorBadRequest("Unknown match algorithm."))?;
+
+    let source = identity::phrase_source(raw);
+
+    // Search for the phrase the way it is stored, a phrase that is never stored can't match
+    let phrase = match phrase {
+        Some(p) => match normalize_phrase(&state.normalizer, &p) {
+            Some(p) => Some(p),
+            None => {
+                return Ok(HttpResponse::BadRequest().json(errors::global::Generic {
+                    error: "BadRequest".to_string(),
+                    message: "`phrase` has a word in it that is not counted.".to_string(),
+                }));
+            }
+        },
+        None => None,
+    };
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // If there is a phrase, narrow it down with the index and add phrase LIKE to the clause,
+    // its values are bound after the length and username
+    let mut values = Vec::<String>::new();
+
+    let where_clause = match phrase {
+        // Score every phrase when the algorithm can find what isn't a subsequence
+        Some(ref p) if !similarity.subsequence() => {
+            let (filter, bound) = index::similarity_filter(index::PHRASE, similarity, p, 3);
+            values.extend(bound);
+
+            format!("WHERE {filter}")
+        }
+        Some(ref p) => {
+            let (filter, grams) = index::subsequence_filter(index::PHRASE, p, 3);
+            values.extend(grams);
+            values.push(index::subsequence_pattern(p));
+
+            format!("WHERE {filter} AND phrase LIKE ?{}", values.len() + 2)
+        }
+        None => String::new(),
+    };
+
+    let offset = (page - 1) * limit;
+    let bound = [&length as &dyn ToSql, &username]
+        .into_iter()
+        .chain(values.iter().map(|v| v as &dyn ToSql));
+
+    // Rank every phrase over the whole table, ties are ordered by name so pages are stable
+    let ranked = format!(
+        r#"
+            WITH ranked AS (
+                SELECT
+                    phrase,
+                    MAX(length) AS length,
+                    SUM(count) AS total,
+                    {}() OVER (ORDER BY SUM(count) DESC) AS rank,
+                    ROW_NUMBER() OVER (ORDER BY SUM(count) {}, phrase ASC) AS position
+                FROM {source}
+                WHERE (?1 IS NULL OR length = ?1) AND (?2 IS NULL OR username = ?2)
+                GROUP BY phrase
+            )
+        "#,
+        rank_function(&ranking),
+        &order
+    );
+
+    // Format the query for the main data
+    let query = format!(
+        r#"
+            {ranked}
+            SELECT phrase, length, total, rank
+            FROM ranked
+            {where_clause}
+            ORDER BY position
+            LIMIT ?{}
+            OFFSET ?{};
+        "#,
+        values.len() + 3,
+        values.len() + 4
+    );
+
+    // Create the statement for the main data
+    let mut stmt = conn
+        .prepare(&query)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the main data
+    let mut items = stmt
+        .query_map(
+            params_from_iter(bound.clone().chain([&limit as &dyn ToSql, &offset])),
+            |row| PhraseData::from_row(row, &phrase, similarity),
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Sort by similarity of found phrase to queried phrase
+    if phrase.is_some() {
+        items.sort_by(|a, b| {
+            b.similarity
+                .unwrap_or(0.0)
+                .partial_cmp(&a.similarity.unwrap_or(0.0))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+
+    // Get the meta data
+    let total_rows = conn
+        .query_row(
+            &format!("{ranked} SELECT COUNT(*) FROM ranked {where_clause};"),
+            params_from_iter(bound),
+            |row| row.get::<usize, u32>(0),
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Format the response
+    let pagination = Pagination::new(page, limit, total_rows);
+    let links = Links::new(&req, &pagination);
+
+    let meta = PhraseMeta {
+        pagination,
+        filters: PhraseFilters {
+            phrase,
+            length,
+            username,
+        },
+        sort: Sort {
+            by: "count".to_string(),
+            order,
+        },
+        algorithm: similarity.name().to_string(),
+    };
+
+    Ok(HttpResponse::Ok().json(Paginated {
+        data: items,
+        meta,
+        links,
+    }))
+}
+
+/// Get the details of a phrase with the users that said it the most
+///
+/// # Route
+/// `GET /counter/phrases/{phrase}`
+///
+/// # Request Query
+/// - `page`: The page number of users to get
+/// - `limit`: The amount of users to display per page
+/// - `order`: The order to return the users (asc|desc). Default `desc`
+///
+/// # Responses
+/// - `200 Ok`: Returns the phrase
+/// - `400 Bad Request`: If invalid parameters
+/// - `404 Not Found`: If the phrase has never been said
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/phrases/good%20morning?page=1&limit=2`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": {
+///         "phrase": "good morning",
+///         "length": 2,
+///         "count": 41,
+///         "users": 3,
+///         "rank": 3,
+///         "topUsers": [
+///             {
+///                 "username": "qa_z",
+///                 "count": 30,
+///                 "share": 0.73170733
+///             },
+///             {
+///                 "username": "adits87",
+///                 "count": 8,
+///                 "share": 0.19512194
+///             }
+///         ]
+///     },
+///     "meta": {
+///         "pagination": {
+///             "page": 1,
+///             "limit": 2,
+///             "totalRows": 3,
+///             "totalPages": 2,
+///             "hasNext": true,
+///             "hasPrev": false
+///         },
+///         "sort": {
+///             "by": "count",
+///             "order": "desc"
+///         }
+///     },
+///     "links": {
+///         "self": "/counter/phrases/good%20morning?page=1&limit=2",
+///         "first": "/counter/phrases/good%20morning?page=1&limit=2",
+///         "last": "/counter/phrases/good%20morning?page=2&limit=2",
+///         "prev": null,
+///         "next": "/counter/phrases/good%20morning?page=2&limit=2"
+///     }
+/// }
+/// ```
+pub async fn get_phrase(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<QueryPagination>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let SetQueryPagination {
+        page,
+        limit,
+        order,
+        raw,
+    } = query.into_inner().into();
+
+    let source = identity::phrase_source(raw);
+
+    // Look up the phrase the way it is stored
+    let Some(phrase) = normalize_phrase(&state.normalizer, &path.into_inner()) else {
+        return Ok(phrase_not_found());
+    };
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the total, user count and rank of the phrase
+    let summary = conn
+        .query_row(
+            &format!(
+                r#"
+                WITH totals AS (
+                    SELECT phrase, MAX(length) AS length, SUM(count) AS total
+                    FROM {source}
+                    GROUP BY phrase
+                )
+                SELECT
+                    length,
+                    total,
+                    (SELECT COUNT(*) FROM totals AS t WHERE t.total > totals.total) + 1 AS rank,
+                    (
+                        SELECT COUNT(DISTINCT(username))
+                        FROM {source}
+                        WHERE phrase = ?1 AND count > 0
+                    ) AS users
+                FROM totals
+                WHERE phrase = ?1;
+                "#
+            ),
+            [&phrase],
+            |row| {
+                Ok((
+                    row.get::<_, u32>("length")?,
+                    row.get::<_, u32>("total")?,
+                    row.get::<_, u32>("rank")?,
+                    row.get::<_, u32>("users")?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let Some((length, count, rank, users)) = summary else {
+        return Ok(phrase_not_found());
+    };
+
+    // Format the query for the users that said the phrase
+    let query = format!(
+        r#"
+            SELECT username, SUM(count) AS total
+            FROM {source}
+            WHERE phrase = ?1 AND count > 0
+            GROUP BY username
+            ORDER BY total {}, username ASC
+            LIMIT ?2
+            OFFSET ?3;
+        "#,
+        &order
+    );
+
+    // Create the statement for the users
+    let mut stmt = conn
+        .prepare(&query)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the users
+    let top_users = stmt
+        .query_map(params![&phrase, limit, (page - 1) * limit], |row| {
+            WordUserData::from_row(row, count)
+        })
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Format the response
+    let pagination = Pagination::new(page, limit, users);
+    let links = Links::new(&req, &pagination);
+
+    let meta = PartMeta {
+        pagination,
+        sort: Sort {
+            by: "count".to_string(),
+            order,
+        },
+    };
+
+    Ok(HttpResponse::Ok().json(PhraseDetailResponse {
+        data: PhraseDetailData {
+            phrase,
+            length,
+            count,
+            users,
+            rank,
+            top_users,
+        },
+        meta,
+        links,
+    }))
+}
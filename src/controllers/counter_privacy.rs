@@ -0,0 +1,288 @@
+use crate::{
+    config::database::AppState,
+    dtos::{errors, requests::counter::Privacy, responses::counter::PrivacyData},
+    middleware::authentication::Claims,
+    services::index,
+};
+
+use actix_web::{Error, HttpMessage, HttpRequest, HttpResponse, error, web};
+use rusqlite::{Connection, OptionalExtension, params};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Gets the privacy settings of an identity, with the defaults if it has none
+pub(crate) fn get_privacy(conn: &Connection, name: &str) -> Result<PrivacyData, rusqlite::Error> {
+    conn.query_row(
+        r#"
+        SELECT
+            ?1 AS identity,
+            COALESCE(counter_privacy.visibility, 'visible') AS visibility,
+            counter_privacy.label AS label,
+            COALESCE(counter_privacy.private, 0) AS private,
+            (
+                SELECT GROUP_CONCAT(word)
+                FROM (SELECT word FROM counter_hidden_words WHERE username = ?1 ORDER BY word)
+            ) AS hidden_words
+        FROM (SELECT 1)
+        LEFT JOIN counter_privacy ON counter_privacy.username = ?1;
+        "#,
+        [name],
+        PrivacyData::from_row,
+    )
+}
+
+/// Gets the counter identity linked to the account making the request
+fn linked_identity(req: &HttpRequest, conn: &Connection) -> Result<Option<String>, Error> {
+    let user_id = req
+        .extensions()
+        .get::<Claims>()
+        .map(|claims| claims.sub)
+        .ok_or_else(|| error::ErrorUnauthorized("No token."))?;
+
+    conn.query_row(
+        "SELECT name FROM counter_identities WHERE user_id = ?1;",
+        [user_id],
+        |row| row.get::<usize, String>(0),
+    )
+    .optional()
+    .map_err(|e| error::ErrorInternalServerError(e.to_string()))
+}
+
+fn not_linked() -> HttpResponse {
+    HttpResponse::NotFound().json(errors::global::Generic {
+        error: "NotFound".to_string(),
+        message: "No counter identity is linked to this account.".to_string(),
+    })
+}
+
+/// Get the privacy settings of the counter identity linked to the account
+///
+/// # Route
+/// `GET /counter/privacy`
+///
+/// # Responses
+/// - `200 Ok`: Returns the privacy settings
+/// - `404 Not Found`: If no identity is linked to the account
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/privacy`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "identity": "adits87",
+///     "visibility": "anonymous",
+///     "label": "anonymous-3f9c2a1b",
+///     "private": false,
+///     "hiddenWords": ["pineapple"]
+/// }
+/// ```
+pub async fn get_privacy_settings(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let Some(name) = linked_identity(&req, &conn)? else {
+        return Ok(not_linked());
+    };
+
+    let privacy =
+        get_privacy(&conn, &name).map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Let the fuzzy search find the identity by its label
+    if let Some(ref label) = privacy.label {
+        index::add(&conn, index::USERNAME, label)
+            .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+    }
+
+    Ok(HttpResponse::Ok().json(privacy))
+}
+
+/// Sets how the counter identity linked to the account is shown
+///
+/// # Route
+/// `PUT /counter/privacy`
+///
+/// # Request Body
+/// - `visibility`: Whether to show the identity, leave it out or show it under an anonymized label (visible|hidden|anonymous)
+///
+/// # Responses
+/// - `200 Ok`: Returns the privacy settings
+/// - `400 Bad Request`: If invalid parameters
+/// - `404 Not Found`: If no identity is linked to the account
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `PUT /counter/privacy`
+///
+/// # Example Request Body
+/// ```
+/// {
+///     "visibility": "anonymous"
+/// }
+/// ```
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "identity": "adits87",
+///     "visibility": "anonymous",
+///     "label": "anonymous-3f9c2a1b",
+///     "private": false,
+///     "hiddenWords": []
+/// }
+/// ```
+pub async fn put_privacy_settings(
+    req: HttpRequest,
+    body: web::Json<Privacy>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate body
+    body.validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let Some(name) = linked_identity(&req, &conn)? else {
+        return Ok(not_linked());
+    };
+
+    // The label is kept once made so an anonymized identity stays the same across changes
+    let label = format!("anonymous-{}", &Uuid::new_v4().simple().to_string()[..8]);
+
+    conn.execute(
+        r#"
+        INSERT INTO counter_privacy(username, visibility, label)
+        VALUES (?1, ?2, ?3)
+        ON CONFLICT(username) DO UPDATE SET
+            visibility = excluded.visibility,
+            label = COALESCE(counter_privacy.label, excluded.label);
+        "#,
+        params![&name, &body.visibility, label],
+    )
+    .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    state.counter_changed();
+
+    let privacy =
+        get_privacy(&conn, &name).map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(privacy))
+}
+
+/// Hides a word from the counts of the counter identity linked to the account
+///
+/// # Route
+/// `PUT /counter/privacy/words/{word}`
+///
+/// # Responses
+/// - `200 Ok`: Returns the privacy settings
+/// - `400 Bad Request`: If the word is empty once normalized
+/// - `404 Not Found`: If no identity is linked to the account
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `PUT /counter/privacy/words/pineapple`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "identity": "adits87",
+///     "visibility": "visible",
+///     "label": null,
+///     "private": false,
+///     "hiddenWords": ["pineapple"]
+/// }
+/// ```
+pub async fn put_hidden_word(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Hide the word the way it is stored
+    let word = state
+        .normalizer
+        .normalize(&path.into_inner())
+        .ok_or_else(|| error::ErrorBadRequest("Word is empty."))?;
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let Some(name) = linked_identity(&req, &conn)? else {
+        return Ok(not_linked());
+    };
+
+    conn.execute(
+        "INSERT OR IGNORE INTO counter_hidden_words(username, word) VALUES (?1, ?2);",
+        params![&name, &word],
+    )
+    .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    state.counter_changed();
+
+    let privacy =
+        get_privacy(&conn, &name).map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(privacy))
+}
+
+/// Shows a hidden word in the counts of the counter identity linked to the account again
+///
+/// # Route
+/// `DELETE /counter/privacy/words/{word}`
+///
+/// # Responses
+/// - `204 No Content`: If the word is shown again
+/// - `404 Not Found`: If no identity is linked to the account or the word isn't hidden
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `DELETE /counter/privacy/words/pineapple`
+pub async fn delete_hidden_word(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let Some(name) = linked_identity(&req, &conn)? else {
+        return Ok(not_linked());
+    };
+
+    let word = state.normalizer.normalize(&path.into_inner());
+
+    let deleted = conn
+        .execute(
+            "DELETE FROM counter_hidden_words WHERE username = ?1 AND word = ?2;",
+            params![&name, word],
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    if deleted == 0 {
+        return Ok(HttpResponse::NotFound().json(errors::global::Generic {
+            error: "NotFound".to_string(),
+            message: "Word is not hidden.".to_string(),
+        }));
+    }
+
+    state.counter_changed();
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,436 @@
+use crate::{
+    config::database::AppState,
+    dtos::{
+        errors,
+        requests::counter::{QueryPagination, Rule, SetQueryPagination},
+        responses::counter::{Links, Paginated, Pagination, PartMeta, RuleData, Sort},
+    },
+    middleware::authentication::Claims,
+    services::milestones,
+};
+
+use actix_web::{Error, HttpMessage, HttpRequest, HttpResponse, error, web};
+use rusqlite::{Connection, OptionalExtension, params};
+use validator::Validate;
+
+const RULE_COLUMNS: &str = r#"
+    id,
+    kind,
+    word,
+    threshold,
+    repeat,
+    enabled,
+    created_by,
+    created_at,
+    (SELECT COUNT(*) FROM counter_milestones WHERE rule_id = counter_rules.id) AS reached
+"#;
+
+fn get_rule(conn: &Connection, id: i64) -> Result<Option<RuleData>, rusqlite::Error> {
+    conn.query_row(
+        &format!("SELECT {RULE_COLUMNS} FROM counter_rules WHERE id = ?1;"),
+        [id],
+        RuleData::from_row,
+    )
+    .optional()
+}
+
+fn rule_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(errors::global::Generic {
+        error: "NotFound".to_string(),
+        message: "Rule not found.".to_string(),
+    })
+}
+
+/// Checks the body of a rule and fills in its defaults
+///
+/// Returns the word, threshold and whether the rule repeats, or the reason it is invalid
+fn settings(body: &Rule, state: &AppState) -> Result<(Option<String>, i64, bool), Error> {
+    body.validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let word = match (&body.word, body.kind.as_str()) {
+        (None, _) => None,
+        (Some(word), milestones::WORD) => Some(
+            state
+                .normalizer
+                .normalize(word)
+                .ok_or_else(|| error::ErrorBadRequest("Word is empty."))?,
+        ),
+        _ => return Err(error::ErrorBadRequest("Only word rules can have a word.")),
+    };
+
+    let threshold = body.threshold.unwrap_or(match body.kind.as_str() {
+        milestones::WORD => 100,
+        milestones::TOTAL => 1000,
+        _ => 1,
+    });
+
+    Ok((word, threshold, body.repeat.unwrap_or(false)))
+}
+
+/// Get the milestone rules
+///
+/// # Route
+/// `GET /counter/admin/rules`
+///
+/// # Request Query
+/// - `page`: The page number to get
+/// - `limit`: The amount of items to display per page
+/// - `order`: The order of the ids (asc|desc). Default `desc`
+///
+/// # Responses
+/// - `200 Ok`: Returns the rules with the amount of thresholds they reached
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `GET /counter/admin/rules?limit=1`
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "data": [
+///         {
+///             "id": 2,
+///             "kind": "total",
+///             "word": null,
+///             "threshold": 1000,
+///             "repeat": false,
+///             "enabled": true,
+///             "createdBy": 1,
+///             "createdAt": "2025-06-04 13:45:12",
+///             "reached": 4
+///         }
+///     ],
+///     "meta": {
+///         "pagination": {
+///             "page": 1,
+///             "limit": 1,
+///             "totalRows": 2,
+///             "totalPages": 2,
+///             "hasNext": true,
+///             "hasPrev": false
+///         },
+///         "sort": {
+///             "by": "id",
+///             "order": "desc"
+///         }
+///     },
+///     "links": {
+///         "self": "/counter/admin/rules?page=1&limit=1",
+///         "first": "/counter/admin/rules?page=1&limit=1",
+///         "last": "/counter/admin/rules?page=2&limit=1",
+///         "prev": null,
+///         "next": "/counter/admin/rules?page=2&limit=1"
+///     }
+/// }
+/// ```
+pub async fn get_rules(
+    req: HttpRequest,
+    query: web::Query<QueryPagination>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate query
+    query
+        .validate()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    // Initialize the variables
+    let SetQueryPagination {
+        page, limit, order, ..
+    } = query.into_inner().into();
+
+    // Connect to the database
+    let conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the main data
+    let items = conn
+        .prepare(&format!(
+            "SELECT {RULE_COLUMNS} FROM counter_rules ORDER BY id {order} LIMIT ?1 OFFSET ?2;"
+        ))
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .query_map([limit, (page - 1) * limit], RuleData::from_row)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Get the meta data
+    let total_rows = conn
+        .query_row("SELECT COUNT(*) FROM counter_rules;", [], |row| {
+            row.get::<usize, u32>(0)
+        })
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    // Format the response
+    let pagination = Pagination::new(page, limit, total_rows);
+    let links = Links::new(&req, &pagination);
+
+    Ok(HttpResponse::Ok().json(Paginated {
+        data: items,
+        meta: PartMeta {
+            pagination,
+            sort: Sort {
+                by: "id".to_string(),
+                order,
+            },
+        },
+        links,
+    }))
+}
+
+/// Creates a milestone rule that notifies users when their counts reach a threshold
+///
+/// Thresholds reached before the rule was made are recorded without notifying anyone, and a
+/// count passing several thresholds at once only notifies about the highest. Users without a
+/// linked account notify the admin who made the rule instead.
+///
+/// # Route
+/// `POST /counter/admin/rules`
+///
+/// # Request Body
+/// - `kind`: What reaches the threshold (word|total|leader)
+///     - `word`: A user's count of a word
+///     - `total`: A user's total words
+///     - `leader`: A new user taking #1 on the leaderboard, ignoring the threshold. Checked every minute
+/// - `word`: The only word a word rule counts. Default every word
+/// - `threshold`: The count to reach. Default `100` for word rules and `1000` for total rules
+/// - `repeat`: Whether every multiple of the threshold is reached too. Default `false`
+/// - `enabled`: Whether the rule is checked. Default `true`
+///
+/// # Responses
+/// - `201 Created`: Returns the rule
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `POST /counter/admin/rules`
+///
+/// # Example Request Body
+/// ```
+/// {
+///     "kind": "word",
+///     "threshold": 100,
+///     "repeat": true
+/// }
+/// ```
+///
+/// # Example Response 201
+/// ```
+/// {
+///     "id": 1,
+///     "kind": "word",
+///     "word": null,
+///     "threshold": 100,
+///     "repeat": true,
+///     "enabled": true,
+///     "createdBy": 1,
+///     "createdAt": "2025-06-04 13:45:12",
+///     "reached": 12
+/// }
+/// ```
+pub async fn create_rule(
+    req: HttpRequest,
+    body: web::Json<Rule>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate body
+    let (word, threshold, repeat) = settings(&body, &state)?;
+
+    let created_by = req
+        .extensions()
+        .get::<Claims>()
+        .map(|claims| claims.sub)
+        .ok_or_else(|| error::ErrorUnauthorized("No token."))?;
+
+    // Connect to the database
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    conn.execute(
+        r#"
+        INSERT INTO counter_rules(kind, word, threshold, repeat, enabled, created_by)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6);
+        "#,
+        params![
+            &body.kind,
+            word,
+            threshold,
+            repeat,
+            body.enabled.unwrap_or(true),
+            created_by
+        ],
+    )
+    .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let id = conn.last_insert_rowid();
+
+    let rule = conn
+        .query_row(
+            "SELECT * FROM counter_rules WHERE id = ?1;",
+            [id],
+            milestones::Rule::from_row,
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    milestones::seed(&mut conn, &rule)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let rule = get_rule(&conn, id)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .ok_or_else(|| error::ErrorInternalServerError("Missing rule."))?;
+
+    Ok(HttpResponse::Created().json(rule))
+}
+
+/// Changes a milestone rule
+///
+/// The thresholds it reached are recorded again for the new settings without notifying anyone.
+///
+/// # Route
+/// `PUT /counter/admin/rules/{id}`
+///
+/// # Request Body
+/// The same as `POST /counter/admin/rules`
+///
+/// # Responses
+/// - `200 Ok`: Returns the rule
+/// - `400 Bad Request`: If invalid parameters
+/// - `403 Forbidden`: If the user is not an admin
+/// - `404 Not Found`: If the rule doesn't exist
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `PUT /counter/admin/rules/1`
+///
+/// # Example Request Body
+/// ```
+/// {
+///     "kind": "word",
+///     "word": "hello",
+///     "threshold": 50,
+///     "enabled": false
+/// }
+/// ```
+///
+/// # Example Response 200
+/// ```
+/// {
+///     "id": 1,
+///     "kind": "word",
+///     "word": "hello",
+///     "threshold": 50,
+///     "repeat": false,
+///     "enabled": false,
+///     "createdBy": 1,
+///     "createdAt": "2025-06-04 13:45:12",
+///     "reached": 3
+/// }
+/// ```
+pub async fn put_rule(
+    path: web::Path<i64>,
+    body: web::Json<Rule>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Validate body
+    let (word, threshold, repeat) = settings(&body, &state)?;
+
+    // Connect to the database
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let id = path.into_inner();
+
+    let updated = conn
+        .execute(
+            r#"
+            UPDATE counter_rules
+            SET kind = ?2, word = ?3, threshold = ?4, repeat = ?5, enabled = ?6
+            WHERE id = ?1;
+            "#,
+            params![
+                id,
+                &body.kind,
+                word,
+                threshold,
+                repeat,
+                body.enabled.unwrap_or(true)
+            ],
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    if updated == 0 {
+        return Ok(rule_not_found());
+    }
+
+    let rule = conn
+        .query_row(
+            "SELECT * FROM counter_rules WHERE id = ?1;",
+            [id],
+            milestones::Rule::from_row,
+        )
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    milestones::seed(&mut conn, &rule)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let rule = get_rule(&conn, id)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?
+        .ok_or_else(|| error::ErrorInternalServerError("Missing rule."))?;
+
+    Ok(HttpResponse::Ok().json(rule))
+}
+
+/// Deletes a milestone rule with the thresholds it reached
+///
+/// # Route
+/// `DELETE /counter/admin/rules/{id}`
+///
+/// # Responses
+/// - `204 No Content`: If the rule was deleted
+/// - `403 Forbidden`: If the user is not an admin
+/// - `404 Not Found`: If the rule doesn't exist
+/// - `500 Internal Server Error`: Server sided error
+///
+/// # Example Request
+/// `DELETE /counter/admin/rules/1`
+pub async fn delete_rule(
+    path: web::Path<i64>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    // Connect to the database
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let id = path.into_inner();
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    let deleted = tx
+        .execute("DELETE FROM counter_rules WHERE id = ?1;", [id])
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    tx.execute("DELETE FROM counter_milestones WHERE rule_id = ?1;", [id])
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    tx.commit()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+
+    if deleted == 0 {
+        return Ok(rule_not_found());
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -1,5 +1,12 @@
 pub mod auth;
 pub mod counter;
+pub mod counter_admin;
+pub mod counter_anomalies;
+pub mod counter_emoji;
+pub mod counter_moderation;
+pub mod counter_phrases;
+pub mod counter_privacy;
+pub mod counter_rules;
 pub mod point;
 pub mod raspi;
 pub mod system;
@@ -0,0 +1,495 @@
+CREATE TABLE IF NOT EXISTS counter (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    username TEXT NOT NULL,
+    word TEXT NOT NULL,
+    count INTEGER NOT NULL DEFAULT 0
+);
+
+-- Merge the rows counted twice for the same user and word before they are made unique
+UPDATE counter
+SET count = (
+    SELECT SUM(duplicate.count)
+    FROM counter AS duplicate
+    WHERE duplicate.username = counter.username AND duplicate.word = counter.word
+)
+WHERE NOT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'counter_username_word_unique')
+    AND id IN (SELECT MIN(id) FROM counter GROUP BY username, word HAVING COUNT(*) > 1);
+
+DELETE FROM counter
+WHERE NOT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'counter_username_word_unique')
+    AND id NOT IN (SELECT MIN(id) FROM counter GROUP BY username, word);
+
+DROP INDEX IF EXISTS counter_username_word;
+
+CREATE UNIQUE INDEX IF NOT EXISTS counter_username_word_unique ON counter (username, word);
+
+CREATE INDEX IF NOT EXISTS counter_word ON counter (word);
+
+-- The count of every word per username, kept in sync by the triggers below so ranks are
+-- read one row per user instead of summing every count
+CREATE TABLE IF NOT EXISTS counter_totals (
+    username TEXT PRIMARY KEY,
+    total INTEGER NOT NULL DEFAULT 0
+) WITHOUT ROWID;
+
+-- Sum the counts recorded before the totals existed
+INSERT INTO counter_totals(username, total)
+SELECT username, SUM(count)
+FROM counter
+WHERE NOT EXISTS (SELECT 1 FROM counter_totals)
+GROUP BY username;
+
+CREATE TRIGGER IF NOT EXISTS counter_totals_insert AFTER INSERT ON counter
+BEGIN
+    INSERT INTO counter_totals(username, total)
+    VALUES (NEW.username, NEW.count)
+    ON CONFLICT(username) DO UPDATE SET total = total + excluded.total;
+END;
+
+CREATE TRIGGER IF NOT EXISTS counter_totals_update AFTER UPDATE OF username, count ON counter
+BEGIN
+    UPDATE counter_totals
+    SET total = total - OLD.count
+    WHERE username = OLD.username;
+
+    INSERT INTO counter_totals(username, total)
+    VALUES (NEW.username, NEW.count)
+    ON CONFLICT(username) DO UPDATE SET total = total + excluded.total;
+
+    DELETE FROM counter_totals
+    WHERE username = OLD.username
+        AND NOT EXISTS (SELECT 1 FROM counter WHERE counter.username = OLD.username);
+END;
+
+CREATE TRIGGER IF NOT EXISTS counter_totals_delete AFTER DELETE ON counter
+BEGIN
+    UPDATE counter_totals
+    SET total = total - OLD.count
+    WHERE username = OLD.username;
+
+    DELETE FROM counter_totals
+    WHERE username = OLD.username
+        AND NOT EXISTS (SELECT 1 FROM counter WHERE counter.username = OLD.username);
+END;
+
+-- Counts written by the maintenance commands, which the running server reads to rebuild
+-- what it cached and to send the added counts to the live streams
+CREATE TABLE IF NOT EXISTS counter_changes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    username TEXT,
+    word TEXT,
+    delta INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS counter_history (
+    username TEXT NOT NULL,
+    word TEXT NOT NULL,
+    hour TEXT NOT NULL,
+    count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (username, word, hour)
+);
+
+CREATE INDEX IF NOT EXISTS counter_history_hour ON counter_history (hour);
+
+-- The history rolled up per day, kept in sync by the triggers below so windows of days
+-- read one row per day instead of 24
+CREATE TABLE IF NOT EXISTS counter_daily (
+    username TEXT NOT NULL,
+    word TEXT NOT NULL,
+    day TEXT NOT NULL,
+    count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (username, word, day)
+) WITHOUT ROWID;
+
+CREATE INDEX IF NOT EXISTS counter_daily_day ON counter_daily (day);
+
+-- Roll up the history recorded before the rollups existed
+INSERT INTO counter_daily(username, word, day, count)
+SELECT username, word, date(hour), SUM(count)
+FROM counter_history
+WHERE NOT EXISTS (SELECT 1 FROM counter_daily)
+GROUP BY username, word, date(hour);
+
+CREATE TRIGGER IF NOT EXISTS counter_daily_insert AFTER INSERT ON counter_history
+BEGIN
+    INSERT INTO counter_daily(username, word, day, count)
+    VALUES (NEW.username, NEW.word, date(NEW.hour), NEW.count)
+    ON CONFLICT(username, word, day) DO UPDATE SET count = count + excluded.count;
+END;
+
+CREATE TRIGGER IF NOT EXISTS counter_daily_update AFTER UPDATE ON counter_history
+BEGIN
+    UPDATE counter_daily
+    SET count = count - OLD.count
+    WHERE username = OLD.username AND word = OLD.word AND day = date(OLD.hour);
+
+    INSERT INTO counter_daily(username, word, day, count)
+    VALUES (NEW.username, NEW.word, date(NEW.hour), NEW.count)
+    ON CONFLICT(username, word, day) DO UPDATE SET count = count + excluded.count;
+
+    DELETE FROM counter_daily
+    WHERE username = OLD.username AND word = OLD.word AND day = date(OLD.hour) AND count <= 0;
+END;
+
+CREATE TRIGGER IF NOT EXISTS counter_daily_delete AFTER DELETE ON counter_history
+BEGIN
+    UPDATE counter_daily
+    SET count = count - OLD.count
+    WHERE username = OLD.username AND word = OLD.word AND day = date(OLD.hour);
+
+    DELETE FROM counter_daily
+    WHERE username = OLD.username AND word = OLD.word AND day = date(OLD.hour) AND count <= 0;
+END;
+
+-- Runs of consecutive words counted when phrase counting is on, their words joined by spaces
+CREATE TABLE IF NOT EXISTS counter_phrases (
+    username TEXT NOT NULL,
+    phrase TEXT NOT NULL,
+    length INTEGER NOT NULL,
+    count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (username, phrase)
+) WITHOUT ROWID;
+
+CREATE INDEX IF NOT EXISTS counter_phrases_phrase ON counter_phrases (phrase);
+
+CREATE TABLE IF NOT EXISTS counter_stopwords (
+    word TEXT PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS counter_grams (
+    kind TEXT NOT NULL,
+    gram TEXT NOT NULL,
+    term TEXT NOT NULL,
+    PRIMARY KEY (kind, gram, term)
+) WITHOUT ROWID;
+
+CREATE INDEX IF NOT EXISTS counter_grams_term ON counter_grams (kind, term);
+
+-- The words that are emoji or custom emotes, registered as they are indexed
+CREATE TABLE IF NOT EXISTS counter_emoji (
+    word TEXT PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS counter_identities (
+    name TEXT PRIMARY KEY,
+    user_id INTEGER UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS counter_aliases (
+    alias TEXT PRIMARY KEY,
+    identity TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS counter_aliases_identity ON counter_aliases (identity);
+
+CREATE TABLE IF NOT EXISTS counter_privacy (
+    username TEXT PRIMARY KEY,
+    visibility TEXT NOT NULL DEFAULT 'visible',
+    label TEXT UNIQUE,
+    private INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS counter_hidden_words (
+    username TEXT NOT NULL,
+    word TEXT NOT NULL,
+    PRIMARY KEY (username, word)
+);
+
+CREATE TABLE IF NOT EXISTS counter_blocklist (
+    word TEXT PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS counter_audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    action TEXT NOT NULL,
+    username TEXT,
+    word TEXT,
+    reason TEXT,
+    actor TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    undone_by INTEGER
+);
+
+-- The value of every row an audited action changed, before and after it
+CREATE TABLE IF NOT EXISTS counter_audit_changes (
+    audit_id INTEGER NOT NULL,
+    target TEXT NOT NULL,
+    username TEXT,
+    word TEXT NOT NULL,
+    hour TEXT,
+    before INTEGER NOT NULL,
+    after INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS counter_audit_changes_audit_id ON counter_audit_changes (audit_id);
+
+-- Messages the importer has counted, so an import can be run again without counting twice
+CREATE TABLE IF NOT EXISTS counter_imports (
+    source TEXT NOT NULL,
+    message_id TEXT NOT NULL,
+    PRIMARY KEY (source, message_id)
+) WITHOUT ROWID;
+
+CREATE TABLE IF NOT EXISTS counter_rules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    word TEXT,
+    threshold INTEGER NOT NULL DEFAULT 1,
+    repeat INTEGER NOT NULL DEFAULT 0,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    created_by INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- The thresholds each rule has reached, so none of them fire twice. Keyed on the identity
+-- rather than the name it is shown under, which changes with its visibility
+CREATE TABLE IF NOT EXISTS counter_milestones (
+    rule_id INTEGER NOT NULL,
+    username TEXT NOT NULL,
+    word TEXT NOT NULL DEFAULT '',
+    threshold INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (rule_id, username, word, threshold)
+);
+
+-- The user and word leaderboards as they were ranked at each snapshot
+CREATE TABLE IF NOT EXISTS counter_snapshots (
+    taken_at TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    name TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    rank INTEGER NOT NULL,
+    dense_rank INTEGER NOT NULL,
+    PRIMARY KEY (kind, name, taken_at)
+) WITHOUT ROWID;
+
+CREATE INDEX IF NOT EXISTS counter_snapshots_taken_at ON counter_snapshots (kind, taken_at);
+
+-- Ingested messages that went over the anomaly limits, waiting for or given a review
+CREATE TABLE IF NOT EXISTS counter_anomalies (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    username TEXT NOT NULL,
+    kinds TEXT NOT NULL,
+    details TEXT NOT NULL,
+    policy TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    hour TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    reviewed_by TEXT,
+    reviewed_at TEXT,
+    audit_id INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS counter_anomalies_status ON counter_anomalies (status);
+
+CREATE INDEX IF NOT EXISTS counter_anomalies_username_hour ON counter_anomalies (username, hour);
+
+-- The usage of each word an anomaly held back, or counted when it was only logged
+CREATE TABLE IF NOT EXISTS counter_anomaly_words (
+    anomaly_id INTEGER NOT NULL,
+    word TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    PRIMARY KEY (anomaly_id, word)
+) WITHOUT ROWID;
+
+-- The phrases of the messages an anomaly held back, counted once it is approved
+CREATE TABLE IF NOT EXISTS counter_anomaly_phrases (
+    anomaly_id INTEGER NOT NULL,
+    phrase TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    PRIMARY KEY (anomaly_id, phrase)
+) WITHOUT ROWID;
+
+-- The views are recreated so changes to them apply on startup
+DROP VIEW IF EXISTS counter_resolved;
+DROP VIEW IF EXISTS counter_history_resolved;
+DROP VIEW IF EXISTS counter_raw;
+DROP VIEW IF EXISTS counter_daily_resolved;
+DROP VIEW IF EXISTS counter_phrases_resolved;
+DROP VIEW IF EXISTS counter_totals_resolved;
+DROP VIEW IF EXISTS counter_history_raw;
+DROP VIEW IF EXISTS counter_daily_raw;
+DROP VIEW IF EXISTS counter_phrases_raw;
+DROP VIEW IF EXISTS counter_visible;
+DROP VIEW IF EXISTS counter_history_visible;
+DROP VIEW IF EXISTS counter_daily_visible;
+DROP VIEW IF EXISTS counter_phrases_visible;
+DROP VIEW IF EXISTS counter_totals_visible;
+
+-- Counts that may be shown, with the identity of their username and its anonymized label.
+-- Visibility, labels and hidden words are set per identity, private can be set on either name.
+CREATE VIEW counter_visible AS
+SELECT
+    counter.id AS id,
+    counter.username AS username,
+    COALESCE(counter_aliases.identity, counter.username) AS identity,
+    CASE WHEN privacy.visibility = 'anonymous' THEN privacy.label END AS label,
+    counter.word AS word,
+    counter.count AS count
+FROM counter
+LEFT JOIN counter_aliases ON counter_aliases.alias = counter.username
+LEFT JOIN counter_privacy AS privacy
+    ON privacy.username = COALESCE(counter_aliases.identity, counter.username)
+WHERE COALESCE(privacy.visibility, 'visible') != 'hidden'
+    AND NOT COALESCE(privacy.private, 0)
+    AND NOT EXISTS (
+        SELECT 1 FROM counter_privacy AS own WHERE own.username = counter.username AND own.private
+    )
+    AND NOT EXISTS (
+        SELECT 1
+        FROM counter_hidden_words AS hidden
+        WHERE hidden.username = COALESCE(counter_aliases.identity, counter.username)
+            AND hidden.word = counter.word
+    );
+
+CREATE VIEW counter_history_visible AS
+SELECT
+    counter_history.username AS username,
+    COALESCE(counter_aliases.identity, counter_history.username) AS identity,
+    CASE WHEN privacy.visibility = 'anonymous' THEN privacy.label END AS label,
+    counter_history.word AS word,
+    counter_history.hour AS hour,
+    counter_history.count AS count
+FROM counter_history
+LEFT JOIN counter_aliases ON counter_aliases.alias = counter_history.username
+LEFT JOIN counter_privacy AS privacy
+    ON privacy.username = COALESCE(counter_aliases.identity, counter_history.username)
+WHERE COALESCE(privacy.visibility, 'visible') != 'hidden'
+    AND NOT COALESCE(privacy.private, 0)
+    AND NOT EXISTS (
+        SELECT 1
+        FROM counter_privacy AS own
+        WHERE own.username = counter_history.username AND own.private
+    )
+    AND NOT EXISTS (
+        SELECT 1
+        FROM counter_hidden_words AS hidden
+        WHERE hidden.username = COALESCE(counter_aliases.identity, counter_history.username)
+            AND hidden.word = counter_history.word
+    );
+
+CREATE VIEW counter_daily_visible AS
+SELECT
+    counter_daily.username AS username,
+    COALESCE(counter_aliases.identity, counter_daily.username) AS identity,
+    CASE WHEN privacy.visibility = 'anonymous' THEN privacy.label END AS label,
+    counter_daily.word AS word,
+    counter_daily.day AS day,
+    counter_daily.count AS count
+FROM counter_daily
+LEFT JOIN counter_aliases ON counter_aliases.alias = counter_daily.username
+LEFT JOIN counter_privacy AS privacy
+    ON privacy.username = COALESCE(counter_aliases.identity, counter_daily.username)
+WHERE COALESCE(privacy.visibility, 'visible') != 'hidden'
+    AND NOT COALESCE(privacy.private, 0)
+    AND NOT EXISTS (
+        SELECT 1
+        FROM counter_privacy AS own
+        WHERE own.username = counter_daily.username AND own.private
+    )
+    AND NOT EXISTS (
+        SELECT 1
+        FROM counter_hidden_words AS hidden
+        WHERE hidden.username = COALESCE(counter_aliases.identity, counter_daily.username)
+            AND hidden.word = counter_daily.word
+    );
+
+-- Phrases are hidden when any of their words is hidden or blocked
+CREATE VIEW counter_phrases_visible AS
+SELECT
+    counter_phrases.username AS username,
+    COALESCE(counter_aliases.identity, counter_phrases.username) AS identity,
+    CASE WHEN privacy.visibility = 'anonymous' THEN privacy.label END AS label,
+    counter_phrases.phrase AS phrase,
+    counter_phrases.length AS length,
+    counter_phrases.count AS count
+FROM counter_phrases
+LEFT JOIN counter_aliases ON counter_aliases.alias = counter_phrases.username
+LEFT JOIN counter_privacy AS privacy
+    ON privacy.username = COALESCE(counter_aliases.identity, counter_phrases.username)
+WHERE COALESCE(privacy.visibility, 'visible') != 'hidden'
+    AND NOT COALESCE(privacy.private, 0)
+    AND NOT EXISTS (
+        SELECT 1
+        FROM counter_privacy AS own
+        WHERE own.username = counter_phrases.username AND own.private
+    )
+    AND NOT EXISTS (
+        SELECT 1
+        FROM counter_hidden_words AS hidden
+        WHERE hidden.username = COALESCE(counter_aliases.identity, counter_phrases.username)
+            AND instr(' ' || counter_phrases.phrase || ' ', ' ' || hidden.word || ' ') > 0
+    )
+    AND NOT EXISTS (
+        SELECT 1
+        FROM counter_blocklist AS blocked
+        WHERE instr(' ' || counter_phrases.phrase || ' ', ' ' || blocked.word || ' ') > 0
+    );
+
+-- Totals are shown less the counts of the words their identity hid
+CREATE VIEW counter_totals_visible AS
+SELECT
+    counter_totals.username AS username,
+    COALESCE(counter_aliases.identity, counter_totals.username) AS identity,
+    CASE WHEN privacy.visibility = 'anonymous' THEN privacy.label END AS label,
+    counter_totals.total - COALESCE((
+        SELECT SUM(counter.count)
+        FROM counter_hidden_words AS hidden
+        JOIN counter ON counter.username = counter_totals.username AND counter.word = hidden.word
+        WHERE hidden.username = COALESCE(counter_aliases.identity, counter_totals.username)
+    ), 0) AS total
+FROM counter_totals
+LEFT JOIN counter_aliases ON counter_aliases.alias = counter_totals.username
+LEFT JOIN counter_privacy AS privacy
+    ON privacy.username = COALESCE(counter_aliases.identity, counter_totals.username)
+WHERE COALESCE(privacy.visibility, 'visible') != 'hidden'
+    AND NOT COALESCE(privacy.private, 0)
+    AND NOT EXISTS (
+        SELECT 1
+        FROM counter_privacy AS own
+        WHERE own.username = counter_totals.username AND own.private
+    );
+
+-- Visible counts under the username they were counted as
+CREATE VIEW counter_raw AS
+SELECT id, COALESCE(label, username) AS username, word, count
+FROM counter_visible;
+
+CREATE VIEW counter_history_raw AS
+SELECT COALESCE(label, username) AS username, word, hour, count
+FROM counter_history_visible;
+
+CREATE VIEW counter_daily_raw AS
+SELECT COALESCE(label, username) AS username, word, day, count
+FROM counter_daily_visible;
+
+CREATE VIEW counter_phrases_raw AS
+SELECT COALESCE(label, username) AS username, phrase, length, count
+FROM counter_phrases_visible;
+
+-- Visible counts with every alias resolved to its identity
+CREATE VIEW counter_resolved AS
+SELECT MIN(id) AS id, COALESCE(label, identity) AS username, word, SUM(count) AS count
+FROM counter_visible
+GROUP BY COALESCE(label, identity), word;
+
+CREATE VIEW counter_history_resolved AS
+SELECT COALESCE(label, identity) AS username, word, hour, SUM(count) AS count
+FROM counter_history_visible
+GROUP BY COALESCE(label, identity), word, hour;
+
+CREATE VIEW counter_daily_resolved AS
+SELECT COALESCE(label, identity) AS username, word, day, SUM(count) AS count
+FROM counter_daily_visible
+GROUP BY COALESCE(label, identity), word, day;
+
+CREATE VIEW counter_phrases_resolved AS
+SELECT COALESCE(label, identity) AS username, phrase, length, SUM(count) AS count
+FROM counter_phrases_visible
+GROUP BY COALESCE(label, identity), phrase;
+
+CREATE VIEW counter_totals_resolved AS
+SELECT identity, COALESCE(label, identity) AS username, SUM(total) AS total
+FROM counter_totals_visible
+GROUP BY identity
+HAVING SUM(total) > 0;
@@ -1,3 +1,4 @@
+use chrono::{Days, NaiveDate, Utc};
 use regex::Regex;
 use serde::Deserialize;
 use std::sync::LazyLock;
@@ -5,6 +6,43 @@ use validator::Validate;
 
 static RE_STRING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9\-_]*$").unwrap());
 
+/// A word, a single Unicode emoji (including zero width joiner sequences) or a custom emote
+static RE_WORD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^([a-zA-Z0-9\-_]*|<a?:[a-zA-Z0-9_]{2,32}:[0-9]{1,20}>|[\p{Extended_Pictographic}\p{Emoji_Component}]+)$",
+    )
+    .unwrap()
+});
+
+static RE_PHRASE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9\-_ ]*$").unwrap());
+
+static RE_ORDER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(asc|desc)$").unwrap());
+
+static RE_RANKING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(competition|dense)$").unwrap());
+
+static RE_FORMAT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(json|csv|ndjson)$").unwrap());
+
+static RE_MATCH: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(dice|levenshtein|damerau|jaro|trigram|phonetic)$").unwrap());
+
+static RE_WEIGHTING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(count|tfidf)$").unwrap());
+
+static RE_WINDOW: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(24h|7d|30d|custom)$").unwrap());
+
+static RE_INTERVAL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(day|week|month)$").unwrap());
+
+static RE_VISIBILITY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(visible|hidden|anonymous)$").unwrap());
+
+static RE_RULE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(word|total|leader)$").unwrap());
+
+static RE_STATUS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(pending|approved|rejected)$").unwrap());
+
+static RE_USERS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9\-_]{3,32}(,[a-zA-Z0-9\-_]{3,32}){1,2}$").unwrap());
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct QueryParams {
     #[validate(range(min = 1))]
@@ -13,8 +51,7 @@ pub struct QueryParams {
     #[validate(range(min = 1, max = 100))]
     pub limit: Option<u32>,
 
-    #[validate(contains(pattern = "asc|desc"))]
-    #[validate(regex(path = *RE_STRING))]
+    #[validate(regex(path = *RE_ORDER))]
     pub order: Option<String>,
 
     #[validate(length(min = 3, max = 32))]
@@ -22,8 +59,19 @@ pub struct QueryParams {
     pub username: Option<String>,
 
     #[validate(length(min = 1, max = 2000))]
-    #[validate(regex(path = *RE_STRING))]
+    #[validate(regex(path = *RE_WORD))]
     pub word: Option<String>,
+
+    #[validate(regex(path = *RE_FORMAT))]
+    pub format: Option<String>,
+
+    #[serde(rename = "match")]
+    #[validate(regex(path = *RE_MATCH))]
+    pub algorithm: Option<String>,
+
+    pub exact: Option<bool>,
+
+    pub raw: Option<bool>,
 }
 
 pub struct SetQueryParams {
@@ -32,6 +80,10 @@ pub struct SetQueryParams {
     pub order: String,
     pub username: Option<String>,
     pub word: Option<String>,
+    pub format: Option<String>,
+    pub algorithm: String,
+    pub exact: bool,
+    pub raw: bool,
 }
 
 impl From<QueryParams> for SetQueryParams {
@@ -42,6 +94,10 @@ impl From<QueryParams> for SetQueryParams {
             order: query.order.unwrap_or("desc".to_string()),
             username: query.username,
             word: query.word,
+            format: query.format,
+            algorithm: query.algorithm.unwrap_or("dice".to_string()),
+            exact: query.exact.unwrap_or(false),
+            raw: query.raw.unwrap_or(false),
         }
     }
 }
@@ -54,15 +110,17 @@ pub struct QueryPagination {
     #[validate(range(min = 1, max = 100))]
     pub limit: Option<u32>,
 
-    #[validate(contains(pattern = "asc|desc"))]
-    #[validate(regex(path = *RE_STRING))]
+    #[validate(regex(path = *RE_ORDER))]
     pub order: Option<String>,
+
+    pub raw: Option<bool>,
 }
 
 pub struct SetQueryPagination {
     pub page: u32,
     pub limit: u32,
     pub order: String,
+    pub raw: bool,
 }
 
 impl From<QueryPagination> for SetQueryPagination {
@@ -71,6 +129,484 @@ impl From<QueryPagination> for SetQueryPagination {
             page: query.page.unwrap_or(1),
             limit: query.limit.unwrap_or(10),
             order: query.order.unwrap_or("desc".to_string()),
+            raw: query.raw.unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QueryLeaderboard {
+    #[validate(range(min = 1))]
+    pub page: Option<u32>,
+
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+
+    #[validate(regex(path = *RE_ORDER))]
+    pub order: Option<String>,
+
+    #[validate(regex(path = *RE_RANKING))]
+    pub ranking: Option<String>,
+
+    #[validate(length(min = 1, max = 2000))]
+    #[validate(regex(path = *RE_WORD))]
+    pub around: Option<String>,
+
+    #[validate(regex(path = *RE_FORMAT))]
+    pub format: Option<String>,
+
+    #[serde(rename = "excludeStopwords")]
+    pub exclude_stopwords: Option<bool>,
+
+    #[validate(regex(path = *RE_WINDOW))]
+    pub window: Option<String>,
+
+    pub from: Option<NaiveDate>,
+
+    pub to: Option<NaiveDate>,
+
+    pub raw: Option<bool>,
+}
+
+pub struct SetQueryLeaderboard {
+    pub page: u32,
+    pub limit: u32,
+    pub order: String,
+    pub ranking: String,
+    pub around: Option<String>,
+    pub format: Option<String>,
+    pub exclude_stopwords: bool,
+    pub window: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub raw: bool,
+}
+
+impl From<QueryLeaderboard> for SetQueryLeaderboard {
+    fn from(query: QueryLeaderboard) -> Self {
+        Self {
+            page: query.page.unwrap_or(1),
+            limit: query.limit.unwrap_or(10),
+            order: query.order.unwrap_or("desc".to_string()),
+            ranking: query.ranking.unwrap_or("competition".to_string()),
+            around: query.around,
+            format: query.format,
+            exclude_stopwords: query.exclude_stopwords.unwrap_or(false),
+            window: query.window,
+            from: query.from,
+            to: query.to,
+            raw: query.raw.unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QueryPhrases {
+    #[validate(range(min = 1))]
+    pub page: Option<u32>,
+
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+
+    #[validate(regex(path = *RE_ORDER))]
+    pub order: Option<String>,
+
+    #[validate(regex(path = *RE_RANKING))]
+    pub ranking: Option<String>,
+
+    #[validate(length(min = 1, max = 2000))]
+    #[validate(regex(path = *RE_PHRASE))]
+    pub phrase: Option<String>,
+
+    #[serde(rename = "match")]
+    #[validate(regex(path = *RE_MATCH))]
+    pub algorithm: Option<String>,
+
+    #[validate(range(min = 2, max = 3))]
+    pub length: Option<u32>,
+
+    #[validate(length(min = 3, max = 32))]
+    #[validate(regex(path = *RE_STRING))]
+    pub username: Option<String>,
+
+    pub raw: Option<bool>,
+}
+
+pub struct SetQueryPhrases {
+    pub page: u32,
+    pub limit: u32,
+    pub order: String,
+    pub ranking: String,
+    pub phrase: Option<String>,
+    pub algorithm: String,
+    pub length: Option<u32>,
+    pub username: Option<String>,
+    pub raw: bool,
+}
+
+impl From<QueryPhrases> for SetQueryPhrases {
+    fn from(query: QueryPhrases) -> Self {
+        Self {
+            page: query.page.unwrap_or(1),
+            limit: query.limit.unwrap_or(10),
+            order: query.order.unwrap_or("desc".to_string()),
+            ranking: query.ranking.unwrap_or("competition".to_string()),
+            phrase: query.phrase,
+            algorithm: query.algorithm.unwrap_or("dice".to_string()),
+            length: query.length,
+            username: query.username,
+            raw: query.raw.unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QueryEmoji {
+    #[validate(range(min = 1))]
+    pub page: Option<u32>,
+
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+
+    #[validate(regex(path = *RE_ORDER))]
+    pub order: Option<String>,
+
+    #[validate(regex(path = *RE_RANKING))]
+    pub ranking: Option<String>,
+
+    pub custom: Option<bool>,
+
+    #[validate(regex(path = *RE_WINDOW))]
+    pub window: Option<String>,
+
+    pub from: Option<NaiveDate>,
+
+    pub to: Option<NaiveDate>,
+
+    pub raw: Option<bool>,
+}
+
+pub struct SetQueryEmoji {
+    pub page: u32,
+    pub limit: u32,
+    pub order: String,
+    pub ranking: String,
+    pub custom: Option<bool>,
+    pub window: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub raw: bool,
+}
+
+impl From<QueryEmoji> for SetQueryEmoji {
+    fn from(query: QueryEmoji) -> Self {
+        Self {
+            page: query.page.unwrap_or(1),
+            limit: query.limit.unwrap_or(10),
+            order: query.order.unwrap_or("desc".to_string()),
+            ranking: query.ranking.unwrap_or("competition".to_string()),
+            custom: query.custom,
+            window: query.window,
+            from: query.from,
+            to: query.to,
+            raw: query.raw.unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct Message {
+    #[validate(length(min = 3, max = 32))]
+    #[validate(regex(path = *RE_STRING))]
+    pub username: String,
+
+    #[validate(length(min = 1, max = 2000))]
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QueryTimeline {
+    #[validate(length(min = 3, max = 32))]
+    #[validate(regex(path = *RE_STRING))]
+    pub username: Option<String>,
+
+    #[validate(length(min = 1, max = 2000))]
+    #[validate(regex(path = *RE_WORD))]
+    pub word: Option<String>,
+
+    pub from: Option<NaiveDate>,
+
+    pub to: Option<NaiveDate>,
+
+    #[validate(regex(path = *RE_INTERVAL))]
+    pub interval: Option<String>,
+
+    pub raw: Option<bool>,
+}
+
+pub struct SetQueryTimeline {
+    pub username: Option<String>,
+    pub word: Option<String>,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub interval: String,
+    pub raw: bool,
+}
+
+impl From<QueryTimeline> for SetQueryTimeline {
+    fn from(query: QueryTimeline) -> Self {
+        let to = query.to.unwrap_or(Utc::now().date_naive());
+
+        Self {
+            username: query.username,
+            word: query.word,
+            from: query.from.unwrap_or(to - Days::new(29)),
+            to,
+            interval: query.interval.unwrap_or("day".to_string()),
+            raw: query.raw.unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QueryRankHistory {
+    pub from: Option<NaiveDate>,
+
+    pub to: Option<NaiveDate>,
+
+    #[validate(regex(path = *RE_RANKING))]
+    pub ranking: Option<String>,
+}
+
+pub struct SetQueryRankHistory {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub ranking: String,
+}
+
+impl From<QueryRankHistory> for SetQueryRankHistory {
+    fn from(query: QueryRankHistory) -> Self {
+        let to = query.to.unwrap_or(Utc::now().date_naive());
+
+        Self {
+            from: query.from.unwrap_or(to - Days::new(364)),
+            to,
+            ranking: query.ranking.unwrap_or("competition".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QueryProfile {
+    #[validate(range(min = 1, max = 100))]
+    pub top: Option<u32>,
+
+    #[serde(rename = "excludeStopwords")]
+    pub exclude_stopwords: Option<bool>,
+
+    pub raw: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QuerySimilar {
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+
+    #[validate(regex(path = *RE_WEIGHTING))]
+    pub weighting: Option<String>,
+
+    pub raw: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QueryDistinctive {
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+
+    #[validate(range(min = 1))]
+    pub min: Option<u32>,
+
+    pub raw: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QueryCompare {
+    #[validate(regex(path = *RE_USERS))]
+    pub users: String,
+
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+
+    #[serde(rename = "excludeStopwords")]
+    pub exclude_stopwords: Option<bool>,
+
+    pub raw: Option<bool>,
+}
+
+pub struct SetQueryCompare {
+    pub users: Vec<String>,
+    pub limit: u32,
+    pub exclude_stopwords: bool,
+    pub raw: bool,
+}
+
+impl From<QueryCompare> for SetQueryCompare {
+    fn from(query: QueryCompare) -> Self {
+        Self {
+            users: query.users.split(',').map(str::to_owned).collect(),
+            limit: query.limit.unwrap_or(10),
+            exclude_stopwords: query.exclude_stopwords.unwrap_or(false),
+            raw: query.raw.unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QueryRaw {
+    pub raw: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct Name {
+    #[validate(length(min = 3, max = 32))]
+    #[validate(regex(path = *RE_STRING))]
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UserPath {
+    #[validate(length(min = 3, max = 32))]
+    #[validate(regex(path = *RE_STRING))]
+    pub username: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct Identity {
+    #[serde(rename = "userId")]
+    pub user_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct Alias {
+    #[validate(length(min = 3, max = 32))]
+    #[validate(regex(path = *RE_STRING))]
+    pub identity: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct Privacy {
+    #[validate(regex(path = *RE_VISIBILITY))]
+    pub visibility: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct Private {
+    pub private: bool,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct Moderation {
+    #[validate(length(min = 3, max = 32))]
+    #[validate(regex(path = *RE_STRING))]
+    pub username: Option<String>,
+
+    #[validate(length(min = 1, max = 256))]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CountPath {
+    #[validate(length(min = 3, max = 32))]
+    #[validate(regex(path = *RE_STRING))]
+    pub username: String,
+
+    pub word: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct Adjustment {
+    pub delta: i64,
+
+    #[validate(length(min = 1, max = 256))]
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct Blocklist {
+    #[validate(length(min = 1, max = 1000))]
+    pub words: Vec<String>,
+
+    #[validate(length(min = 1, max = 256))]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QueryReason {
+    #[validate(length(min = 1, max = 256))]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QueryAnomalies {
+    #[validate(range(min = 1))]
+    pub page: Option<u32>,
+
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+
+    #[validate(regex(path = *RE_ORDER))]
+    pub order: Option<String>,
+
+    #[validate(regex(path = *RE_STATUS))]
+    pub status: Option<String>,
+
+    #[validate(length(min = 3, max = 32))]
+    #[validate(regex(path = *RE_STRING))]
+    pub username: Option<String>,
+}
+
+pub struct SetQueryAnomalies {
+    pub page: u32,
+    pub limit: u32,
+    pub order: String,
+    pub status: String,
+    pub username: Option<String>,
+}
+
+impl From<QueryAnomalies> for SetQueryAnomalies {
+    fn from(query: QueryAnomalies) -> Self {
+        Self {
+            page: query.page.unwrap_or(1),
+            limit: query.limit.unwrap_or(10),
+            order: query.order.unwrap_or("desc".to_string()),
+            status: query.status.unwrap_or("pending".to_string()),
+            username: query.username,
         }
     }
 }
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QueryStream {
+    #[validate(length(min = 3, max = 32))]
+    #[validate(regex(path = *RE_STRING))]
+    pub username: Option<String>,
+
+    #[validate(length(min = 1, max = 64))]
+    pub word: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct Rule {
+    #[validate(regex(path = *RE_RULE))]
+    pub kind: String,
+
+    #[validate(length(min = 1, max = 64))]
+    pub word: Option<String>,
+
+    #[validate(range(min = 1))]
+    pub threshold: Option<i64>,
+
+    pub repeat: Option<bool>,
+
+    pub enabled: Option<bool>,
+}
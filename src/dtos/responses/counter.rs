@@ -1,6 +1,9 @@
-use crate::utils::string;
-use rusqlite::{Error, Row};
-use serde::Serialize;
+use crate::utils::{emoji, pagination, string::Similarity};
+use actix_web::HttpRequest;
+use chrono::{NaiveDate, NaiveDateTime};
+use rusqlite::{Error, Row, types::Type};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Serialize)]
 pub struct DataLinks {
@@ -21,15 +24,16 @@ impl Data {
         row: &Row,
         username: &Option<String>,
         word: &Option<String>,
+        algorithm: &dyn Similarity,
     ) -> Result<Self, Error> {
         let row_username: String = row.get("username")?;
         let row_word: String = row.get("word")?;
         let similarity: Option<f32>;
 
         if let Some(u) = username {
-            similarity = Some(string::similarity(&row_username, u));
+            similarity = Some(algorithm.similarity(&row_username, u));
         } else if let Some(w) = word {
-            similarity = Some(string::similarity(&row_word, w));
+            similarity = Some(algorithm.similarity(&row_word, w));
         } else {
             similarity = None;
         }
@@ -38,7 +42,7 @@ impl Data {
             username: row_username,
             word: row_word,
             count: row.get("count")?,
-            similarity: similarity,
+            similarity,
         })
     }
 }
@@ -61,6 +65,21 @@ pub struct Pagination {
     pub has_prev: bool,
 }
 
+impl Pagination {
+    pub fn new(page: u32, limit: u32, total_rows: u32) -> Self {
+        let total_pages = total_rows.div_ceil(limit);
+
+        Self {
+            page,
+            limit,
+            total_rows,
+            total_pages,
+            has_next: page < total_pages,
+            has_prev: page > 1,
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct Filters {
     pub username: Option<String>,
@@ -78,6 +97,10 @@ pub struct Meta {
     pub pagination: Pagination,
     pub filters: Filters,
     pub sort: Sort,
+
+    /// The algorithm that scored `similarity`
+    #[serde(rename = "match")]
+    pub algorithm: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -86,6 +109,21 @@ pub struct PartMeta {
     pub sort: Sort,
 }
 
+/// The range a windowed leaderboard counted usage in, from inclusive to exclusive
+#[derive(Debug, Serialize)]
+pub struct Window {
+    pub name: String,
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LeaderboardMeta {
+    pub pagination: Pagination,
+    pub sort: Sort,
+    pub window: Option<Window>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct Links {
     #[serde(rename = "self")]
@@ -96,55 +134,732 @@ pub struct Links {
     pub next: Option<String>,
 }
 
+impl Links {
+    /// Builds the page links of a list from the path and query of its request
+    pub fn new(req: &HttpRequest, pagination: &Pagination) -> Self {
+        let Pagination {
+            page,
+            limit,
+            total_pages,
+            has_next,
+            has_prev,
+            ..
+        } = *pagination;
+
+        let link = |page: u32| pagination::link(req.path(), req.query_string(), page, limit);
+
+        Self {
+            own: link(page),
+            first: link(1),
+            last: link(total_pages.max(1)),
+            prev: has_prev.then(|| link(page - 1)),
+            next: has_next.then(|| link(page + 1)),
+        }
+    }
+}
+
+/// A page of a list with its meta data and page links
 #[derive(Debug, Serialize)]
-pub struct Response {
-    pub data: Vec<Data>,
-    pub meta: Meta,
+pub struct Paginated<T, M = PartMeta> {
+    pub data: Vec<T>,
+    pub meta: M,
     pub links: Links,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct UserData {
     pub username: String,
     pub count: u32,
+    pub rank: u32,
+
+    /// The rank in the latest leaderboard snapshot
+    #[serde(rename = "previousRank")]
+    pub previous_rank: Option<u32>,
+
+    /// How many places the rank went up since the snapshot, negative when it went down
+    #[serde(rename = "rankDelta")]
+    pub rank_delta: Option<i64>,
 }
 
 impl UserData {
     pub fn from_row(row: &Row) -> Result<Self, Error> {
         let username: String = row.get("username")?;
+        let rank = row.get("rank")?;
+        let previous_rank = previous_rank(row)?;
+
         Ok(Self {
             username: username.clone(),
             count: row.get("total")?,
+            rank,
+            previous_rank,
+            rank_delta: previous_rank.map(|previous| i64::from(previous) - i64::from(rank)),
         })
     }
 }
 
-#[derive(Debug, Serialize)]
-pub struct UserResponse {
-    pub data: Vec<UserData>,
-    pub meta: PartMeta,
-    pub links: Links,
-}
-
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct WordData {
     pub word: String,
     pub count: u32,
+    pub rank: u32,
+
+    /// The rank in the latest leaderboard snapshot
+    #[serde(rename = "previousRank")]
+    pub previous_rank: Option<u32>,
+
+    /// How many places the rank went up since the snapshot, negative when it went down
+    #[serde(rename = "rankDelta")]
+    pub rank_delta: Option<i64>,
 }
 
 impl WordData {
     pub fn from_row(row: &Row) -> Result<Self, Error> {
         let word: String = row.get("word")?;
+        let rank = row.get("rank")?;
+        let previous_rank = previous_rank(row)?;
+
         Ok(Self {
             word: word.clone(),
             count: row.get("total")?,
+            rank,
+            previous_rank,
+            rank_delta: previous_rank.map(|previous| i64::from(previous) - i64::from(rank)),
+        })
+    }
+}
+
+/// Reads the snapshot rank of a row, which only leaderboard queries select
+fn previous_rank(row: &Row) -> Result<Option<u32>, Error> {
+    match row.as_ref().column_index("previous_rank") {
+        Ok(i) => row.get(i),
+        Err(_) => Ok(None),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RankHistoryData {
+    #[serde(rename = "takenAt")]
+    pub taken_at: String,
+    pub count: u32,
+    pub rank: u32,
+}
+
+impl RankHistoryData {
+    pub fn from_row(row: &Row) -> Result<Self, Error> {
+        Ok(Self {
+            taken_at: row.get("taken_at")?,
+            count: row.get("count")?,
+            rank: row.get("rank")?,
         })
     }
 }
 
 #[derive(Debug, Serialize)]
-pub struct WordResponse {
-    pub data: Vec<WordData>,
+pub struct RankHistoryResponse {
+    pub data: Vec<RankHistoryData>,
+    pub meta: RankHistoryMeta,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RankHistoryMeta {
+    pub username: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub ranking: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IngestData {
+    pub word: String,
+    pub count: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IngestResponse {
+    pub username: String,
+    pub words: Vec<IngestData>,
+
+    /// The anomaly the message was flagged as
+    #[serde(rename = "anomalyId")]
+    pub anomaly_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimelineData {
+    pub bucket: NaiveDate,
+    pub count: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Range {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub interval: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimelineMeta {
+    pub filters: Filters,
+    pub range: Range,
+    pub total: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimelineResponse {
+    pub data: Vec<TimelineData>,
+    pub meta: TimelineMeta,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileData {
+    pub username: String,
+    pub count: u32,
+    pub vocabulary: u32,
+    pub rank: u32,
+    pub percentile: f32,
+
+    #[serde(rename = "topWords")]
+    pub top_words: Vec<WordData>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileLinks {
+    #[serde(rename = "self")]
+    pub own: String,
+    pub rows: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileResponse {
+    pub data: ProfileData,
+    pub links: ProfileLinks,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WordUserData {
+    pub username: String,
+    pub count: u32,
+    pub share: f32,
+}
+
+impl WordUserData {
+    pub fn from_row(row: &Row, word_total: u32) -> Result<Self, Error> {
+        let count: u32 = row.get("total")?;
+        Ok(Self {
+            username: row.get("username")?,
+            count,
+            share: count as f32 / word_total.max(1) as f32,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimilarWordData {
+    pub word: String,
+    pub count: u32,
+    pub similarity: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WordDetailData {
+    pub word: String,
+    pub count: u32,
+    pub users: u32,
+    pub rank: u32,
+
+    #[serde(rename = "topUsers")]
+    pub top_users: Vec<WordUserData>,
+
+    pub similar: Vec<SimilarWordData>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WordDetailResponse {
+    pub data: WordDetailData,
     pub meta: PartMeta,
     pub links: Links,
 }
+
+#[derive(Debug, Serialize)]
+pub struct EmojiData {
+    pub emoji: String,
+
+    /// Whether the emoji is a `<:name:id>` custom emote
+    pub custom: bool,
+
+    pub count: u32,
+    pub rank: u32,
+
+    /// The part of all the emoji used that this emoji makes up
+    pub share: f32,
+}
+
+impl EmojiData {
+    pub fn from_row(row: &Row, emoji_total: u32) -> Result<Self, Error> {
+        let emoji: String = row.get("word")?;
+        let count: u32 = row.get("total")?;
+
+        Ok(Self {
+            custom: emoji::is_emote(&emoji),
+            emoji,
+            count,
+            rank: row.get("rank")?,
+            share: count as f32 / emoji_total.max(1) as f32,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhraseData {
+    pub phrase: String,
+    pub length: u32,
+    pub count: u32,
+    pub rank: u32,
+    pub similarity: Option<f32>,
+}
+
+impl PhraseData {
+    pub fn from_row(
+        row: &Row,
+        phrase: &Option<String>,
+        algorithm: &dyn Similarity,
+    ) -> Result<Self, Error> {
+        let row_phrase: String = row.get("phrase")?;
+
+        Ok(Self {
+            similarity: phrase
+                .as_ref()
+                .map(|p| algorithm.similarity(&row_phrase, p)),
+            phrase: row_phrase,
+            length: row.get("length")?,
+            count: row.get("total")?,
+            rank: row.get("rank")?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhraseFilters {
+    pub phrase: Option<String>,
+    pub length: Option<u32>,
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhraseMeta {
+    pub pagination: Pagination,
+    pub filters: PhraseFilters,
+    pub sort: Sort,
+
+    /// The algorithm that scored `similarity`
+    #[serde(rename = "match")]
+    pub algorithm: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhraseDetailData {
+    pub phrase: String,
+    pub length: u32,
+    pub count: u32,
+    pub users: u32,
+    pub rank: u32,
+
+    #[serde(rename = "topUsers")]
+    pub top_users: Vec<WordUserData>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhraseDetailResponse {
+    pub data: PhraseDetailData,
+    pub meta: PartMeta,
+    pub links: Links,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimilarUserData {
+    pub username: String,
+    pub similarity: f32,
+
+    #[serde(rename = "sharedWords")]
+    pub shared_words: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimilarUserMeta {
+    pub username: String,
+    pub weighting: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimilarUserResponse {
+    pub data: Vec<SimilarUserData>,
+    pub meta: SimilarUserMeta,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DistinctiveData {
+    pub word: String,
+    pub count: u32,
+
+    #[serde(rename = "userShare")]
+    pub user_share: f32,
+
+    #[serde(rename = "populationShare")]
+    pub population_share: f32,
+
+    pub ratio: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DistinctiveMeta {
+    pub username: String,
+    pub min: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DistinctiveResponse {
+    pub data: Vec<DistinctiveData>,
+    pub meta: DistinctiveMeta,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ZipfData {
+    pub slope: f32,
+
+    #[serde(rename = "rSquared")]
+    pub r_squared: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LengthData {
+    pub length: u32,
+    pub words: u32,
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsData {
+    pub total: u32,
+    pub distinct: u32,
+
+    #[serde(rename = "typeTokenRatio")]
+    pub type_token_ratio: f32,
+
+    #[serde(rename = "hapaxLegomena")]
+    pub hapax_legomena: u32,
+
+    pub zipf: ZipfData,
+    pub lengths: Vec<LengthData>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatsMeta {
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatsResponse {
+    pub data: StatsData,
+    pub meta: StatsMeta,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CountData {
+    pub word: String,
+    pub count: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompareUserData {
+    pub username: String,
+    pub total: u32,
+    pub vocabulary: u32,
+    pub unique: Vec<CountData>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SharedWordData {
+    pub word: String,
+    pub counts: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompareData {
+    pub users: Vec<CompareUserData>,
+    pub shared: Vec<SharedWordData>,
+    pub overlap: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompareMeta {
+    pub users: Vec<String>,
+    pub shared: u32,
+    pub limit: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompareResponse {
+    pub data: CompareData,
+    pub meta: CompareMeta,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheData {
+    pub name: String,
+    pub entries: u32,
+    pub hits: u64,
+    pub misses: u64,
+
+    #[serde(rename = "hitRate")]
+    pub hit_rate: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheMeta {
+    pub ttl: u64,
+    pub capacity: u32,
+    pub version: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheResponse {
+    pub data: Vec<CacheData>,
+    pub meta: CacheMeta,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IdentityData {
+    pub name: String,
+
+    #[serde(rename = "userId")]
+    pub user_id: Option<i64>,
+
+    pub aliases: Vec<String>,
+}
+
+impl IdentityData {
+    pub fn from_row(row: &Row) -> Result<Self, Error> {
+        let aliases: Option<String> = row.get("aliases")?;
+
+        Ok(Self {
+            name: row.get("name")?,
+            user_id: row.get("user_id")?,
+            aliases: aliases
+                .map(|a| a.split(',').map(str::to_owned).collect())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AliasData {
+    pub alias: String,
+    pub identity: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MergeData {
+    pub identity: String,
+    pub moved: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrivacyData {
+    pub identity: String,
+    pub visibility: String,
+    pub label: Option<String>,
+    pub private: bool,
+
+    #[serde(rename = "hiddenWords")]
+    pub hidden_words: Vec<String>,
+}
+
+impl PrivacyData {
+    pub fn from_row(row: &Row) -> Result<Self, Error> {
+        let hidden_words: Option<String> = row.get("hidden_words")?;
+
+        Ok(Self {
+            identity: row.get("identity")?,
+            visibility: row.get("visibility")?,
+            label: row.get("label")?,
+            private: row.get("private")?,
+            hidden_words: hidden_words
+                .map(|w| w.split(',').map(str::to_owned).collect())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangeData {
+    pub target: String,
+    pub username: Option<String>,
+    pub word: String,
+    pub hour: Option<String>,
+    pub before: i64,
+    pub after: i64,
+}
+
+impl ChangeData {
+    pub fn from_row(row: &Row) -> Result<Self, Error> {
+        Ok(Self {
+            target: row.get("target")?,
+            username: row.get("username")?,
+            word: row.get("word")?,
+            hour: row.get("hour")?,
+            before: row.get("before")?,
+            after: row.get("after")?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditData {
+    pub id: i64,
+    pub action: String,
+    pub username: Option<String>,
+    pub word: Option<String>,
+    pub reason: Option<String>,
+    pub actor: String,
+
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+
+    #[serde(rename = "undoneBy")]
+    pub undone_by: Option<i64>,
+
+    pub changed: u32,
+}
+
+impl AuditData {
+    pub fn from_row(row: &Row) -> Result<Self, Error> {
+        Ok(Self {
+            id: row.get("id")?,
+            action: row.get("action")?,
+            username: row.get("username")?,
+            word: row.get("word")?,
+            reason: row.get("reason")?,
+            actor: row.get("actor")?,
+            created_at: row.get("created_at")?,
+            undone_by: row.get("undone_by")?,
+            changed: row.get("changed")?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditDetailData {
+    #[serde(flatten)]
+    pub audit: AuditData,
+
+    pub changes: Vec<ChangeData>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CountEvent {
+    pub username: String,
+    pub word: String,
+    pub delta: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RankEvent {
+    pub username: String,
+    pub rank: u32,
+
+    #[serde(rename = "previousRank")]
+    pub previous_rank: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuleData {
+    pub id: i64,
+    pub kind: String,
+    pub word: Option<String>,
+    pub threshold: i64,
+    pub repeat: bool,
+    pub enabled: bool,
+
+    #[serde(rename = "createdBy")]
+    pub created_by: i64,
+
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+
+    /// The amount of thresholds the rule has reached
+    pub reached: u32,
+}
+
+impl RuleData {
+    pub fn from_row(row: &Row) -> Result<Self, Error> {
+        Ok(Self {
+            id: row.get("id")?,
+            kind: row.get("kind")?,
+            word: row.get("word")?,
+            threshold: row.get("threshold")?,
+            repeat: row.get("repeat")?,
+            enabled: row.get("enabled")?,
+            created_by: row.get("created_by")?,
+            created_at: row.get("created_at")?,
+            reached: row.get("reached")?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnomalyWordData {
+    pub word: String,
+    pub count: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnomalyData {
+    pub id: i64,
+    pub username: String,
+    pub kinds: Vec<String>,
+    pub details: String,
+    pub policy: String,
+    pub status: String,
+
+    /// The hour the usage was counted in, or would be once approved
+    pub hour: String,
+
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+
+    #[serde(rename = "reviewedBy")]
+    pub reviewed_by: Option<String>,
+
+    #[serde(rename = "reviewedAt")]
+    pub reviewed_at: Option<String>,
+
+    /// The audit entry of the counts the review changed
+    #[serde(rename = "auditId")]
+    pub audit_id: Option<i64>,
+
+    /// The usage that was held back, or counted when the policy only logs
+    pub words: Vec<AnomalyWordData>,
+}
+
+impl AnomalyData {
+    pub fn from_row(row: &Row) -> Result<Self, Error> {
+        let kinds: String = row.get("kinds")?;
+        let words: String = row.get("words")?;
+
+        Ok(Self {
+            id: row.get("id")?,
+            username: row.get("username")?,
+            kinds: kinds.split(',').map(str::to_owned).collect(),
+            details: row.get("details")?,
+            policy: row.get("policy")?,
+            status: row.get("status")?,
+            hour: row.get("hour")?,
+            created_at: row.get("created_at")?,
+            reviewed_by: row.get("reviewed_by")?,
+            reviewed_at: row.get("reviewed_at")?,
+            audit_id: row.get("audit_id")?,
+            words: serde_json::from_str(&words)
+                .map_err(|e| Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))?,
+        })
+    }
+}
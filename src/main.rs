@@ -1,9 +1,11 @@
+mod commands;
 mod config;
 mod controllers;
 mod dtos;
 mod middleware;
 mod models;
 mod routes;
+mod services;
 mod utils;
 
 use actix_web::{App, HttpServer, dev::Server, middleware::Logger, web};
@@ -16,6 +18,25 @@ async fn main() -> std::io::Result<()> {
 
     let db: web::Data<database::AppState> = database::init();
 
+    // Run a maintenance command instead of the server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if !args.is_empty() {
+        return commands::run(&args, &db);
+    }
+
+    // Keep the live counter streams open while they are idle
+    actix_web::rt::spawn(services::stream::keepalive(db.clone()));
+
+    // Snapshot the leaderboards so ranks can be compared over time
+    actix_web::rt::spawn(services::snapshots::schedule(db.clone()));
+
+    // Notify about a new leader outside of counting, as it needs the totals of everyone
+    actix_web::rt::spawn(services::milestones::schedule(db.clone()));
+
+    // Pick up the counts written by the maintenance commands
+    actix_web::rt::spawn(services::changes::watch(db.clone()));
+
     let port: u16 = std::env::var("PORT")
         .expect("`PORT` must be defined in `.env`.")
         .parse()
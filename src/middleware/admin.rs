@@ -0,0 +1,73 @@
+use crate::middleware::authentication::Claims;
+
+use actix_web::{
+    Error, HttpMessage, HttpResponse,
+    body::{BoxBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready},
+    http::header,
+};
+use futures_util::future::LocalBoxFuture;
+use serde_json::json;
+use std::future::{Ready, ready};
+
+/// Only lets admins through, so it must be wrapped inside `AuthenticationMiddleware`
+pub struct AdminMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for AdminMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static + MessageBody,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AdminMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AdminMiddlewareService { service }))
+    }
+}
+
+pub struct AdminMiddlewareService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for AdminMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static + MessageBody,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let is_admin = req
+            .extensions()
+            .get::<Claims>()
+            .is_some_and(|claims| claims.role == "admin");
+
+        if !is_admin {
+            return Box::pin(async {
+                Ok(req.into_response(
+                    HttpResponse::Forbidden()
+                        .content_type(header::ContentType::json())
+                        .json(json!({"error": "Forbidden", "message": "Must be an admin."}))
+                        .map_into_boxed_body(),
+                ))
+            });
+        }
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let res = fut.await?.map_into_boxed_body();
+            Ok(res)
+        })
+    }
+}
@@ -0,0 +1,124 @@
+use crate::utils::etag;
+
+use actix_web::{
+    Error,
+    body::{self, BodySize, BoxBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready},
+    error,
+    http::{Method, StatusCode, header},
+};
+use futures_util::future::LocalBoxFuture;
+use std::future::{Ready, ready};
+
+/// Tags successful `GET` responses with a weak ETag of their body and answers
+/// matching `If-None-Match` requests with `304 Not Modified`
+///
+/// The innermost `ETagMiddleware` wins, so a route can wrap its own to change the
+/// `Cache-Control` of its scope. Streamed bodies are passed through untouched.
+pub struct ETagMiddleware {
+    cache_control: &'static str,
+}
+
+impl ETagMiddleware {
+    pub fn new(cache_control: &'static str) -> Self {
+        Self { cache_control }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ETagMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static + MessageBody,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ETagMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ETagMiddlewareService {
+            service,
+            cache_control: self.cache_control,
+        }))
+    }
+}
+
+pub struct ETagMiddlewareService<S> {
+    service: S,
+    cache_control: &'static str,
+}
+
+impl<S, B> Service<ServiceRequest> for ETagMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static + MessageBody,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let is_get = req.method() == Method::GET || req.method() == Method::HEAD;
+        let if_none_match = req
+            .headers()
+            .get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let cache_control = self.cache_control;
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let res = fut.await?;
+
+            // Only tag whole bodies of successful reads that an inner middleware hasn't tagged
+            let is_sized = matches!(res.response().body().size(), BodySize::Sized(_));
+
+            if !is_get
+                || !is_sized
+                || res.status() != StatusCode::OK
+                || res.headers().contains_key(header::ETAG)
+            {
+                return Ok(res.map_into_boxed_body());
+            }
+
+            let (req, res) = res.into_parts();
+            let (mut res, body) = res.into_parts();
+
+            let bytes = body::to_bytes(body).await.map_err(|e| {
+                let e: Box<dyn std::error::Error> = e.into();
+                error::ErrorInternalServerError(e.to_string())
+            })?;
+
+            let tag = etag::weak(&bytes);
+
+            let headers = res.headers_mut();
+            headers.insert(
+                header::ETAG,
+                header::HeaderValue::from_str(&tag).map_err(error::ErrorInternalServerError)?,
+            );
+
+            if !headers.contains_key(header::CACHE_CONTROL) {
+                headers.insert(
+                    header::CACHE_CONTROL,
+                    header::HeaderValue::from_static(cache_control),
+                );
+            }
+
+            // The client already has this body
+            if if_none_match.is_some_and(|value| etag::matches(&value, &tag)) {
+                *res.status_mut() = StatusCode::NOT_MODIFIED;
+                res.headers_mut().remove(header::CONTENT_TYPE);
+
+                return Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(()))));
+            }
+
+            Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(bytes))))
+        })
+    }
+}
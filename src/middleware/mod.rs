@@ -1 +1,3 @@
+pub mod admin;
 pub mod authentication;
+pub mod etag;
@@ -1,14 +1,89 @@
-use crate::controllers::counter::*;
-use crate::middleware::authentication::AuthenticationMiddleware;
+use crate::controllers::{
+    counter::*, counter_admin::*, counter_anomalies::*, counter_emoji::*, counter_moderation::*,
+    counter_phrases::*, counter_privacy::*, counter_rules::*,
+};
+use crate::middleware::{
+    admin::AdminMiddleware, authentication::AuthenticationMiddleware, etag::ETagMiddleware,
+};
 
-use actix_web::web;
+use actix_web::{guard, web};
 
 pub fn router(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/counter")
+            .wrap(ETagMiddleware::new("private, no-cache"))
             .wrap(AuthenticationMiddleware)
             .route("", web::get().to(get_all))
+            .service(
+                web::resource("")
+                    .guard(guard::Post())
+                    .wrap(AdminMiddleware)
+                    .route(web::post().to(create)),
+            )
+            .route("/timeline", web::get().to(get_timeline))
+            .route("/stream", web::get().to(get_stream))
+            .service(
+                web::resource("/stats")
+                    .wrap(ETagMiddleware::new("private, max-age=60"))
+                    .route(web::get().to(get_stats)),
+            )
+            .route("/compare", web::get().to(get_compare))
             .route("/users", web::get().to(get_all_users))
-            .route("/words", web::get().to(get_all_words)),
+            .route("/users/{username}", web::get().to(get_user))
+            .route(
+                "/users/{username}/similar",
+                web::get().to(get_similar_users),
+            )
+            .route(
+                "/users/{username}/distinctive",
+                web::get().to(get_distinctive_words),
+            )
+            .route("/users/{username}/stats", web::get().to(get_user_stats))
+            .route("/users/{username}/history", web::get().to(get_rank_history))
+            .route("/users/{username}/emoji", web::get().to(get_user_emoji))
+            .route("/words", web::get().to(get_all_words))
+            .route("/words/{word}", web::get().to(get_word))
+            .route("/emoji", web::get().to(get_all_emoji))
+            .route("/phrases", web::get().to(get_all_phrases))
+            .route("/phrases/{phrase}", web::get().to(get_phrase))
+            .route("/privacy", web::get().to(get_privacy_settings))
+            .route("/privacy", web::put().to(put_privacy_settings))
+            .route("/privacy/words/{word}", web::put().to(put_hidden_word))
+            .route(
+                "/privacy/words/{word}",
+                web::delete().to(delete_hidden_word),
+            )
+            .service(
+                web::scope("/admin")
+                    .wrap(AdminMiddleware)
+                    .route("/identities", web::get().to(get_identities))
+                    .route("/identities/{name}", web::put().to(put_identity))
+                    .route("/identities/{name}/merge", web::post().to(merge_identity))
+                    .route("/aliases/{name}", web::put().to(put_alias))
+                    .route("/aliases/{name}", web::delete().to(delete_alias))
+                    .route("/privacy/{name}", web::put().to(put_private))
+                    .route("/audit", web::get().to(get_audit_log))
+                    .route("/audit/{id}", web::get().to(get_audit_entry))
+                    .route("/audit/{id}/undo", web::post().to(undo_audit))
+                    .route("/words/{word}", web::delete().to(delete_word))
+                    .route("/words/{word}/redact", web::post().to(redact_word))
+                    .route("/counts/{username}/{word}", web::patch().to(adjust_count))
+                    .route("/blocklist", web::get().to(get_blocklist))
+                    .route("/blocklist", web::put().to(put_blocklist))
+                    .route("/blocklist/{word}", web::delete().to(delete_blocklist_word))
+                    .route("/rules", web::get().to(get_rules))
+                    .route("/rules", web::post().to(create_rule))
+                    .route("/rules/{id}", web::put().to(put_rule))
+                    .route("/rules/{id}", web::delete().to(delete_rule))
+                    .route("/anomalies", web::get().to(get_anomalies))
+                    .route("/anomalies/{id}", web::get().to(get_anomaly_entry))
+                    .route("/anomalies/{id}/approve", web::post().to(approve_anomaly))
+                    .route("/anomalies/{id}/reject", web::post().to(reject_anomaly))
+                    .service(
+                        web::resource("/cache")
+                            .wrap(ETagMiddleware::new("no-store"))
+                            .route(web::get().to(get_cache)),
+                    ),
+            ),
     );
 }
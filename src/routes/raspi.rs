@@ -1,7 +1,12 @@
 use crate::controllers::raspi::*;
+use crate::middleware::etag::ETagMiddleware;
 
 use actix_web::web;
 
 pub fn router(cfg: &mut web::ServiceConfig) {
-    cfg.route("/", web::get().to(get_system_info));
+    cfg.service(
+        web::resource("/")
+            .wrap(ETagMiddleware::new("no-cache"))
+            .route(web::get().to(get_system_info)),
+    );
 }
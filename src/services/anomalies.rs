@@ -0,0 +1,714 @@
+use crate::{
+    services::{
+        counter,
+        moderation::{self, Action},
+    },
+    utils::{normalize::Normalizer, time},
+};
+
+use chrono::{NaiveDateTime, TimeDelta};
+use rusqlite::{Connection, Error, OptionalExtension, Transaction, params};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Mutex, PoisonError},
+};
+
+/// More messages in a minute than the rate limit allows
+pub const RATE: &str = "rate";
+/// One word used more times in a message than the repeat limit allows
+pub const REPEAT: &str = "repeat";
+/// Far more words in an hour than the user usually uses
+pub const BURST: &str = "burst";
+
+pub const PENDING: &str = "pending";
+pub const APPROVED: &str = "approved";
+pub const REJECTED: &str = "rejected";
+
+/// The amount of users whose recent messages are kept before idle ones are dropped
+const RECENT_USERS: usize = 1024;
+
+/// The hours a user's usual usage is averaged over
+const BASELINE_HOURS: i64 = 7 * 24;
+
+/// The most reasons kept on an anomaly the flags of an hour are merged into
+const MAX_DETAILS: usize = 20;
+
+/// What is done with the usage of a flagged message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    /// Count up to the limits and hold the rest for review
+    Cap,
+    /// Hold the whole message for review
+    Quarantine,
+    /// Count everything and keep the excess for review
+    Log,
+}
+
+impl Policy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "cap" => Some(Policy::Cap),
+            "quarantine" => Some(Policy::Quarantine),
+            "log" => Some(Policy::Log),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Policy::Cap => "cap",
+            Policy::Quarantine => "quarantine",
+            Policy::Log => "log",
+        }
+    }
+}
+
+/// The limits ingest holds messages to, with the recent messages of every user
+#[derive(Debug)]
+pub struct Guard {
+    pub policy: Policy,
+    /// The most times one word is counted per message
+    pub repeats: u32,
+    /// The most messages a user may send in a minute
+    pub rate: usize,
+    /// How many times their usual hourly usage a user may use in an hour
+    pub burst: u32,
+    /// The words in an hour below which nothing is a burst
+    pub burst_floor: u32,
+    /// When each user sent their messages of the last minute
+    recent: Mutex<HashMap<String, VecDeque<NaiveDateTime>>>,
+}
+
+impl Guard {
+    /// Reads the limits from the environment
+    ///
+    /// - `COUNTER_ANOMALY_POLICY`: What is done with flagged usage (cap|quarantine|log). Default `log`
+    /// - `COUNTER_ANOMALY_REPEATS`: The most times one word is counted per message. Default `20`
+    /// - `COUNTER_ANOMALY_RATE`: The most messages a user may send in a minute. Default `20`
+    /// - `COUNTER_ANOMALY_BURST`: How many times their usual hourly usage a user may use in an
+    ///   hour. Default `10`
+    /// - `COUNTER_ANOMALY_BURST_FLOOR`: The words in an hour below which nothing is a burst.
+    ///   Default `500`
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        }
+
+        let policy = std::env::var("COUNTER_ANOMALY_POLICY").unwrap_or("log".to_string());
+
+        Self {
+            policy: Policy::parse(&policy)
+                .unwrap_or_else(|| panic!("Unknown anomaly policy `{policy}`.")),
+            repeats: var("COUNTER_ANOMALY_REPEATS", 20).max(1),
+            rate: var("COUNTER_ANOMALY_RATE", 20).max(1),
+            burst: var("COUNTER_ANOMALY_BURST", 10).max(1),
+            burst_floor: var("COUNTER_ANOMALY_BURST_FLOOR", 500),
+            recent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Notes a message of a user and gets how many they sent in the minute up to it
+    fn note(&self, username: &str, at: NaiveDateTime) -> usize {
+        let mut recent = self.recent.lock().unwrap_or_else(PoisonError::into_inner);
+        let since = at - TimeDelta::minutes(1);
+
+        if recent.len() > RECENT_USERS {
+            recent.retain(|_, sent| sent.back().is_some_and(|last| *last > since));
+        }
+
+        let sent = recent.entry(username.to_owned()).or_default();
+
+        while sent.front().is_some_and(|first| *first <= since) {
+            sent.pop_front();
+        }
+
+        sent.push_back(at);
+        sent.len()
+    }
+}
+
+/// What ingest counted of a message
+#[derive(Debug)]
+pub struct Ingested {
+    pub words: Vec<(String, u32)>,
+    /// The anomaly the message was flagged as
+    pub anomaly_id: Option<i64>,
+}
+
+/// Why a message was flagged and the usage held back or kept for review
+#[derive(Debug, Default)]
+struct Flag {
+    kinds: Vec<&'static str>,
+    details: Vec<String>,
+    /// The usage over the limits, the whole message for rate limits and what doesn't fit
+    /// in the rest of the hour for bursts
+    excess: Vec<(String, u32)>,
+}
+
+/// Checks the words of a message against the limits of the guard
+fn inspect(
+    tx: &Transaction,
+    guard: &Guard,
+    username: &str,
+    words: &[(String, u32)],
+    sent: usize,
+    at: NaiveDateTime,
+) -> Result<Option<Flag>, Error> {
+    let mut flag = Flag::default();
+    let total = words.iter().map(|(_, count)| count).sum::<u32>();
+
+    // The usage of each word within the limits, lowered by every limit that is passed
+    let mut allowed = words.to_vec();
+
+    for (word, count) in allowed.iter_mut() {
+        if *count > guard.repeats {
+            flag.details.push(format!(
+                "\"{word}\" was repeated {count} times, over the limit of {}",
+                guard.repeats
+            ));
+            *count = guard.repeats;
+        }
+    }
+
+    if !flag.details.is_empty() {
+        flag.kinds.push(REPEAT);
+    }
+
+    if sent > guard.rate {
+        flag.kinds.push(RATE);
+        flag.details.push(format!(
+            "{sent} messages in a minute, over the limit of {}",
+            guard.rate
+        ));
+        allowed.clear();
+    }
+
+    // Compare the hour so far to the average hour of the week before it
+    let hour = time::hour(at);
+    let (current, past) = tx
+        .prepare_cached(
+            r#"
+            SELECT
+                COALESCE(SUM(CASE WHEN hour = ?2 THEN count END), 0),
+                COALESCE(SUM(CASE WHEN hour < ?2 THEN count END), 0)
+            FROM counter_history
+            WHERE username = ?1 AND hour >= ?3 AND hour <= ?2;
+            "#,
+        )?
+        .query_row(
+            params![
+                username,
+                &hour,
+                time::hour(at - TimeDelta::hours(BASELINE_HOURS))
+            ],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+        )?;
+
+    let baseline = past / BASELINE_HOURS;
+    let used = current + i64::from(total);
+    let limit = i64::from(guard.burst_floor).max(baseline * i64::from(guard.burst));
+
+    if used > limit {
+        flag.kinds.push(BURST);
+        flag.details.push(format!(
+            "{used} words this hour, usually {baseline} an hour"
+        ));
+
+        // Only what fits in the rest of the hour is within the limits
+        let mut budget = u32::try_from((limit - current).max(0)).unwrap_or(u32::MAX);
+
+        for (_, count) in allowed.iter_mut() {
+            *count = (*count).min(budget);
+            budget -= *count;
+        }
+    }
+
+    if flag.kinds.is_empty() {
+        return Ok(None);
+    }
+
+    flag.excess = words
+        .iter()
+        .map(|(word, count)| {
+            let within = allowed
+                .iter()
+                .find(|(w, _)| w == word)
+                .map_or(0, |(_, within)| *within);
+
+            (word.to_owned(), count - within)
+        })
+        .filter(|(_, excess)| *excess > 0)
+        .collect();
+
+    Ok(Some(flag))
+}
+
+/// Counts the words of a message and records them for a user in a single transaction,
+/// flagging the message when it goes over the limits of the guard
+///
+/// Flagged usage is held back or counted as the policy says and stored for admins to review.
+/// The phrases of the message are recorded with its words when phrase counting is on.
+pub fn ingest(
+    conn: &mut Connection,
+    normalizer: &Normalizer,
+    guard: &Guard,
+    username: &str,
+    message: &str,
+    at: NaiveDateTime,
+) -> Result<Ingested, Error> {
+    let words = counter::count(normalizer, message);
+    let words = counter::unblocked(conn, words)?;
+    let phrases = counter::phrases(normalizer, message);
+    let sent = guard.note(username, at);
+
+    let tx = conn.transaction()?;
+
+    let Some(flag) = inspect(&tx, guard, username, &words, sent, at)? else {
+        let words = counter::record(&tx, username, words, at)?;
+        counter::record_phrases(&tx, username, &phrases)?;
+        tx.commit()?;
+
+        return Ok(Ingested {
+            words,
+            anomaly_id: None,
+        });
+    };
+
+    let (counted, flagged) = match guard.policy {
+        Policy::Cap => {
+            let counted = words
+                .iter()
+                .filter_map(|(word, count)| {
+                    let held = flag
+                        .excess
+                        .iter()
+                        .find(|(w, _)| w == word)
+                        .map_or(0, |(_, held)| *held);
+
+                    (*count > held).then(|| (word.to_owned(), count - held))
+                })
+                .collect();
+
+            (counted, flag.excess)
+        }
+        Policy::Quarantine => (Vec::new(), words),
+        Policy::Log => (words, flag.excess),
+    };
+
+    let counted = counter::record(&tx, username, counted, at)?;
+
+    // Logged messages count their phrases, the phrases of held back ones wait for review
+    if guard.policy == Policy::Log {
+        counter::record_phrases(&tx, username, &phrases)?;
+    }
+
+    // The flags of a user in the same hour are merged into one anomaly to review
+    let hour = time::hour(at);
+    let pending = tx
+        .query_row(
+            r#"
+            SELECT id, kinds, details
+            FROM counter_anomalies
+            WHERE username = ?1 AND hour = ?2 AND policy = ?3 AND status = ?4;
+            "#,
+            params![username, &hour, guard.policy.name(), PENDING],
+            |row| {
+                Ok((
+                    row.get::<_, i64>("id")?,
+                    row.get::<_, String>("kinds")?,
+                    row.get::<_, String>("details")?,
+                ))
+            },
+        )
+        .optional()?;
+
+    let id = match pending {
+        Some((id, kinds, details)) => {
+            let mut kinds = kinds.split(',').collect::<Vec<_>>();
+            let mut details = details.split("; ").collect::<Vec<_>>();
+
+            for kind in &flag.kinds {
+                if !kinds.contains(kind) {
+                    kinds.push(kind);
+                }
+            }
+
+            for detail in &flag.details {
+                if details.len() < MAX_DETAILS && !details.contains(&detail.as_str()) {
+                    details.push(detail);
+                }
+            }
+
+            tx.execute(
+                "UPDATE counter_anomalies SET kinds = ?2, details = ?3 WHERE id = ?1;",
+                params![id, kinds.join(","), details.join("; ")],
+            )?;
+
+            id
+        }
+        None => {
+            tx.execute(
+                r#"
+                INSERT INTO counter_anomalies(username, kinds, details, policy, hour)
+                VALUES (?1, ?2, ?3, ?4, ?5);
+                "#,
+                params![
+                    username,
+                    flag.kinds.join(","),
+                    flag.details.join("; "),
+                    guard.policy.name(),
+                    &hour
+                ],
+            )?;
+
+            tx.last_insert_rowid()
+        }
+    };
+
+    for (word, count) in &flagged {
+        tx.execute(
+            r#"
+            INSERT INTO counter_anomaly_words(anomaly_id, word, count)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT(anomaly_id, word) DO UPDATE SET count = count + excluded.count;
+            "#,
+            params![id, word, count],
+        )?;
+    }
+
+    if guard.policy != Policy::Log {
+        for (phrase, count) in &phrases {
+            tx.execute(
+                r#"
+                INSERT INTO counter_anomaly_phrases(anomaly_id, phrase, count)
+                VALUES (?1, ?2, ?3)
+                ON CONFLICT(anomaly_id, phrase) DO UPDATE SET count = count + excluded.count;
+                "#,
+                params![id, phrase, count],
+            )?;
+        }
+    }
+
+    tx.commit()?;
+
+    Ok(Ingested {
+        words: counted,
+        anomaly_id: Some(id),
+    })
+}
+
+/// Approves or rejects a pending anomaly
+///
+/// Approving counts the usage and phrases that were held back and rejecting takes back the
+/// usage that was only logged, at the hour it was used. Usage that was already where it belongs is left
+/// as is.
+///
+/// Returns the id of the audit entry of the counts that changed, or `None` if none did or
+/// the anomaly isn't pending
+pub fn review(
+    conn: &mut Connection,
+    action: &Action,
+    id: i64,
+    approve: bool,
+) -> Result<Option<i64>, Error> {
+    let tx = conn.transaction()?;
+
+    let Some((username, policy, hour)) = tx
+        .query_row(
+            "SELECT username, policy, hour FROM counter_anomalies WHERE id = ?1 AND status = ?2;",
+            params![id, PENDING],
+            |row| {
+                Ok((
+                    row.get::<_, String>("username")?,
+                    row.get::<_, String>("policy")?,
+                    row.get::<_, String>("hour")?,
+                ))
+            },
+        )
+        .optional()?
+    else {
+        return Ok(None);
+    };
+
+    let sign = match (approve, Policy::parse(&policy)) {
+        // Held back usage is counted once it is approved
+        (true, Some(Policy::Cap | Policy::Quarantine)) => 1,
+        // Logged usage was counted, so it is taken back once it is rejected
+        (false, Some(Policy::Log)) => -1,
+        _ => 0,
+    };
+
+    let deltas = tx
+        .prepare("SELECT word, count FROM counter_anomaly_words WHERE anomaly_id = ?1;")?
+        .query_map([id], |row| {
+            Ok((row.get::<_, String>(0)?, sign * row.get::<_, i64>(1)?))
+        })?
+        .filter(|delta| !matches!(delta, Ok((_, 0))))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Only held back messages keep their phrases, so they are only counted when approved
+    let phrases = tx
+        .prepare("SELECT phrase, count FROM counter_anomaly_phrases WHERE anomaly_id = ?1;")?
+        .query_map([id], |row| {
+            Ok((row.get::<_, String>(0)?, sign * row.get::<_, i64>(1)?))
+        })?
+        .filter(|delta| !matches!(delta, Ok((_, 0))))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let audit_id = moderation::amend(&tx, action, &username, &hour, &deltas, &phrases)?;
+
+    tx.execute(
+        r#"
+        UPDATE counter_anomalies
+        SET status = ?2, reviewed_by = ?3, reviewed_at = CURRENT_TIMESTAMP, audit_id = ?4
+        WHERE id = ?1;
+        "#,
+        params![
+            id,
+            if approve { APPROVED } else { REJECTED },
+            action.actor,
+            audit_id
+        ],
+    )?;
+
+    tx.commit()?;
+
+    Ok(audit_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connect() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../database/counter.sql"))
+            .unwrap();
+        conn
+    }
+
+    fn guard(policy: Policy) -> Guard {
+        Guard {
+            policy,
+            repeats: 3,
+            rate: 5,
+            burst: 10,
+            burst_floor: 10,
+            recent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn counted(conn: &Connection, word: &str) -> i64 {
+        conn.query_row(
+            "SELECT COALESCE(SUM(count), 0) FROM counter WHERE word = ?1;",
+            [word],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    fn held(conn: &Connection, id: i64) -> Vec<(String, i64)> {
+        conn.prepare(
+            "SELECT word, count FROM counter_anomaly_words WHERE anomaly_id = ?1 ORDER BY word;",
+        )
+        .unwrap()
+        .query_map([id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+    }
+
+    #[test]
+    fn cap_test() {
+        let mut conn = connect();
+        let normalizer = Normalizer::new(false, &[]);
+        let guard = guard(Policy::Cap);
+        let at = NaiveDateTime::default();
+
+        // Repeats are counted up to the limit
+        let ingested = ingest(&mut conn, &normalizer, &guard, "bob", "gg gg gg gg gg", at).unwrap();
+        let id = ingested.anomaly_id.unwrap();
+        assert_eq!(counted(&conn, "gg"), 3);
+        assert_eq!(held(&conn, id), vec![("gg".to_string(), 2)]);
+
+        // A burst is counted up to what is left of the hour, 10 words less the 3 counted
+        let message = "one two three four five six seven eight nine ten";
+        let ingested = ingest(&mut conn, &normalizer, &guard, "bob", message, at).unwrap();
+        assert_eq!(ingested.anomaly_id, Some(id));
+        assert_eq!(ingested.words.len(), 7);
+        assert_eq!(counted(&conn, "seven"), 1);
+        assert_eq!(counted(&conn, "eight"), 0);
+
+        // Every flag of the hour is merged into the one anomaly
+        let ingested = ingest(&mut conn, &normalizer, &guard, "bob", "gg ten", at).unwrap();
+        assert_eq!(ingested.anomaly_id, Some(id));
+        assert!(ingested.words.is_empty());
+
+        let (kinds, anomalies) = conn
+            .query_row(
+                "SELECT MAX(kinds), COUNT(*) FROM counter_anomalies;",
+                [],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .unwrap();
+        assert_eq!(kinds, "repeat,burst");
+        assert_eq!(anomalies, 1);
+        assert_eq!(
+            held(&conn, id),
+            vec![
+                ("eight".to_string(), 1),
+                ("gg".to_string(), 3),
+                ("nine".to_string(), 1),
+                ("ten".to_string(), 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn log_test() {
+        let mut conn = connect();
+        let normalizer = Normalizer::new(false, &[]);
+        let guard = guard(Policy::Log);
+        let at = NaiveDateTime::default();
+
+        let ingested = ingest(&mut conn, &normalizer, &guard, "bob", "gg gg gg gg gg", at).unwrap();
+        assert_eq!(counted(&conn, "gg"), 5);
+        assert_eq!(
+            held(&conn, ingested.anomaly_id.unwrap()),
+            vec![("gg".to_string(), 2)]
+        );
+    }
+
+    fn review_action() -> Action<'static> {
+        Action {
+            name: "approve",
+            username: None,
+            word: None,
+            reason: None,
+            actor: "admin",
+        }
+    }
+
+    fn phrase(conn: &Connection, phrase: &str) -> i64 {
+        conn.query_row(
+            "SELECT COALESCE(SUM(count), 0) FROM counter_phrases WHERE phrase = ?1;",
+            [phrase],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    fn hourly(conn: &Connection, word: &str) -> i64 {
+        conn.query_row(
+            "SELECT COALESCE(SUM(count), 0) FROM counter_history WHERE word = ?1;",
+            [word],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn approve_test() {
+        let mut conn = connect();
+        let normalizer = Normalizer::new(false, &[]).with_phrase_length(2);
+        let guard = guard(Policy::Quarantine);
+        let at = NaiveDateTime::default();
+
+        let ingested = ingest(&mut conn, &normalizer, &guard, "bob", "gg gg gg gg wp", at).unwrap();
+        let id = ingested.anomaly_id.unwrap();
+        assert_eq!(counted(&conn, "gg"), 0);
+        assert_eq!(phrase(&conn, "gg wp"), 0);
+
+        let audit_id = review(&mut conn, &review_action(), id, true)
+            .unwrap()
+            .unwrap();
+
+        // The held back usage is counted in total, in its hour and in its phrases
+        assert_eq!(counted(&conn, "gg"), 4);
+        assert_eq!(hourly(&conn, "gg"), 4);
+        assert_eq!(counted(&conn, "wp"), 1);
+        assert_eq!(phrase(&conn, "gg gg"), 3);
+        assert_eq!(phrase(&conn, "gg wp"), 1);
+
+        let (status, audited) = conn
+            .query_row(
+                "SELECT status, audit_id FROM counter_anomalies WHERE id = ?1;",
+                [id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .unwrap();
+        assert_eq!(status, APPROVED);
+        assert_eq!(audited, audit_id);
+
+        // A reviewed anomaly can't be reviewed again
+        assert_eq!(
+            review(&mut conn, &review_action(), id, false).unwrap(),
+            None
+        );
+
+        // Undoing the approval takes the usage back
+        moderation::undo(&mut conn, &review_action(), audit_id).unwrap();
+        assert_eq!(counted(&conn, "gg"), 0);
+        assert_eq!(hourly(&conn, "gg"), 0);
+        assert_eq!(phrase(&conn, "gg wp"), 0);
+    }
+
+    #[test]
+    fn reject_test() {
+        let mut conn = connect();
+        let normalizer = Normalizer::new(false, &[]);
+        let at = NaiveDateTime::default();
+
+        // Rejecting logged usage takes back the excess
+        let logged = ingest(
+            &mut conn,
+            &normalizer,
+            &guard(Policy::Log),
+            "bob",
+            "gg gg gg gg gg",
+            at,
+        )
+        .unwrap()
+        .anomaly_id
+        .unwrap();
+
+        assert!(
+            review(&mut conn, &review_action(), logged, false)
+                .unwrap()
+                .is_some()
+        );
+        assert_eq!(counted(&conn, "gg"), 3);
+        assert_eq!(hourly(&conn, "gg"), 3);
+
+        // Rejecting held back usage changes nothing
+        let capped = ingest(
+            &mut conn,
+            &normalizer,
+            &guard(Policy::Cap),
+            "alice",
+            "wp wp wp wp",
+            at,
+        )
+        .unwrap()
+        .anomaly_id
+        .unwrap();
+
+        assert_eq!(
+            review(&mut conn, &review_action(), capped, false).unwrap(),
+            None
+        );
+        assert_eq!(counted(&conn, "wp"), 3);
+
+        let status = conn
+            .query_row(
+                "SELECT status FROM counter_anomalies WHERE id = ?1;",
+                [capped],
+                |row| row.get::<_, String>(0),
+            )
+            .unwrap();
+        assert_eq!(status, REJECTED);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{config::database::AppState, services::stream};
+
+use actix_web::{rt::time, web::Data};
+use rusqlite::{Connection, Error, params};
+use std::{collections::BTreeMap, time::Duration};
+
+/// How often the server checks for counts written by the maintenance commands
+const CHECK: Duration = Duration::from_secs(5);
+
+/// Records that a maintenance command wrote counts, with how much it added to each word
+///
+/// The commands run in their own process, so this is how the running server learns it has
+/// to rebuild what it cached. Commands that add nothing still record one change.
+pub fn record(
+    conn: &mut Connection,
+    deltas: &BTreeMap<(String, String), u64>,
+) -> Result<(), Error> {
+    let tx = conn.transaction()?;
+
+    if deltas.is_empty() {
+        tx.execute("INSERT INTO counter_changes(delta) VALUES (0);", [])?;
+    }
+
+    {
+        let mut stmt =
+            tx.prepare("INSERT INTO counter_changes(username, word, delta) VALUES (?1, ?2, ?3);")?;
+
+        for ((username, word), delta) in deltas {
+            stmt.execute(params![username, word, delta])?;
+        }
+    }
+
+    tx.commit()
+}
+
+/// Applies the recorded changes to the server, returning whether there were any
+///
+/// Cached data is rebuilt and the added counts are sent to the live streams, then the
+/// changes are removed.
+fn apply(state: &AppState, conn: &Connection) -> Result<bool, Error> {
+    let changes = conn
+        .prepare("SELECT id, username, word, delta FROM counter_changes ORDER BY id;")?
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>("id")?,
+                row.get::<_, Option<String>>("username")?,
+                row.get::<_, Option<String>>("word")?,
+                row.get::<_, u64>("delta")?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let Some(last) = changes.last().map(|(id, ..)| *id) else {
+        return Ok(false);
+    };
+
+    state.counter_changed();
+
+    let mut words = BTreeMap::<String, Vec<(String, u32)>>::new();
+
+    for (_, username, word, delta) in changes {
+        if let (Some(username), Some(word)) = (username, word) {
+            words
+                .entry(username)
+                .or_default()
+                .push((word, u32::try_from(delta).unwrap_or(u32::MAX)));
+        }
+    }
+
+    for (username, words) in &words {
+        stream::publish(state, conn, username, words)?;
+    }
+
+    conn.execute("DELETE FROM counter_changes WHERE id <= ?1;", [last])?;
+
+    Ok(true)
+}
+
+/// Applies the changes the maintenance commands record for as long as the server runs
+pub async fn watch(state: Data<AppState>) {
+    let mut interval = time::interval(CHECK);
+
+    loop {
+        interval.tick().await;
+
+        let result = state
+            .pool
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|conn| apply(&state, &conn).map_err(|e| e.to_string()));
+
+        if let Err(e) = result {
+            eprintln!("Failed to apply the changes of a command: {e}");
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use crate::{
+    services::index,
+    utils::{emoji, normalize::Normalizer, string, time},
+};
+
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, Error, Transaction, params};
+
+/// Counts how often each word is used in a message, keeping the first seen order
+///
+/// Emoji and custom emotes are split off the words they are written against and counted as
+/// words of their own.
+pub fn count(normalizer: &Normalizer, message: &str) -> Vec<(String, u32)> {
+    let mut words: Vec<(String, u32)> = Vec::new();
+
+    let tokens = string::tokenize(message);
+
+    for segment in tokens.iter().flat_map(|token| emoji::split(token)) {
+        let Some(token) = normalizer.normalize(segment) else {
+            continue;
+        };
+
+        match words.iter_mut().find(|(word, _)| *word == token) {
+            Some((_, count)) => *count += 1,
+            None => words.push((token, 1)),
+        }
+    }
+
+    words
+}
+
+/// Counts how often each phrase of consecutive words is used in a message, keeping the first
+/// seen order
+///
+/// Phrases run from two words up to the configured phrase length. Emoji and tokens that
+/// normalize to nothing break the run of words, and phrases made of stopwords only are left
+/// out.
+pub fn phrases(normalizer: &Normalizer, message: &str) -> Vec<(String, u32)> {
+    let mut phrases: Vec<(String, u32)> = Vec::new();
+    let length = normalizer.phrase_length();
+
+    if length < 2 {
+        return phrases;
+    }
+
+    let tokens = string::tokenize(message)
+        .iter()
+        .flat_map(|token| {
+            emoji::split(token)
+                .into_iter()
+                .map(|segment| match emoji::is_emoji(segment) {
+                    true => None,
+                    false => normalizer.normalize(segment),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    for run in tokens.split(Option::is_none) {
+        let words = run.iter().flatten().map(String::as_str).collect::<Vec<_>>();
+
+        for n in 2..=length {
+            for window in words.windows(n) {
+                if window.iter().all(|word| normalizer.is_stopword(word)) {
+                    continue;
+                }
+
+                let phrase = window.join(" ");
+
+                match phrases.iter_mut().find(|(p, _)| *p == phrase) {
+                    Some((_, count)) => *count += 1,
+                    None => phrases.push((phrase, 1)),
+                }
+            }
+        }
+    }
+
+    phrases
+}
+
+/// Leaves out the words admins have blocked
+pub fn unblocked(
+    conn: &Connection,
+    words: Vec<(String, u32)>,
+) -> Result<Vec<(String, u32)>, Error> {
+    let mut blocked = conn.prepare_cached("SELECT 1 FROM counter_blocklist WHERE word = ?1;")?;
+    let mut kept = Vec::with_capacity(words.len());
+
+    for (word, count) in words {
+        if !blocked.exists([&word])? {
+            kept.push((word, count));
+        }
+    }
+
+    Ok(kept)
+}
+
+/// Records counted words for a user inside an open transaction
+///
+/// Every word updates the running total in `counter` and the hourly bucket in
+/// `counter_history`. Words on the blocklist are skipped.
+///
+/// Returns the words that were recorded
+pub fn record(
+    tx: &Transaction,
+    username: &str,
+    words: Vec<(String, u32)>,
+    at: NaiveDateTime,
+) -> Result<Vec<(String, u32)>, Error> {
+    let words = unblocked(tx, words)?;
+    let hour = time::hour(at);
+
+    for (word, count) in &words {
+        // Update the running total, creating the row if this is a new word for the user
+        let updated = tx.execute(
+            r#"
+            UPDATE counter
+            SET count = count + ?3
+            WHERE username = ?1 AND word = ?2;
+            "#,
+            params![username, word, count],
+        )?;
+
+        if updated == 0 {
+            tx.execute(
+                r#"
+                INSERT INTO counter(username, word, count)
+                VALUES (?1, ?2, ?3);
+                "#,
+                params![username, word, count],
+            )?;
+
+            index::add(tx, index::USERNAME, username)?;
+            index::add(tx, index::WORD, word)?;
+        }
+
+        // Record when the usage happened
+        tx.execute(
+            r#"
+            INSERT INTO counter_history(username, word, hour, count)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(username, word, hour) DO UPDATE SET count = count + excluded.count;
+            "#,
+            params![username, word, hour, count],
+        )?;
+    }
+
+    Ok(words)
+}
+
+/// Records counted phrases for a user inside an open transaction
+///
+/// Phrases holding a word on the blocklist are skipped.
+pub fn record_phrases(
+    tx: &Transaction,
+    username: &str,
+    phrases: &[(String, u32)],
+) -> Result<(), Error> {
+    let mut blocked = tx.prepare_cached("SELECT 1 FROM counter_blocklist WHERE word = ?1;")?;
+
+    'phrases: for (phrase, count) in phrases {
+        for word in phrase.split(' ') {
+            if blocked.exists([word])? {
+                continue 'phrases;
+            }
+        }
+
+        let updated = tx.execute(
+            r#"
+            UPDATE counter_phrases
+            SET count = count + ?3
+            WHERE username = ?1 AND phrase = ?2;
+            "#,
+            params![username, phrase, count],
+        )?;
+
+        if updated == 0 {
+            tx.execute(
+                r#"
+                INSERT INTO counter_phrases(username, phrase, length, count)
+                VALUES (?1, ?2, ?3, ?4);
+                "#,
+                params![username, phrase, phrase.split(' ').count(), count],
+            )?;
+
+            index::add(tx, index::PHRASE, phrase)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces the stored stopwords with the configured ones
+pub fn sync_stopwords(conn: &mut Connection, normalizer: &Normalizer) -> Result<(), Error> {
+    let tx = conn.transaction()?;
+
+    tx.execute("DELETE FROM counter_stopwords;", [])?;
+
+    for word in normalizer.stopwords() {
+        tx.execute(
+            "INSERT OR IGNORE INTO counter_stopwords(word) VALUES (?1);",
+            [word],
+        )?;
+    }
+
+    tx.commit()
+}
+
+/// Runs every stored word through the normalizer and merges the duplicates it creates
+///
+/// Words that normalize to nothing are removed. Words that only exist after the merge
+/// are added to the n-gram index and the words that no longer exist are taken out of it.
+/// Phrases are merged the same way.
+///
+/// Returns the amount of words that were changed
+pub fn renormalize(conn: &mut Connection, normalizer: &Normalizer) -> Result<usize, Error> {
+    let tx = conn.transaction()?;
+
+    // Find the words that normalize differently from how they are stored
+    let changed = tx
+        .prepare(
+            r#"
+            SELECT word FROM counter
+            UNION
+            SELECT word FROM counter_history;
+            "#,
+        )?
+        .query_map([], |row| row.get::<_, String>("word"))?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .map(|word| {
+            let normalized = normalizer.normalize(&word);
+            (word, normalized)
+        })
+        .filter(|(word, normalized)| normalized.as_ref() != Some(word))
+        .collect::<Vec<_>>();
+
+    for (word, normalized) in &changed {
+        let rows = tx
+            .prepare("SELECT id, username, count FROM counter WHERE word = ?1;")?
+            .query_map([word], |row| {
+                Ok((
+                    row.get::<_, i64>("id")?,
+                    row.get::<_, String>("username")?,
+                    row.get::<_, i64>("count")?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (id, username, count) in rows {
+            // Merge into the user's existing row for the normalized word, or rename this one
+            let merged = match normalized {
+                Some(n) => tx.execute(
+                    r#"
+                    UPDATE counter
+                    SET count = count + ?3
+                    WHERE username = ?1 AND word = ?2;
+                    "#,
+                    params![username, n, count],
+                )?,
+                None => 0,
+            };
+
+            match (normalized, merged) {
+                (Some(n), 0) => {
+                    index::add(&tx, index::WORD, n)?;
+                    tx.execute(
+                        "UPDATE counter SET word = ?2 WHERE id = ?1;",
+                        params![id, n],
+                    )?
+                }
+                _ => tx.execute("DELETE FROM counter WHERE id = ?1;", [id])?,
+            };
+        }
+
+        if let Some(n) = normalized {
+            tx.execute(
+                r#"
+                INSERT INTO counter_history(username, word, hour, count)
+                SELECT username, ?2, hour, count
+                FROM counter_history
+                WHERE word = ?1
+                ON CONFLICT(username, word, hour) DO UPDATE SET count = count + excluded.count;
+                "#,
+                params![word, n],
+            )?;
+        }
+
+        tx.execute("DELETE FROM counter_history WHERE word = ?1;", [word])?;
+        index::remove(&tx, index::WORD, word)?;
+    }
+
+    // Phrases are renormalized word by word, dropping the ones that lose a word
+    let phrases = tx
+        .prepare("SELECT DISTINCT phrase FROM counter_phrases;")?
+        .query_map([], |row| row.get::<_, String>("phrase"))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for phrase in &phrases {
+        let normalized = phrase
+            .split(' ')
+            .map(|word| normalizer.normalize(word))
+            .collect::<Option<Vec<_>>>()
+            .map(|words| words.join(" "));
+
+        if normalized.as_ref() == Some(phrase) {
+            continue;
+        }
+
+        if let Some(n) = normalized {
+            tx.execute(
+                r#"
+                INSERT INTO counter_phrases(username, phrase, length, count)
+                SELECT username, ?2, length, count
+                FROM counter_phrases
+                WHERE phrase = ?1
+                ON CONFLICT(username, phrase) DO UPDATE SET count = count + excluded.count;
+                "#,
+                params![phrase, n],
+            )?;
+
+            index::add(&tx, index::PHRASE, &n)?;
+        }
+
+        tx.execute("DELETE FROM counter_phrases WHERE phrase = ?1;", [phrase])?;
+        index::remove(&tx, index::PHRASE, phrase)?;
+    }
+
+    tx.commit()?;
+
+    Ok(changed.len())
+}
@@ -0,0 +1,229 @@
+use crate::{dtos::errors, utils::string};
+
+use actix_web::{
+    HttpRequest, HttpResponse, error,
+    http::header::{self, ContentDisposition, DispositionParam, DispositionType},
+    web::Bytes,
+};
+use futures_util::stream;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Row, params_from_iter};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::{Arc, LazyLock};
+use tokio::sync::{Semaphore, mpsc};
+
+/// The amount of formatted rows buffered before the database thread waits on the client
+const BUFFER_ROWS: usize = 64;
+
+/// The most exports that may run at once, each holds a pooled connection until it is read
+const MAX_EXPORTS: usize = 2;
+
+static EXPORTS: LazyLock<Arc<Semaphore>> = LazyLock::new(|| Arc::new(Semaphore::new(MAX_EXPORTS)));
+
+#[derive(Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Csv,
+    Ndjson,
+}
+
+impl Format {
+    /// Picks the format from `?format=` or else the `Accept` header, defaulting to JSON
+    ///
+    /// Of the media types `Accept` lists, the supported one with the highest quality wins,
+    /// the first listed on a tie.
+    pub fn negotiate(req: &HttpRequest, format: &Option<String>) -> Self {
+        if let Some(format) = format {
+            return match format.as_str() {
+                "csv" => Format::Csv,
+                "ndjson" => Format::Ndjson,
+                _ => Format::Json,
+            };
+        }
+
+        let accept = req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .unwrap_or_default();
+
+        let mut best = (Format::Json, 0.0);
+
+        for entry in accept.split(',') {
+            let mut parts = entry.split(';').map(str::trim);
+            let media_type = parts.next().unwrap_or_default().to_ascii_lowercase();
+
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.parse::<f32>().ok())
+                .unwrap_or(0.0);
+
+            let format = match media_type.as_str() {
+                "text/csv" => Format::Csv,
+                "application/x-ndjson" | "application/ndjson" => Format::Ndjson,
+                "application/json" | "application/*" | "*/*" => Format::Json,
+                _ => continue,
+            };
+
+            if quality > best.1 {
+                best = (format, quality);
+            }
+        }
+
+        best.0
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Csv => "csv",
+            Format::Ndjson => "ndjson",
+        }
+    }
+
+    fn line<T: Serialize>(&self, item: &T, columns: &[&str]) -> Result<String, serde_json::Error> {
+        match self {
+            Format::Csv => {
+                // Round trip through the JSON text so floats keep their short `f32` form
+                let value: Value = serde_json::from_str(&serde_json::to_string(item)?)?;
+                let fields = columns
+                    .iter()
+                    .map(|column| match value.get(column) {
+                        Some(Value::String(s)) => string::csv_field(s),
+                        Some(Value::Null) | None => String::new(),
+                        Some(v) => string::csv_field(&v.to_string()),
+                    })
+                    .collect::<Vec<_>>();
+
+                Ok(fields.join(",") + "\n")
+            }
+            _ => Ok(serde_json::to_string(item)? + "\n"),
+        }
+    }
+}
+
+/// Streams every row of a query to the client without paginating
+///
+/// The query runs on its own thread and each row is sent as soon as it is read from the
+/// cursor, the bounded channel keeps only a few rows in memory when the client is slow.
+/// Only a few exports run at once so slow clients can not hold every pooled connection,
+/// any more are turned away with `503 Service Unavailable`.
+pub fn stream<T, F>(
+    pool: Pool<SqliteConnectionManager>,
+    query: String,
+    values: Vec<String>,
+    name: &str,
+    columns: &'static [&'static str],
+    format: Format,
+    from_row: F,
+) -> HttpResponse
+where
+    T: Serialize,
+    F: Fn(&Row) -> Result<T, rusqlite::Error> + Send + 'static,
+{
+    let Ok(permit) = Arc::clone(&EXPORTS).try_acquire_owned() else {
+        return HttpResponse::ServiceUnavailable()
+            .insert_header((header::RETRY_AFTER, "5"))
+            .json(errors::global::Generic {
+                error: "ServiceUnavailable".to_string(),
+                message: "Too many exports are running, try again shortly.".to_string(),
+            });
+    };
+
+    let (tx, rx) = mpsc::channel::<Result<Bytes, String>>(BUFFER_ROWS);
+    let content_type = format.content_type();
+    let filename = format!("{name}.{}", format.extension());
+
+    std::thread::spawn(move || {
+        // Hold the permit until the export is done or the client has gone away
+        let _permit = permit;
+
+        let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+            let conn = pool.get()?;
+            let mut stmt = conn.prepare(&query)?;
+            let mut rows = stmt.query(params_from_iter(&values))?;
+
+            if format == Format::Csv
+                && tx
+                    .blocking_send(Ok(Bytes::from(columns.join(",") + "\n")))
+                    .is_err()
+            {
+                return Ok(());
+            }
+
+            while let Some(row) = rows.next()? {
+                let line = format.line(&from_row(row)?, columns)?;
+
+                // Stop reading when the client has gone away
+                if tx.blocking_send(Ok(Bytes::from(line))).is_err() {
+                    break;
+                }
+            }
+
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            let _ = tx.blocking_send(Err(e.to_string()));
+        }
+    });
+
+    let body = stream::unfold(rx, |mut rx| async move {
+        rx.recv()
+            .await
+            .map(|chunk| (chunk.map_err(error::ErrorInternalServerError), rx))
+    });
+
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename)],
+        })
+        .streaming(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn negotiate_test() {
+        let negotiate = |accept: &str| {
+            let req = TestRequest::default()
+                .insert_header((header::ACCEPT, accept))
+                .to_http_request();
+            Format::negotiate(&req, &None)
+        };
+
+        assert_eq!(negotiate("text/csv"), Format::Csv);
+        assert_eq!(negotiate("application/json, text/csv;q=0.1"), Format::Json);
+        assert_eq!(negotiate("application/json;q=0.5, text/csv"), Format::Csv);
+        assert_eq!(
+            negotiate("text/csv;q=0, application/x-ndjson"),
+            Format::Ndjson
+        );
+        assert_eq!(negotiate("text/csvx, image/png"), Format::Json);
+        assert_eq!(negotiate("application/x-ndjson, text/csv"), Format::Ndjson);
+
+        // The parameter wins over the header
+        let req = TestRequest::default()
+            .insert_header((header::ACCEPT, "text/csv"))
+            .to_http_request();
+        assert_eq!(
+            Format::negotiate(&req, &Some("json".to_string())),
+            Format::Json
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use crate::services::index;
+
+use rusqlite::{Connection, Error, OptionalExtension, params};
+
+/// The view of counts to read, with aliases resolved to their identity unless `raw`
+///
+/// Both leave out hidden and private users and hidden words, and show anonymized users
+/// under their label.
+pub fn source(raw: bool) -> &'static str {
+    if raw {
+        "counter_raw"
+    } else {
+        "counter_resolved"
+    }
+}
+
+/// The view of hourly counts to read, with aliases resolved to their identity unless `raw`
+pub fn history_source(raw: bool) -> &'static str {
+    if raw {
+        "counter_history_raw"
+    } else {
+        "counter_history_resolved"
+    }
+}
+
+/// The view of daily counts to read, with aliases resolved to their identity unless `raw`
+pub fn daily_source(raw: bool) -> &'static str {
+    if raw {
+        "counter_daily_raw"
+    } else {
+        "counter_daily_resolved"
+    }
+}
+
+/// The view of phrase counts to read, with aliases resolved to their identity unless `raw`
+pub fn phrase_source(raw: bool) -> &'static str {
+    if raw {
+        "counter_phrases_raw"
+    } else {
+        "counter_phrases_resolved"
+    }
+}
+
+/// Gets the identity a counter username belongs to, or the username if it has none
+pub fn resolve(conn: &Connection, username: &str) -> Result<String, Error> {
+    let identity = conn
+        .query_row(
+            "SELECT identity FROM counter_aliases WHERE alias = ?1;",
+            [username],
+            |row| row.get::<_, String>("identity"),
+        )
+        .optional()?;
+
+    Ok(identity.unwrap_or(username.to_owned()))
+}
+
+/// Moves the counts of every alias of an identity onto the identity's own username
+///
+/// The aliases are kept so new counts under an old name still resolve.
+///
+/// Returns the amount of rows that were moved
+pub fn merge(conn: &mut Connection, identity: &str) -> Result<usize, Error> {
+    let tx = conn.transaction()?;
+
+    let aliases = tx
+        .prepare("SELECT alias FROM counter_aliases WHERE identity = ?1 AND alias != ?1;")?
+        .query_map([identity], |row| row.get::<_, String>("alias"))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut moved = 0;
+
+    for alias in &aliases {
+        moved += tx.query_row(
+            "SELECT COUNT(*) FROM counter WHERE username = ?1;",
+            [alias],
+            |row| row.get::<_, usize>(0),
+        )?;
+
+        // Add the alias's counts to the identity's rows, then move the words it doesn't have yet
+        tx.execute(
+            r#"
+            UPDATE counter
+            SET count = count + (
+                SELECT aliased.count
+                FROM counter AS aliased
+                WHERE aliased.username = ?1 AND aliased.word = counter.word
+            )
+            WHERE username = ?2 AND word IN (SELECT word FROM counter WHERE username = ?1);
+            "#,
+            params![alias, identity],
+        )?;
+
+        tx.execute(
+            r#"
+            DELETE FROM counter
+            WHERE username = ?1 AND word IN (SELECT word FROM counter WHERE username = ?2);
+            "#,
+            params![alias, identity],
+        )?;
+
+        tx.execute(
+            "UPDATE counter SET username = ?2 WHERE username = ?1;",
+            params![alias, identity],
+        )?;
+
+        tx.execute(
+            r#"
+            INSERT INTO counter_history(username, word, hour, count)
+            SELECT ?2, word, hour, count
+            FROM counter_history
+            WHERE username = ?1
+            ON CONFLICT(username, word, hour) DO UPDATE SET count = count + excluded.count;
+            "#,
+            params![alias, identity],
+        )?;
+
+        tx.execute("DELETE FROM counter_history WHERE username = ?1;", [alias])?;
+
+        tx.execute(
+            r#"
+            INSERT INTO counter_phrases(username, phrase, length, count)
+            SELECT ?2, phrase, length, count
+            FROM counter_phrases
+            WHERE username = ?1
+            ON CONFLICT(username, phrase) DO UPDATE SET count = count + excluded.count;
+            "#,
+            params![alias, identity],
+        )?;
+
+        tx.execute("DELETE FROM counter_phrases WHERE username = ?1;", [alias])?;
+    }
+
+    // The moved rows are counted under the identity now
+    if moved > 0 {
+        index::add(&tx, index::USERNAME, identity)?;
+    }
+
+    tx.commit()?;
+
+    Ok(moved)
+}
@@ -0,0 +1,287 @@
+use crate::{
+    services::counter,
+    utils::{normalize::Normalizer, string, time},
+};
+
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, Error, params};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum Format {
+    /// A Discord chat export with a `messages` array
+    Json,
+    /// One `username: message` per line
+    Text,
+    /// A header row naming the id, timestamp, username and message columns
+    Csv,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Format::Json),
+            "text" | "txt" => Some(Format::Text),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+
+    /// Picks the format from the file extension, defaulting to text
+    pub fn detect(path: &Path) -> Self {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| Format::parse(&extension.to_lowercase()))
+            .unwrap_or(Format::Text)
+    }
+}
+
+/// A chat message read from an export
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    /// The id of the message, or its row number or a hash of its line if the export has none
+    pub id: String,
+    pub username: String,
+    pub content: String,
+    pub at: NaiveDateTime,
+}
+
+/// What an import counted, or would count on a dry run
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub imported: usize,
+    /// Messages that were imported before
+    pub skipped: usize,
+    /// Messages from usernames the counter can't store
+    pub invalid: usize,
+    /// How much each word was added to for each username
+    pub deltas: BTreeMap<(String, String), u64>,
+}
+
+/// Reads the messages of an export, dating the ones without a timestamp at `now`
+pub fn read(format: &Format, input: &str, now: NaiveDateTime) -> Result<Vec<Message>, String> {
+    match format {
+        Format::Json => read_json(input, now),
+        Format::Text => Ok(read_text(input, now)),
+        Format::Csv => read_csv(input, now),
+    }
+}
+
+fn read_json(input: &str, now: NaiveDateTime) -> Result<Vec<Message>, String> {
+    let export: Value = serde_json::from_str(input).map_err(|e| e.to_string())?;
+
+    let messages = export["messages"]
+        .as_array()
+        .ok_or("Expected a `messages` array.")?;
+
+    Ok(messages
+        .iter()
+        .enumerate()
+        .filter_map(|(i, message)| {
+            let id = match &message["id"] {
+                Value::String(id) => id.to_owned(),
+                Value::Number(id) => id.to_string(),
+                _ => (i + 1).to_string(),
+            };
+
+            Some(Message {
+                id,
+                username: message["author"]["name"].as_str()?.to_owned(),
+                content: message["content"].as_str()?.to_owned(),
+                at: message["timestamp"]
+                    .as_str()
+                    .and_then(time::parse)
+                    .unwrap_or(now),
+            })
+        })
+        .collect())
+}
+
+/// A 64-bit FNV-1a hash, which unlike the std hashers stays the same across releases
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Reads `username: message` lines, leaving out the lines that don't start with a username
+///
+/// Each line is identified by a hash of its content and how many of the same line came
+/// before it, so editing or adding lines doesn't change the ids of the others.
+fn read_text(input: &str, now: NaiveDateTime) -> Vec<Message> {
+    let mut seen = HashMap::<&str, usize>::new();
+
+    input
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (username, content) = line.split_once(':')?;
+            let username = username.trim();
+
+            if !string::valid_username(username) {
+                return None;
+            }
+
+            let occurrence = seen.entry(line).or_default();
+            *occurrence += 1;
+
+            Some(Message {
+                id: format!("{:016x}-{occurrence}", fnv1a(line)),
+                username: username.to_owned(),
+                content: content.trim().to_owned(),
+                at: now,
+            })
+        })
+        .collect()
+}
+
+fn read_csv(input: &str, now: NaiveDateTime) -> Result<Vec<Message>, String> {
+    let mut records = string::csv_records(input).into_iter();
+
+    let header = records
+        .next()
+        .ok_or("Expected a header row.")?
+        .into_iter()
+        .map(|name| name.trim().to_lowercase())
+        .collect::<Vec<_>>();
+
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|name| names.contains(&name.as_str()))
+    };
+
+    let id = column(&["id", "messageid", "message_id"]);
+    let at = column(&["timestamp", "date", "time"]);
+    let username = column(&["username", "author", "user"]).ok_or("Expected a username column.")?;
+    let content = column(&["message", "content"]).ok_or("Expected a message column.")?;
+
+    Ok(records
+        .enumerate()
+        .filter_map(|(i, record)| {
+            Some(Message {
+                id: id
+                    .and_then(|id| record.get(id).cloned())
+                    .unwrap_or((i + 1).to_string()),
+                username: record.get(username)?.trim().to_owned(),
+                content: record.get(content)?.to_owned(),
+                at: at
+                    .and_then(|at| record.get(at))
+                    .and_then(|at| time::parse(at))
+                    .unwrap_or(now),
+            })
+        })
+        .collect())
+}
+
+fn tally(summary: &mut Summary, username: &str, words: &[(String, u32)]) {
+    for (word, count) in words {
+        *summary
+            .deltas
+            .entry((username.to_owned(), word.to_owned()))
+            .or_default() += u64::from(*count);
+    }
+}
+
+/// Counts the messages of an export the same way live ingest does
+///
+/// Messages are committed `chunk` at a time together with their ids, so an interrupted
+/// import picks up after the last committed chunk when run again. A dry run only reads.
+pub fn import(
+    conn: &mut Connection,
+    normalizer: &Normalizer,
+    source: &str,
+    messages: &[Message],
+    chunk: usize,
+    dry_run: bool,
+) -> Result<Summary, Error> {
+    let mut summary = Summary::default();
+
+    if dry_run {
+        let mut seen = HashSet::new();
+
+        for message in messages {
+            let imported = conn
+                .prepare_cached(
+                    "SELECT 1 FROM counter_imports WHERE source = ?1 AND message_id = ?2;",
+                )?
+                .exists(params![source, &message.id])?;
+
+            if imported || !seen.insert(&message.id) {
+                summary.skipped += 1;
+            } else if !string::valid_username(&message.username) {
+                summary.invalid += 1;
+            } else {
+                let words = counter::count(normalizer, &message.content);
+                let words = counter::unblocked(conn, words)?;
+
+                tally(&mut summary, &message.username, &words);
+                summary.imported += 1;
+            }
+        }
+
+        return Ok(summary);
+    }
+
+    for messages in messages.chunks(chunk.max(1)) {
+        let tx = conn.transaction()?;
+
+        for message in messages {
+            let new = tx.execute(
+                "INSERT OR IGNORE INTO counter_imports(source, message_id) VALUES (?1, ?2);",
+                params![source, &message.id],
+            )?;
+
+            if new == 0 {
+                summary.skipped += 1;
+            } else if !string::valid_username(&message.username) {
+                summary.invalid += 1;
+            } else {
+                let words = counter::count(normalizer, &message.content);
+                let words = counter::record(&tx, &message.username, words, message.at)?;
+                let phrases = counter::phrases(normalizer, &message.content);
+                counter::record_phrases(&tx, &message.username, &phrases)?;
+
+                tally(&mut summary, &message.username, &words);
+                summary.imported += 1;
+            }
+        }
+
+        tx.commit()?;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_text_test() {
+        let now = NaiveDateTime::default();
+        let ids = |input: &str| {
+            read_text(input, now)
+                .into_iter()
+                .map(|message| (message.username, message.id))
+                .collect::<Vec<_>>()
+        };
+
+        let messages = read_text("bob: hi: there\n12:30 bob: hi\n\nalice:  yo ", now);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].username, "bob");
+        assert_eq!(messages[0].content, "hi: there");
+        assert_eq!(messages[1].username, "alice");
+        assert_eq!(messages[1].content, "yo");
+
+        // Repeated lines are told apart, and prepending a line keeps the other ids
+        let before = ids("bob: gg\nbob: gg");
+        let after = ids("alice: hi\nbob: gg\nbob: gg");
+        assert_ne!(before[0], before[1]);
+        assert_eq!(before[..], after[1..]);
+    }
+}
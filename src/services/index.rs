@@ -0,0 +1,214 @@
+use crate::utils::{
+    emoji,
+    string::{self, Similarity},
+};
+
+use rusqlite::{Connection, Error, functions::FunctionFlags, params};
+
+/// The kinds of terms kept in the index, named after the column they come from
+pub const USERNAME: &str = "username";
+pub const WORD: &str = "word";
+pub const PHRASE: &str = "phrase";
+
+/// Adds a username, word or phrase to the n-gram index
+///
+/// Single characters are indexed for the fuzzy subsequence search, which needs every
+/// character of the query, and bigrams for the words that share any with another word.
+/// Words that are emoji or custom emotes are also registered for the emoji leaderboard.
+pub fn add(conn: &Connection, kind: &str, term: &str) -> Result<(), Error> {
+    if kind == WORD && emoji::is_emoji(term) {
+        conn.prepare_cached("INSERT OR IGNORE INTO counter_emoji(word) VALUES (?1);")?
+            .execute([term])?;
+    }
+
+    let mut stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO counter_grams(kind, gram, term) VALUES (?1, ?2, ?3);",
+    )?;
+
+    for gram in string::ngrams(term, 1)
+        .iter()
+        .chain(&string::ngrams(term, 2))
+    {
+        stmt.execute(params![kind, gram, term])?;
+    }
+
+    Ok(())
+}
+
+/// Removes a username, word or phrase from the n-gram index once nothing is counted or
+/// shown under it
+pub fn remove(conn: &Connection, kind: &str, term: &str) -> Result<(), Error> {
+    let query = match kind {
+        USERNAME => {
+            r#"
+            SELECT
+                EXISTS (SELECT 1 FROM counter WHERE username = ?1)
+                OR EXISTS (SELECT 1 FROM counter_identities WHERE name = ?1)
+                OR EXISTS (SELECT 1 FROM counter_privacy WHERE label = ?1);
+            "#
+        }
+        WORD => "SELECT EXISTS (SELECT 1 FROM counter WHERE word = ?1);",
+        _ => "SELECT EXISTS (SELECT 1 FROM counter_phrases WHERE phrase = ?1);",
+    };
+
+    let counted: bool = conn
+        .prepare_cached(query)?
+        .query_row([term], |row| row.get(0))?;
+
+    if counted {
+        return Ok(());
+    }
+
+    conn.prepare_cached("DELETE FROM counter_grams WHERE kind = ?1 AND term = ?2;")?
+        .execute(params![kind, term])?;
+
+    if kind == WORD {
+        conn.prepare_cached("DELETE FROM counter_emoji WHERE word = ?1;")?
+            .execute([term])?;
+    }
+
+    Ok(())
+}
+
+/// Rebuilds the n-gram index from the distinct usernames, words and phrases, with the
+/// identities and anonymized labels the resolved counts are shown under
+///
+/// Returns the amount of terms that were indexed
+pub fn rebuild(conn: &mut Connection) -> Result<usize, Error> {
+    let tx = conn.transaction()?;
+
+    tx.execute("DELETE FROM counter_grams;", [])?;
+    tx.execute("DELETE FROM counter_emoji;", [])?;
+
+    let terms = tx
+        .prepare(
+            r#"
+            SELECT DISTINCT 'username' AS kind, username AS term FROM counter
+            UNION
+            SELECT DISTINCT 'word' AS kind, word AS term FROM counter
+            UNION
+            SELECT DISTINCT 'phrase' AS kind, phrase AS term FROM counter_phrases
+            UNION
+            SELECT 'username' AS kind, name AS term FROM counter_identities
+            UNION
+            SELECT 'username' AS kind, label AS term FROM counter_privacy WHERE label IS NOT NULL;
+            "#,
+        )?
+        .query_map([], |row| {
+            Ok((row.get::<_, String>("kind")?, row.get::<_, String>("term")?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (kind, term) in &terms {
+        add(&tx, kind, term)?;
+    }
+
+    tx.commit()?;
+
+    Ok(terms.len())
+}
+
+/// Builds the index if words are counted but nothing is indexed, like the counts written
+/// before the index existed
+///
+/// Everything that writes counts keeps the index in sync, the `reindex` command rebuilds it
+/// in full.
+pub fn ensure(conn: &mut Connection) -> Result<(), Error> {
+    let missing: bool = conn.query_row(
+        r#"
+        SELECT
+            (EXISTS (SELECT 1 FROM counter) OR EXISTS (SELECT 1 FROM counter_phrases))
+            AND NOT EXISTS (SELECT 1 FROM counter_grams);
+        "#,
+        [],
+        |row| row.get(0),
+    )?;
+
+    if missing {
+        rebuild(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Limits a `counter` or `counter_phrases` column to the terms holding every character of a
+/// fuzzy query
+///
+/// `_` is left out because `LIKE` lets it stand for any character, so the candidates
+/// are exactly the rows the subsequence `LIKE` could match.
+/// The characters are bound as the parameters numbered from `first`, and returned in
+/// that order with the clause. `column` must be one of the index kinds.
+pub fn subsequence_filter(column: &str, query: &str, first: usize) -> (String, Vec<String>) {
+    let grams = string::ngrams(query, 1)
+        .into_iter()
+        .filter(|gram| gram != "_")
+        .collect::<Vec<_>>();
+
+    if grams.is_empty() {
+        return ("TRUE".to_string(), grams);
+    }
+
+    let placeholders = (first..first + grams.len())
+        .map(|i| format!("?{i}"))
+        .collect::<Vec<_>>();
+
+    let clause = format!(
+        r#"
+        {column} IN (
+            SELECT term
+            FROM counter_grams
+            WHERE kind = '{column}' AND gram IN ({})
+            GROUP BY term
+            HAVING COUNT(*) = {}
+        )
+        "#,
+        placeholders.join(", "),
+        grams.len()
+    );
+
+    (clause, grams)
+}
+
+/// Registers `similarity(algorithm, string1, string2)` so searches can score every term,
+/// an unknown algorithm scores `NULL`
+pub fn register(conn: &Connection) -> Result<(), Error> {
+    conn.create_scalar_function(
+        "similarity",
+        3,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let name = ctx.get::<String>(0)?;
+            let string1 = ctx.get::<String>(1)?;
+            let string2 = ctx.get::<String>(2)?;
+
+            Ok(string::algorithm(&name)
+                .map(|algorithm| f64::from(algorithm.similarity(&string1, &string2))))
+        },
+    )
+}
+
+/// Limits a column to the terms an algorithm scores at least [`string::MIN_SIMILARITY`]
+/// against a query, for the algorithms that don't search by subsequence
+///
+/// The algorithm and query are bound as the parameters numbered from `first`, and returned
+/// in that order with the clause. Every row is scored, as the index can't narrow it down.
+pub fn similarity_filter(
+    column: &str,
+    algorithm: &dyn Similarity,
+    query: &str,
+    first: usize,
+) -> (String, Vec<String>) {
+    let clause = format!(
+        "similarity(?{}, {column}, ?{}) >= {}",
+        first,
+        first + 1,
+        string::MIN_SIMILARITY
+    );
+
+    (clause, vec![algorithm.name().to_string(), query.to_owned()])
+}
+
+/// The `LIKE` pattern matching the terms that hold every character of a query in order
+pub fn subsequence_pattern(query: &str) -> String {
+    query.split("").collect::<Vec<_>>().join("%")
+}
@@ -0,0 +1,411 @@
+use crate::config::database::AppState;
+
+use actix_web::{rt::time, web::Data};
+use rusqlite::{Connection, Error, OptionalExtension, Row, Transaction, params};
+use std::{
+    collections::BTreeSet,
+    sync::{Mutex, PoisonError, atomic::Ordering},
+    time::Duration,
+};
+
+/// A user's count of a word, or of one word, reaching the threshold
+pub const WORD: &str = "word";
+/// A user's total words reaching the threshold
+pub const TOTAL: &str = "total";
+/// A new user taking #1 on the leaderboard
+pub const LEADER: &str = "leader";
+
+/// The name of the system counter notifications are sent from
+const SYSTEM: &str = "counter";
+
+/// How often the scheduler checks whether the leader changed
+const CHECK: Duration = Duration::from_secs(60);
+
+/// The usernames and words whose rules failed to be checked, checked again by [`schedule`]
+static MISSED: Mutex<BTreeSet<(String, String)>> = Mutex::new(BTreeSet::new());
+
+#[derive(Debug)]
+pub struct Rule {
+    pub id: i64,
+    pub kind: String,
+    pub word: Option<String>,
+    pub threshold: i64,
+    pub repeat: bool,
+    pub created_by: i64,
+}
+
+impl Rule {
+    pub fn from_row(row: &Row) -> Result<Self, Error> {
+        Ok(Self {
+            id: row.get("id")?,
+            kind: row.get("kind")?,
+            word: row.get("word")?,
+            threshold: row.get("threshold")?,
+            repeat: row.get("repeat")?,
+            created_by: row.get("created_by")?,
+        })
+    }
+
+    /// The highest threshold a count has reached, every multiple of it when repeating
+    fn reached(&self, count: i64) -> Option<i64> {
+        match (count >= self.threshold, self.repeat) {
+            (false, _) => None,
+            (true, false) => Some(self.threshold),
+            (true, true) => Some(count / self.threshold * self.threshold),
+        }
+    }
+}
+
+/// Gets the id of the counter system, adding it if it doesn't exist yet
+fn system(tx: &Transaction) -> Result<i64, Error> {
+    tx.execute(
+        "INSERT INTO systems(name) SELECT ?1 WHERE NOT EXISTS (SELECT 1 FROM systems WHERE name = ?1);",
+        [SYSTEM],
+    )?;
+
+    tx.query_row("SELECT id FROM systems WHERE name = ?1;", [SYSTEM], |row| {
+        row.get(0)
+    })
+}
+
+/// Gets the identity with more words than anyone else and the name it is shown under, if
+/// there is one
+fn leader(conn: &Connection) -> Result<Option<(String, String)>, Error> {
+    let top = conn
+        .prepare_cached(
+            r#"
+            SELECT identity, username AS name, total
+            FROM counter_totals_resolved
+            ORDER BY total DESC
+            LIMIT 2;
+            "#,
+        )?
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>("identity")?,
+                row.get::<_, String>("name")?,
+                row.get::<_, i64>("total")?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(match top.as_slice() {
+        [(identity, name, _)] => Some((identity.to_owned(), name.to_owned())),
+        [(identity, name, first), (.., second)] if first > second => {
+            Some((identity.to_owned(), name.to_owned()))
+        }
+        _ => None,
+    })
+}
+
+/// Gets the shown count of an identity, of one word or of every word
+///
+/// Only the rows of the identity's own usernames are read.
+fn count(tx: &Transaction, identity: &str, word: Option<&str>) -> Result<i64, Error> {
+    tx.prepare_cached(
+        r#"
+        SELECT COALESCE(SUM(count), 0)
+        FROM counter_visible
+        WHERE username IN (SELECT alias FROM counter_aliases WHERE identity = ?1 UNION SELECT ?1)
+            AND identity = ?1
+            AND (?2 IS NULL OR word = ?2);
+        "#,
+    )?
+    .query_row(params![identity, word], |row| row.get::<_, i64>(0))
+}
+
+/// Records a reached threshold and notifies the account linked to the identity about it
+///
+/// Thresholds are kept per identity, so they don't fire again when the name it is shown
+/// under changes. Identities without a linked account notify the admin who made the rule
+/// instead.
+///
+/// Returns whether the threshold was new
+fn fire(
+    tx: &Transaction,
+    rule: &Rule,
+    system_id: i64,
+    (identity, word): (&str, &str),
+    threshold: i64,
+    message: String,
+) -> Result<bool, Error> {
+    let new = tx.execute(
+        r#"
+        INSERT OR IGNORE INTO counter_milestones(rule_id, username, word, threshold)
+        VALUES (?1, ?2, ?3, ?4);
+        "#,
+        params![rule.id, identity, word, threshold],
+    )?;
+
+    if new == 0 {
+        return Ok(false);
+    }
+
+    let subject_id = tx
+        .query_row(
+            "SELECT user_id FROM counter_identities WHERE name = ?1 AND user_id IS NOT NULL;",
+            [identity],
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()?
+        .unwrap_or(rule.created_by);
+
+    tx.execute(
+        r#"
+        INSERT INTO notifications(subject_id, issuer_id, system_id, item_id, message)
+        VALUES (?1, ?2, ?3, ?4, ?5);
+        "#,
+        params![subject_id, rule.created_by, system_id, rule.id, message],
+    )?;
+
+    Ok(true)
+}
+
+/// Checks the enabled word and total rules against counts that went up for usernames and
+/// words
+///
+/// Counts are read the way the leaderboard shows them, so aliases add up under their
+/// identity and hidden users and words reach nothing. The leader is checked by
+/// [`schedule`] instead, as it needs the totals of everyone.
+///
+/// Returns the amount of notifications that were made
+pub fn evaluate(conn: &mut Connection, touched: &[(String, String)]) -> Result<usize, Error> {
+    let tx = conn.transaction()?;
+
+    let rules = tx
+        .prepare_cached("SELECT * FROM counter_rules WHERE enabled AND kind != ?1;")?
+        .query_map([LEADER], Rule::from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if rules.is_empty() {
+        return Ok(0);
+    }
+
+    // Find the identities of the touched counts with the names they are shown under
+    let mut shown = BTreeSet::new();
+
+    for (username, word) in touched {
+        let identity = tx
+            .prepare_cached(
+                r#"
+                SELECT identity, COALESCE(label, identity) AS name
+                FROM counter_visible
+                WHERE username = ?1 AND word = ?2
+                LIMIT 1;
+                "#,
+            )?
+            .query_row(params![username, word], |row| {
+                Ok((
+                    row.get::<_, String>("identity")?,
+                    row.get::<_, String>("name")?,
+                ))
+            })
+            .optional()?;
+
+        if let Some((identity, name)) = identity {
+            shown.insert((identity, name, word.to_owned()));
+        }
+    }
+
+    let identities = shown
+        .iter()
+        .map(|(identity, name, _)| (identity.as_str(), name.as_str()))
+        .collect::<BTreeSet<_>>();
+
+    let system_id = system(&tx)?;
+    let mut fired = 0;
+
+    for rule in &rules {
+        match rule.kind.as_str() {
+            WORD => {
+                for (identity, name, word) in &shown {
+                    if rule.word.as_ref().is_some_and(|w| w != word) {
+                        continue;
+                    }
+
+                    if let Some(threshold) = rule.reached(count(&tx, identity, Some(word))?) {
+                        let message = format!("{name} has said \"{word}\" {threshold} times!");
+                        fired += fire(&tx, rule, system_id, (identity, word), threshold, message)?
+                            as usize;
+                    }
+                }
+            }
+            TOTAL => {
+                for (identity, name) in &identities {
+                    if let Some(threshold) = rule.reached(count(&tx, identity, None)?) {
+                        let message = format!("{name} has passed {threshold} words!");
+                        fired += fire(&tx, rule, system_id, (identity, ""), threshold, message)?
+                            as usize;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    tx.commit()?;
+
+    Ok(fired)
+}
+
+/// Checks the enabled leader rules against who has the most words now
+///
+/// Returns the amount of notifications that were made
+pub fn lead(conn: &mut Connection) -> Result<usize, Error> {
+    let tx = conn.transaction()?;
+
+    let rules = tx
+        .prepare_cached("SELECT * FROM counter_rules WHERE enabled AND kind = ?1;")?
+        .query_map([LEADER], Rule::from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if rules.is_empty() {
+        return Ok(0);
+    }
+
+    let Some((identity, name)) = leader(&tx)? else {
+        return Ok(0);
+    };
+
+    let system_id = system(&tx)?;
+    let mut fired = 0;
+
+    for rule in &rules {
+        // Every change of leader fires once, counting up from the first
+        let (last, changes) = tx.query_row(
+            r#"
+                    SELECT
+                        (
+                            SELECT username
+                            FROM counter_milestones
+                            WHERE rule_id = ?1
+                            ORDER BY threshold DESC
+                            LIMIT 1
+                        ),
+                        (SELECT COUNT(*) FROM counter_milestones WHERE rule_id = ?1);
+                    "#,
+            [rule.id],
+            |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, i64>(1)?)),
+        )?;
+
+        if last.as_ref() != Some(&identity) {
+            let message = format!("{name} is the new #1 on the counter leaderboard!");
+            fired += fire(&tx, rule, system_id, (&identity, ""), changes + 1, message)? as usize;
+        }
+    }
+
+    tx.commit()?;
+
+    Ok(fired)
+}
+
+/// Checks the rules reached by counts that went up, like [`evaluate`], keeping the counts
+/// for [`schedule`] to check again when it fails
+pub fn check(conn: &mut Connection, touched: &[(String, String)]) {
+    if let Err(e) = evaluate(conn, touched) {
+        eprintln!("Failed to check milestones: {e}");
+
+        MISSED
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend(touched.iter().cloned());
+    }
+}
+
+/// Checks the leader rules for as long as the server runs, whenever the counts changed,
+/// and the counts whose rules failed to be checked
+pub async fn schedule(state: Data<AppState>) {
+    let mut interval = time::interval(CHECK);
+    let mut checked = None;
+
+    loop {
+        interval.tick().await;
+
+        let missed = std::mem::take(&mut *MISSED.lock().unwrap_or_else(PoisonError::into_inner));
+
+        if !missed.is_empty() {
+            let touched = missed.into_iter().collect::<Vec<_>>();
+
+            match state.pool.get() {
+                Ok(mut conn) => check(&mut conn, &touched),
+                Err(e) => {
+                    eprintln!("Failed to check milestones: {e}");
+
+                    MISSED
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .extend(touched);
+                }
+            }
+        }
+
+        let version = state.counter_version.load(Ordering::Acquire);
+
+        if checked == Some(version) {
+            continue;
+        }
+
+        let result = state
+            .pool
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|mut conn| lead(&mut conn).map_err(|e| e.to_string()));
+
+        match result {
+            Ok(_) => checked = Some(version),
+            Err(e) => eprintln!("Failed to check the leader: {e}"),
+        }
+    }
+}
+
+/// Records the thresholds a rule has already reached without notifying anyone
+///
+/// This keeps a new or changed rule from firing for every count made before it.
+pub fn seed(conn: &mut Connection, rule: &Rule) -> Result<(), Error> {
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "DELETE FROM counter_milestones WHERE rule_id = ?1;",
+        [rule.id],
+    )?;
+
+    match rule.kind.as_str() {
+        WORD | TOTAL => {
+            let word = match rule.kind.as_str() {
+                WORD => "word",
+                _ => "''",
+            };
+
+            tx.execute(
+                &format!(
+                    r#"
+                    INSERT OR IGNORE INTO counter_milestones(rule_id, username, word, threshold)
+                    SELECT
+                        ?1,
+                        identity,
+                        {word},
+                        CASE WHEN ?3 THEN SUM(count) / ?2 * ?2 ELSE ?2 END
+                    FROM counter_visible
+                    WHERE ?4 IS NULL OR word = ?4
+                    GROUP BY identity, {word}
+                    HAVING SUM(count) >= ?2;
+                    "#
+                ),
+                params![rule.id, rule.threshold, rule.repeat, rule.word],
+            )?;
+        }
+        _ => {
+            if let Some((identity, _)) = leader(&tx)? {
+                tx.execute(
+                    r#"
+                    INSERT INTO counter_milestones(rule_id, username, threshold)
+                    VALUES (?1, ?2, 1);
+                    "#,
+                    params![rule.id, identity],
+                )?;
+            }
+        }
+    }
+
+    tx.commit()
+}
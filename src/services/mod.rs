@@ -0,0 +1,13 @@
+pub mod anomalies;
+pub mod changes;
+pub mod counter;
+pub mod export;
+pub mod identity;
+pub mod import;
+pub mod index;
+pub mod milestones;
+pub mod moderation;
+pub mod snapshots;
+pub mod stats;
+pub mod stream;
+pub mod vectors;
@@ -0,0 +1,779 @@
+use crate::{services::index, utils::time};
+
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, Error, Transaction, params};
+
+/// What redacted words are counted as, which the normalizer can never produce
+pub const REDACTED: &str = "[redacted]";
+
+pub const COUNTER: &str = "counter";
+pub const HISTORY: &str = "counter_history";
+pub const BLOCKLIST: &str = "counter_blocklist";
+pub const PHRASES: &str = "counter_phrases";
+
+/// A row changed by a moderation action with its value before and after
+///
+/// Blocklist rows have no username or hour and are `1` while the word is blocked. Phrase
+/// rows have no hour and hold their phrase in `word`.
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub target: &'static str,
+    pub username: Option<String>,
+    pub word: String,
+    pub hour: Option<String>,
+    pub before: i64,
+    pub after: i64,
+}
+
+impl Change {
+    fn with(&self, username: Option<String>, word: String, before: i64, after: i64) -> Self {
+        Self {
+            target: self.target,
+            username,
+            word,
+            hour: self.hour.clone(),
+            before,
+            after,
+        }
+    }
+}
+
+/// Who did a moderation action, why and what it was done to
+pub struct Action<'a> {
+    pub name: &'a str,
+    pub username: Option<&'a str>,
+    pub word: Option<&'a str>,
+    pub reason: Option<&'a str>,
+    pub actor: &'a str,
+}
+
+fn target(name: &str) -> Option<&'static str> {
+    [COUNTER, HISTORY, BLOCKLIST, PHRASES]
+        .into_iter()
+        .find(|target| *target == name)
+}
+
+/// Gets the current value of the row a change is made to
+fn current(tx: &Transaction, change: &Change) -> Result<i64, Error> {
+    match change.target {
+        COUNTER => tx.query_row(
+            "SELECT COALESCE(SUM(count), 0) FROM counter WHERE username = ?1 AND word = ?2;",
+            params![change.username, change.word],
+            |row| row.get(0),
+        ),
+        HISTORY => tx.query_row(
+            r#"
+            SELECT COALESCE(SUM(count), 0)
+            FROM counter_history
+            WHERE username = ?1 AND word = ?2 AND hour = ?3;
+            "#,
+            params![change.username, change.word, change.hour],
+            |row| row.get(0),
+        ),
+        PHRASES => tx.query_row(
+            "SELECT COALESCE(SUM(count), 0) FROM counter_phrases WHERE username = ?1 AND phrase = ?2;",
+            params![change.username, change.word],
+            |row| row.get(0),
+        ),
+        _ => tx.query_row(
+            "SELECT COUNT(*) FROM counter_blocklist WHERE word = ?1;",
+            [&change.word],
+            |row| row.get(0),
+        ),
+    }
+}
+
+/// Gets the value a row will have once the changes before it apply, or its current value
+fn pending(tx: &Transaction, changes: &[Change], change: &Change) -> Result<i64, Error> {
+    match changes.iter().rev().find(|c| {
+        c.target == change.target
+            && c.username == change.username
+            && c.word == change.word
+            && c.hour == change.hour
+    }) {
+        Some(earlier) => Ok(earlier.after),
+        None => current(tx, change),
+    }
+}
+
+/// Sets the row a change is made to to its value after the change, removing it at zero
+fn apply(tx: &Transaction, change: &Change) -> Result<(), Error> {
+    match (change.target, change.after) {
+        (COUNTER, 0) => {
+            tx.execute(
+                "DELETE FROM counter WHERE username = ?1 AND word = ?2;",
+                params![change.username, change.word],
+            )?;
+
+            if let Some(username) = &change.username {
+                index::remove(tx, index::USERNAME, username)?;
+            }
+
+            index::remove(tx, index::WORD, &change.word)?;
+        }
+        (COUNTER, count) => {
+            let updated = tx.execute(
+                "UPDATE counter SET count = ?3 WHERE username = ?1 AND word = ?2;",
+                params![change.username, change.word, count],
+            )?;
+
+            if updated == 0 {
+                tx.execute(
+                    "INSERT INTO counter(username, word, count) VALUES (?1, ?2, ?3);",
+                    params![change.username, change.word, count],
+                )?;
+
+                if let Some(username) = &change.username {
+                    index::add(tx, index::USERNAME, username)?;
+                }
+
+                index::add(tx, index::WORD, &change.word)?;
+            }
+        }
+        (HISTORY, 0) => {
+            tx.execute(
+                "DELETE FROM counter_history WHERE username = ?1 AND word = ?2 AND hour = ?3;",
+                params![change.username, change.word, change.hour],
+            )?;
+        }
+        (HISTORY, count) => {
+            tx.execute(
+                r#"
+                INSERT INTO counter_history(username, word, hour, count)
+                VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT(username, word, hour) DO UPDATE SET count = excluded.count;
+                "#,
+                params![change.username, change.word, change.hour, count],
+            )?;
+        }
+        (PHRASES, 0) => {
+            tx.execute(
+                "DELETE FROM counter_phrases WHERE username = ?1 AND phrase = ?2;",
+                params![change.username, change.word],
+            )?;
+
+            index::remove(tx, index::PHRASE, &change.word)?;
+        }
+        (PHRASES, count) => {
+            let updated = tx.execute(
+                "UPDATE counter_phrases SET count = ?3 WHERE username = ?1 AND phrase = ?2;",
+                params![change.username, change.word, count],
+            )?;
+
+            if updated == 0 {
+                tx.execute(
+                    r#"
+                    INSERT INTO counter_phrases(username, phrase, length, count)
+                    VALUES (?1, ?2, ?3, ?4);
+                    "#,
+                    params![
+                        change.username,
+                        change.word,
+                        change.word.split(' ').count(),
+                        count
+                    ],
+                )?;
+
+                index::add(tx, index::PHRASE, &change.word)?;
+            }
+        }
+        (_, 0) => {
+            tx.execute(
+                "DELETE FROM counter_blocklist WHERE word = ?1;",
+                [&change.word],
+            )?;
+        }
+        _ => {
+            tx.execute(
+                "INSERT OR IGNORE INTO counter_blocklist(word) VALUES (?1);",
+                [&change.word],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies the changes of an action and writes them to the audit table
+///
+/// Returns the id of the audit entry, or `None` if the action changed nothing
+fn record(tx: &Transaction, action: &Action, changes: &[Change]) -> Result<Option<i64>, Error> {
+    if changes.is_empty() {
+        return Ok(None);
+    }
+
+    tx.execute(
+        r#"
+        INSERT INTO counter_audit(action, username, word, reason, actor)
+        VALUES (?1, ?2, ?3, ?4, ?5);
+        "#,
+        params![
+            action.name,
+            action.username,
+            action.word,
+            action.reason,
+            action.actor
+        ],
+    )?;
+
+    let id = tx.last_insert_rowid();
+
+    for change in changes {
+        apply(tx, change)?;
+
+        tx.execute(
+            r#"
+            INSERT INTO counter_audit_changes(audit_id, target, username, word, hour, before, after)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);
+            "#,
+            params![
+                id,
+                change.target,
+                change.username,
+                change.word,
+                change.hour,
+                change.before,
+                change.after
+            ],
+        )?;
+    }
+
+    Ok(Some(id))
+}
+
+/// Records an action and commits it, leaving the transaction to roll back if nothing changed
+fn commit(tx: Transaction, action: &Action, changes: &[Change]) -> Result<Option<i64>, Error> {
+    let id = record(&tx, action, changes)?;
+
+    if id.is_some() {
+        tx.commit()?;
+    }
+
+    Ok(id)
+}
+
+/// Gets the counts, hourly counts and phrase counts of a word, for one identity and its
+/// aliases or for everyone
+fn counts(tx: &Transaction, word: &str, username: Option<&str>) -> Result<Vec<Change>, Error> {
+    let mut changes = Vec::new();
+
+    for (target, query) in [
+        (
+            COUNTER,
+            "SELECT username, word, NULL AS hour, count FROM counter WHERE word = ?1",
+        ),
+        (
+            HISTORY,
+            "SELECT username, word, hour, count FROM counter_history WHERE word = ?1",
+        ),
+        (
+            PHRASES,
+            r#"
+            SELECT username, phrase AS word, NULL AS hour, count
+            FROM counter_phrases
+            WHERE instr(' ' || phrase || ' ', ' ' || ?1 || ' ') > 0
+            "#,
+        ),
+    ] {
+        let rows = tx
+            .prepare(&format!(
+                r#"
+                {query}
+                    AND (
+                        ?2 IS NULL
+                        OR username = ?2
+                        OR username IN (SELECT alias FROM counter_aliases WHERE identity = ?2)
+                    )
+                ORDER BY username;
+                "#
+            ))?
+            .query_map(params![word, username], |row| {
+                Ok(Change {
+                    target,
+                    username: row.get("username")?,
+                    word: row.get("word")?,
+                    hour: row.get("hour")?,
+                    before: row.get("count")?,
+                    after: 0,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        changes.extend(rows);
+    }
+
+    Ok(changes)
+}
+
+/// Deletes the counts of a word, for one identity and its aliases or for everyone
+pub fn delete(conn: &mut Connection, action: &Action, word: &str) -> Result<Option<i64>, Error> {
+    let tx = conn.transaction()?;
+    let changes = counts(&tx, word, action.username)?;
+
+    commit(tx, action, &changes)
+}
+
+/// Moves the counts of a word onto [`REDACTED`], for one identity and its aliases or for everyone
+///
+/// Totals stay the same while the word itself is no longer shown. Phrases with the word are
+/// moved onto the phrase with [`REDACTED`] in its place.
+pub fn redact(conn: &mut Connection, action: &Action, word: &str) -> Result<Option<i64>, Error> {
+    let tx = conn.transaction()?;
+    let mut changes: Vec<Change> = Vec::new();
+
+    for change in counts(&tx, word, action.username)? {
+        let replacement = match change.target {
+            PHRASES => change
+                .word
+                .split(' ')
+                .map(|w| if w == word { REDACTED } else { w })
+                .collect::<Vec<_>>()
+                .join(" "),
+            _ => REDACTED.to_owned(),
+        };
+
+        let redacted = change.with(change.username.clone(), replacement, 0, 0);
+
+        // Different phrases can be redacted into the same one
+        let before = pending(&tx, &changes, &redacted)?;
+
+        changes.push(redacted.with(
+            change.username.clone(),
+            redacted.word.clone(),
+            before,
+            before + change.before,
+        ));
+        changes.push(change);
+    }
+
+    commit(tx, action, &changes)
+}
+
+/// Adds to the count of a word for exactly one username, in total and in the hour it is
+/// adjusted at, never going below zero
+pub fn adjust(
+    conn: &mut Connection,
+    action: &Action,
+    username: &str,
+    word: &str,
+    delta: i64,
+    at: NaiveDateTime,
+) -> Result<Option<i64>, Error> {
+    let tx = conn.transaction()?;
+    let id = amend(
+        &tx,
+        action,
+        username,
+        &time::hour(at),
+        &[(word.to_owned(), delta)],
+        &[],
+    )?;
+
+    if id.is_some() {
+        tx.commit()?;
+    }
+
+    Ok(id)
+}
+
+/// Adds to or takes from the counts of a user's words, in total and in the hour they were
+/// used, and the counts of their phrases inside an open transaction
+///
+/// Returns the id of the audit entry, or `None` if nothing changed
+pub fn amend(
+    tx: &Transaction,
+    action: &Action,
+    username: &str,
+    hour: &str,
+    deltas: &[(String, i64)],
+    phrases: &[(String, i64)],
+) -> Result<Option<i64>, Error> {
+    let mut changes = Vec::new();
+
+    for (word, delta) in deltas {
+        for (target, hour) in [(COUNTER, None), (HISTORY, Some(hour.to_owned()))] {
+            let mut change = Change {
+                target,
+                username: Some(username.to_owned()),
+                word: word.to_owned(),
+                hour,
+                before: 0,
+                after: 0,
+            };
+
+            change.before = current(tx, &change)?;
+            change.after = (change.before + delta).max(0);
+
+            if change.before != change.after {
+                changes.push(change);
+            }
+        }
+    }
+
+    for (phrase, delta) in phrases {
+        let mut change = Change {
+            target: PHRASES,
+            username: Some(username.to_owned()),
+            word: phrase.to_owned(),
+            hour: None,
+            before: 0,
+            after: 0,
+        };
+
+        change.before = current(tx, &change)?;
+        change.after = (change.before + delta).max(0);
+
+        if change.before != change.after {
+            changes.push(change);
+        }
+    }
+
+    record(tx, action, &changes)
+}
+
+/// Adds words to or removes words from the blocklist that ingest skips
+pub fn block(
+    conn: &mut Connection,
+    action: &Action,
+    words: &[String],
+    blocked: bool,
+) -> Result<Option<i64>, Error> {
+    let tx = conn.transaction()?;
+    let mut changes: Vec<Change> = Vec::new();
+
+    for word in words {
+        let mut change = Change {
+            target: BLOCKLIST,
+            username: None,
+            word: word.to_owned(),
+            hour: None,
+            before: 0,
+            after: blocked.into(),
+        };
+
+        change.before = current(&tx, &change)?;
+
+        if change.before != change.after && !changes.iter().any(|c| c.word == *word) {
+            changes.push(change);
+        }
+    }
+
+    commit(tx, action, &changes)
+}
+
+/// Reverts an audited action by taking back what it added to or removed from each row
+///
+/// Counts that changed since keep those changes. The action is marked as undone by the
+/// new audit entry.
+pub fn undo(conn: &mut Connection, action: &Action, id: i64) -> Result<Option<i64>, Error> {
+    let tx = conn.transaction()?;
+
+    let done = tx
+        .prepare(
+            r#"
+            SELECT target, username, word, hour, before, after
+            FROM counter_audit_changes
+            WHERE audit_id = ?1;
+            "#,
+        )?
+        .query_map([id], |row| {
+            Ok(Change {
+                target: target(&row.get::<_, String>("target")?).unwrap_or(COUNTER),
+                username: row.get("username")?,
+                word: row.get("word")?,
+                hour: row.get("hour")?,
+                before: row.get("before")?,
+                after: row.get("after")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut changes = Vec::new();
+
+    for change in done {
+        let before = pending(&tx, &changes, &change)?;
+        let after = match change.target {
+            BLOCKLIST => change.before,
+            _ => (before - (change.after - change.before)).max(0),
+        };
+
+        if before != after {
+            changes.push(change.with(change.username.clone(), change.word.clone(), before, after));
+        }
+    }
+
+    let Some(undone_by) = record(&tx, action, &changes)? else {
+        return Ok(None);
+    };
+
+    tx.execute(
+        "UPDATE counter_audit SET undone_by = ?2 WHERE id = ?1;",
+        params![id, undone_by],
+    )?;
+
+    tx.commit()?;
+
+    Ok(Some(undone_by))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: &str = "2025-06-04 14:00:00";
+
+    fn at() -> NaiveDateTime {
+        time::parse(HOUR).unwrap()
+    }
+
+    fn connect() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../database/counter.sql"))
+            .unwrap();
+        conn.execute_batch(&format!(
+            r#"
+            INSERT INTO counter(username, word, count) VALUES
+                ('bob', 'secret', 2), ('bob', 'sauce', 3), ('alice', 'secret', 1);
+            INSERT INTO counter_history(username, word, hour, count) VALUES
+                ('bob', 'secret', '{HOUR}', 2), ('alice', 'secret', '{HOUR}', 1);
+            INSERT INTO counter_phrases(username, phrase, length, count) VALUES
+                ('bob', 'secret sauce', 2, 2);
+            "#
+        ))
+        .unwrap();
+        conn
+    }
+
+    fn action(name: &'static str, username: Option<&'static str>) -> Action<'static> {
+        Action {
+            name,
+            username,
+            word: None,
+            reason: None,
+            actor: "admin",
+        }
+    }
+
+    fn count(conn: &Connection, table: &str, column: &str, username: &str, word: &str) -> i64 {
+        conn.query_row(
+            &format!(
+                "SELECT COALESCE(SUM(count), 0) FROM {table} WHERE username = ?1 AND {column} = ?2;"
+            ),
+            [username, word],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    fn changes(conn: &Connection, id: i64) -> Vec<(String, Option<String>, String, i64, i64)> {
+        conn.prepare(
+            r#"
+            SELECT target, username, word, before, after
+            FROM counter_audit_changes
+            WHERE audit_id = ?1
+            ORDER BY target, username, word;
+            "#,
+        )
+        .unwrap()
+        .query_map([id], |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+            ))
+        })
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+    }
+
+    fn change(
+        target: &str,
+        username: &str,
+        word: &str,
+        before: i64,
+        after: i64,
+    ) -> (String, Option<String>, String, i64, i64) {
+        (
+            target.to_string(),
+            Some(username.to_string()),
+            word.to_string(),
+            before,
+            after,
+        )
+    }
+
+    #[test]
+    fn delete_test() {
+        let mut conn = connect();
+
+        let id = delete(&mut conn, &action("delete", Some("bob")), "secret")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            changes(&conn, id),
+            vec![
+                change(COUNTER, "bob", "secret", 2, 0),
+                change(HISTORY, "bob", "secret", 2, 0),
+                change(PHRASES, "bob", "secret sauce", 2, 0),
+            ]
+        );
+        assert_eq!(count(&conn, COUNTER, "word", "bob", "secret"), 0);
+        assert_eq!(count(&conn, PHRASES, "phrase", "bob", "secret sauce"), 0);
+        assert_eq!(count(&conn, COUNTER, "word", "alice", "secret"), 1);
+
+        // Nothing is left to delete
+        assert_eq!(
+            delete(&mut conn, &action("delete", Some("bob")), "secret").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn unindex_test() {
+        let mut conn = connect();
+        index::rebuild(&mut conn).unwrap();
+
+        let indexed = |conn: &Connection, kind: &str, term: &str| {
+            conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM counter_grams WHERE kind = ?1 AND term = ?2);",
+                [kind, term],
+                |row| row.get::<_, bool>(0),
+            )
+            .unwrap()
+        };
+
+        // Words stay indexed while anyone still has them
+        delete(&mut conn, &action("delete", Some("bob")), "secret").unwrap();
+        assert!(indexed(&conn, index::WORD, "secret"));
+        assert!(!indexed(&conn, index::PHRASE, "secret sauce"));
+
+        delete(&mut conn, &action("delete", None), "secret").unwrap();
+        assert!(!indexed(&conn, index::WORD, "secret"));
+        assert!(!indexed(&conn, index::USERNAME, "alice"));
+        assert!(indexed(&conn, index::USERNAME, "bob"));
+    }
+
+    #[test]
+    fn redact_test() {
+        let mut conn = connect();
+
+        let id = redact(&mut conn, &action("redact", None), "secret")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            changes(&conn, id),
+            vec![
+                change(COUNTER, "alice", "[redacted]", 0, 1),
+                change(COUNTER, "alice", "secret", 1, 0),
+                change(COUNTER, "bob", "[redacted]", 0, 2),
+                change(COUNTER, "bob", "secret", 2, 0),
+                change(HISTORY, "alice", "[redacted]", 0, 1),
+                change(HISTORY, "alice", "secret", 1, 0),
+                change(HISTORY, "bob", "[redacted]", 0, 2),
+                change(HISTORY, "bob", "secret", 2, 0),
+                change(PHRASES, "bob", "[redacted] sauce", 0, 2),
+                change(PHRASES, "bob", "secret sauce", 2, 0),
+            ]
+        );
+        assert_eq!(count(&conn, COUNTER, "word", "bob", REDACTED), 2);
+        assert_eq!(
+            count(&conn, PHRASES, "phrase", "bob", "[redacted] sauce"),
+            2
+        );
+    }
+
+    #[test]
+    fn undo_test() {
+        let mut conn = connect();
+
+        let id = redact(&mut conn, &action("redact", Some("bob")), "secret")
+            .unwrap()
+            .unwrap();
+
+        // Counts added since the action keep what was added
+        adjust(&mut conn, &action("adjust", None), "bob", REDACTED, 1, at()).unwrap();
+
+        let undone_by = undo(&mut conn, &action("undo", None), id).unwrap().unwrap();
+
+        assert_eq!(count(&conn, COUNTER, "word", "bob", "secret"), 2);
+        assert_eq!(count(&conn, COUNTER, "word", "bob", REDACTED), 1);
+        assert_eq!(count(&conn, HISTORY, "word", "bob", "secret"), 2);
+        assert_eq!(count(&conn, PHRASES, "phrase", "bob", "secret sauce"), 2);
+        assert_eq!(
+            count(&conn, PHRASES, "phrase", "bob", "[redacted] sauce"),
+            0
+        );
+
+        let marked = conn
+            .query_row(
+                "SELECT undone_by FROM counter_audit WHERE id = ?1;",
+                [id],
+                |row| row.get::<_, Option<i64>>(0),
+            )
+            .unwrap();
+        assert_eq!(marked, Some(undone_by));
+
+        // Undoing the undo redacts the word again
+        undo(&mut conn, &action("undo", None), undone_by).unwrap();
+        assert_eq!(count(&conn, COUNTER, "word", "bob", "secret"), 0);
+        assert_eq!(count(&conn, COUNTER, "word", "bob", REDACTED), 3);
+    }
+
+    #[test]
+    fn adjust_test() {
+        let mut conn = connect();
+        let daily = |conn: &Connection| {
+            conn.query_row(
+                "SELECT SUM(count) FROM counter_daily WHERE username = 'bob' AND word = 'secret';",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .unwrap()
+        };
+
+        // The windowed totals move with the all-time total
+        let id = adjust(&mut conn, &action("adjust", None), "bob", "secret", 3, at())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(count(&conn, COUNTER, "word", "bob", "secret"), 5);
+        assert_eq!(count(&conn, HISTORY, "word", "bob", "secret"), 5);
+        assert_eq!(daily(&conn), 5);
+
+        undo(&mut conn, &action("undo", None), id).unwrap();
+
+        assert_eq!(count(&conn, COUNTER, "word", "bob", "secret"), 2);
+        assert_eq!(count(&conn, HISTORY, "word", "bob", "secret"), 2);
+        assert_eq!(daily(&conn), 2);
+    }
+
+    #[test]
+    fn block_test() {
+        let mut conn = connect();
+        let words = ["spam".to_string(), "spam".to_string()];
+
+        let id = block(&mut conn, &action("block", None), &words, true)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(changes(&conn, id).len(), 1);
+        assert_eq!(
+            block(&mut conn, &action("block", None), &words, true).unwrap(),
+            None
+        );
+
+        undo(&mut conn, &action("undo", None), id).unwrap();
+
+        let blocked = conn
+            .query_row("SELECT COUNT(*) FROM counter_blocklist;", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .unwrap();
+        assert_eq!(blocked, 0);
+    }
+}
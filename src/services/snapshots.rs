@@ -0,0 +1,172 @@
+use crate::config::database::AppState;
+
+use actix_web::{rt::time, web::Data};
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use rusqlite::{Connection, Error, params};
+use std::time::Duration;
+
+/// Snapshots of the user leaderboard
+pub const USER: &str = "user";
+/// Snapshots of the word leaderboard
+pub const WORD: &str = "word";
+
+/// How often the scheduler checks whether a snapshot is due
+const CHECK: Duration = Duration::from_secs(60);
+
+/// The format snapshot times are stored in, the same as `CURRENT_TIMESTAMP`
+const FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug)]
+pub struct Schedule {
+    /// The time between snapshots
+    pub every: TimeDelta,
+    /// How long snapshots are kept, forever if `None`
+    pub retention: Option<TimeDelta>,
+}
+
+impl Schedule {
+    /// Reads the schedule from the environment
+    ///
+    /// - `COUNTER_SNAPSHOT_HOURS`: The hours between snapshots. Default `24`
+    /// - `COUNTER_SNAPSHOT_RETENTION`: The days snapshots are kept, `0` keeps them forever.
+    ///   Default `365`
+    pub fn from_env() -> Self {
+        let hours = std::env::var("COUNTER_SNAPSHOT_HOURS")
+            .ok()
+            .and_then(|hours| hours.parse::<i64>().ok())
+            .filter(|hours| *hours > 0)
+            .unwrap_or(24);
+
+        let days = std::env::var("COUNTER_SNAPSHOT_RETENTION")
+            .ok()
+            .and_then(|days| days.parse::<i64>().ok())
+            .unwrap_or(365);
+
+        Self {
+            every: TimeDelta::hours(hours),
+            retention: (days > 0).then(|| TimeDelta::days(days)),
+        }
+    }
+}
+
+/// The query for the rank a leaderboard row had in the latest snapshot of its kind
+///
+/// `name` is the column of the row holding the username or word, `ranking` picks whether
+/// the competition or dense rank is read.
+pub fn previous_rank(kind: &str, name: &str, ranking: &str) -> String {
+    let rank = match ranking {
+        "dense" => "dense_rank",
+        _ => "rank",
+    };
+
+    format!(
+        r#"(
+            SELECT {rank}
+            FROM counter_snapshots
+            WHERE kind = '{kind}'
+                AND name = {name}
+                AND taken_at = (SELECT MAX(taken_at) FROM counter_snapshots WHERE kind = '{kind}')
+        )"#
+    )
+}
+
+/// Stores the user and word leaderboards as they are ranked at `at`
+///
+/// Users are ranked the way the resolved leaderboard shows them, so hidden users and words
+/// are left out and anonymized users are stored under their label.
+///
+/// Returns the amount of rows that were stored
+pub fn take(conn: &mut Connection, at: NaiveDateTime) -> Result<usize, Error> {
+    let tx = conn.transaction()?;
+    let at = at.format(FORMAT).to_string();
+    let mut stored = 0;
+
+    for (kind, column) in [(USER, "username"), (WORD, "word")] {
+        stored += tx.execute(
+            &format!(
+                r#"
+                INSERT OR REPLACE INTO counter_snapshots(taken_at, kind, name, count, rank, dense_rank)
+                SELECT
+                    ?1,
+                    ?2,
+                    {column},
+                    SUM(count),
+                    RANK() OVER (ORDER BY SUM(count) DESC),
+                    DENSE_RANK() OVER (ORDER BY SUM(count) DESC)
+                FROM counter_resolved
+                GROUP BY {column};
+                "#
+            ),
+            params![at, kind],
+        )?;
+    }
+
+    tx.commit()?;
+
+    Ok(stored)
+}
+
+/// Deletes the snapshots taken before `before`
+///
+/// Returns the amount of rows that were deleted
+pub fn prune(conn: &Connection, before: NaiveDateTime) -> Result<usize, Error> {
+    conn.execute(
+        "DELETE FROM counter_snapshots WHERE taken_at < ?1;",
+        [before.format(FORMAT).to_string()],
+    )
+}
+
+/// Takes a snapshot when the latest one is older than the schedule allows and prunes the
+/// ones past retention
+///
+/// Returns whether a snapshot was taken
+pub fn run(conn: &mut Connection, schedule: &Schedule, now: NaiveDateTime) -> Result<bool, Error> {
+    let latest = conn.query_row("SELECT MAX(taken_at) FROM counter_snapshots;", [], |row| {
+        row.get::<_, Option<String>>(0)
+    })?;
+
+    let due = latest
+        .and_then(|latest| NaiveDateTime::parse_from_str(&latest, FORMAT).ok())
+        .is_none_or(|latest| latest + schedule.every <= now);
+
+    if due {
+        take(conn, now)?;
+    }
+
+    if let Some(retention) = schedule.retention {
+        prune(conn, now - retention)?;
+    }
+
+    Ok(due)
+}
+
+/// Takes the scheduled snapshots for as long as the server runs
+///
+/// The time of the latest snapshot is read from the database, so restarts don't take extra
+/// ones.
+pub async fn schedule(state: Data<AppState>) {
+    let schedule = Schedule::from_env();
+    let mut interval = time::interval(CHECK);
+
+    loop {
+        interval.tick().await;
+
+        let result = state
+            .pool
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|mut conn| {
+                run(&mut conn, &schedule, Utc::now().naive_utc()).map_err(|e| e.to_string())
+            });
+
+        match result {
+            // Cached pages hold the ranks of the previous snapshot
+            Ok(true) => {
+                state.users_cache.clear();
+                state.words_cache.clear();
+            }
+            Ok(false) => {}
+            Err(e) => eprintln!("Failed to snapshot the leaderboards: {e}"),
+        }
+    }
+}
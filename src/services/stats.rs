@@ -0,0 +1,108 @@
+use crate::{
+    config::database::AppState,
+    dtos::responses::counter::{LengthData, StatsData, ZipfData},
+    services::vectors,
+    utils::stats,
+};
+
+use rusqlite::{Connection, Error};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{PoisonError, atomic::Ordering},
+};
+
+fn compute<'a>(counts: impl Iterator<Item = (&'a String, &'a u32)>) -> StatsData {
+    let mut total: u32 = 0;
+    let mut frequencies = Vec::new();
+    let mut hapax_legomena: u32 = 0;
+    let mut lengths: BTreeMap<usize, LengthData> = BTreeMap::new();
+
+    for (word, count) in counts {
+        total += count;
+        frequencies.push(*count);
+
+        if *count == 1 {
+            hapax_legomena += 1;
+        }
+
+        let length = word.chars().count();
+        let entry = lengths.entry(length).or_insert(LengthData {
+            length: length as u32,
+            words: 0,
+            count: 0,
+        });
+        entry.words += 1;
+        entry.count += count;
+    }
+
+    let distinct = frequencies.len() as u32;
+    let (slope, r_squared) = stats::zipf(&frequencies);
+
+    StatsData {
+        total,
+        distinct,
+        type_token_ratio: if total > 0 {
+            distinct as f32 / total as f32
+        } else {
+            0.0
+        },
+        hapax_legomena,
+        zipf: ZipfData {
+            slope: slope as f32,
+            r_squared: r_squared as f32,
+        },
+        lengths: lengths.into_values().collect(),
+    }
+}
+
+/// Gets the vocabulary statistics of everyone, or of a single user
+///
+/// Results are cached until counter rows are written. Usernames are resolved to their
+/// identity unless `raw`.
+///
+/// Returns `None` if the user has no counts
+pub fn get(
+    state: &AppState,
+    conn: &Connection,
+    username: Option<&str>,
+    raw: bool,
+) -> Result<Option<StatsData>, Error> {
+    let version = state.counter_version.load(Ordering::Acquire);
+    let key = (raw, username.map(str::to_owned));
+
+    if let Some((_, data)) = state
+        .stats
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&key)
+        .filter(|(cached, _)| *cached == version)
+    {
+        return Ok(Some(data.clone()));
+    }
+
+    let vectors = vectors::get(state, conn, raw)?;
+
+    let data = match username {
+        Some(u) => match vectors.users.get(u) {
+            Some(counts) => compute(counts.iter()),
+            None => return Ok(None),
+        },
+        None => {
+            let totals: HashMap<&String, &u32> = vectors
+                .words
+                .iter()
+                .map(|(word, (total, _))| (word, total))
+                .collect();
+
+            compute(totals.into_iter())
+        }
+    };
+
+    state
+        .stats
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(key, (version, data.clone()));
+
+    Ok(Some(data))
+}
@@ -0,0 +1,219 @@
+use crate::{
+    config::database::AppState,
+    dtos::responses::counter::{CountEvent, RankEvent},
+};
+
+use actix_web::{rt::time, web::Bytes};
+use futures_util::{Stream, StreamExt, stream};
+use rusqlite::{Connection, Error, OptionalExtension, params};
+use serde::Serialize;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// The amount of events kept for clients that fall behind before they skip ahead
+pub const CAPACITY: usize = 256;
+
+/// How often idle connections get a comment so proxies don't close them
+const KEEPALIVE: Duration = Duration::from_secs(30);
+
+#[derive(Debug, PartialEq)]
+pub enum Kind {
+    Count,
+    Rank,
+    Ping,
+}
+
+/// An event formatted once for every client it is sent to
+#[derive(Debug)]
+pub struct Event {
+    pub kind: Kind,
+    pub username: String,
+    pub word: Option<String>,
+    pub data: Bytes,
+}
+
+impl Event {
+    fn new(
+        kind: Kind,
+        name: &str,
+        data: &impl Serialize,
+        username: &str,
+        word: Option<&str>,
+    ) -> Self {
+        let json = serde_json::to_string(data).unwrap_or_default();
+
+        Self {
+            kind,
+            username: username.to_owned(),
+            word: word.map(str::to_owned),
+            data: Bytes::from(format!("event: {name}\ndata: {json}\n\n")),
+        }
+    }
+
+    /// Whether a client filtering on a username and word wants the event
+    ///
+    /// Rank events have no word, so they are left out when filtering on one.
+    pub fn matches(&self, username: Option<&str>, word: Option<&str>) -> bool {
+        match self.kind {
+            Kind::Ping => true,
+            _ => {
+                username.is_none_or(|username| username == self.username)
+                    && word.is_none_or(|word| Some(word) == self.word.as_deref())
+            }
+        }
+    }
+}
+
+/// Sends the events of an ingested message to the connected clients
+///
+/// Counts are shown the way the resolved leaderboard shows them, so hidden users and words
+/// send nothing and anonymized users are sent under their label. Nothing is queried while
+/// no client is connected.
+pub fn publish(
+    state: &AppState,
+    conn: &Connection,
+    username: &str,
+    words: &[(String, u32)],
+) -> Result<(), Error> {
+    if state.events.receiver_count() == 0 {
+        return Ok(());
+    }
+
+    let mut shown = None;
+    let mut delta = 0;
+
+    for (word, count) in words {
+        let Some(name) = conn
+            .prepare_cached(
+                r#"
+                SELECT COALESCE(label, identity)
+                FROM counter_visible
+                WHERE username = ?1 AND word = ?2
+                LIMIT 1;
+                "#,
+            )?
+            .query_row(params![username, word], |row| row.get::<_, String>(0))
+            .optional()?
+        else {
+            continue;
+        };
+
+        let event = CountEvent {
+            username: name.to_owned(),
+            word: word.to_owned(),
+            delta: *count,
+        };
+
+        let _ = state.events.send(Arc::new(Event::new(
+            Kind::Count,
+            "count",
+            &event,
+            &name,
+            Some(word),
+        )));
+
+        delta += count;
+        shown = Some(name);
+    }
+
+    let Some(name) = shown else {
+        return Ok(());
+    };
+
+    // Find who the user passed, ranking them the way the leaderboard does
+    let ranks = conn
+        .prepare_cached(
+            r#"
+            WITH totals AS (
+                SELECT username, total FROM counter_totals_resolved
+            ),
+            user AS (
+                SELECT total FROM totals WHERE username = ?1
+            )
+            SELECT
+                totals.username,
+                (SELECT COUNT(*) FROM totals AS above WHERE above.total > totals.total) + 1 AS rank,
+                CASE
+                    WHEN totals.username = ?1 THEN (
+                        SELECT COUNT(*)
+                        FROM totals AS above
+                        WHERE above.username != ?1 AND above.total > (SELECT total FROM user) - ?2
+                    ) + 1
+                    ELSE (SELECT COUNT(*) FROM totals AS above WHERE above.total > totals.total)
+                END AS previous_rank
+            FROM totals
+            WHERE totals.username = ?1
+                OR (
+                    totals.total > (SELECT total FROM user) - ?2
+                    AND totals.total < (SELECT total FROM user)
+                )
+            ORDER BY rank;
+            "#,
+        )?
+        .query_map(params![&name, delta], |row| {
+            Ok(RankEvent {
+                username: row.get("username")?,
+                rank: row.get("rank")?,
+                previous_rank: row.get("previous_rank")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for event in ranks {
+        if event.rank != event.previous_rank {
+            let _ = state.events.send(Arc::new(Event::new(
+                Kind::Rank,
+                "rank",
+                &event,
+                &event.username,
+                None,
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Streams the events a client filters for as Server-Sent Events
+///
+/// Clients that fall too far behind skip the events they missed.
+pub fn subscribe(
+    receiver: broadcast::Receiver<Arc<Event>>,
+    username: Option<String>,
+    word: Option<String>,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>> {
+    let connected = stream::once(async { Ok(Bytes::from_static(b": connected\n\n")) });
+
+    let events = stream::unfold(
+        (receiver, username, word),
+        |(mut receiver, username, word)| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if event.matches(username.as_deref(), word.as_deref()) => {
+                        return Some((Ok(event.data.clone()), (receiver, username, word)));
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        },
+    );
+
+    connected.chain(events)
+}
+
+/// Pings every connected client on one shared timer
+pub async fn keepalive(state: actix_web::web::Data<AppState>) {
+    let mut interval = time::interval(KEEPALIVE);
+
+    loop {
+        interval.tick().await;
+
+        let _ = state.events.send(Arc::new(Event {
+            kind: Kind::Ping,
+            username: String::new(),
+            word: None,
+            data: Bytes::from_static(b": ping\n\n"),
+        }));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{
+    config::database::AppState,
+    services::identity,
+    utils::vector::{self, Vector},
+};
+
+use rusqlite::{Connection, Error};
+use std::{
+    collections::HashMap,
+    sync::{Arc, PoisonError, atomic::Ordering},
+};
+
+/// The word counts of every user, kept in memory until the counter table changes
+pub struct Vectors {
+    pub version: u64,
+    pub users: HashMap<String, HashMap<String, u32>>,
+
+    /// The total count and the amount of users of every word
+    pub words: HashMap<String, (u32, u32)>,
+
+    pub total: u64,
+}
+
+impl Vectors {
+    fn load(conn: &Connection, version: u64, raw: bool) -> Result<Self, Error> {
+        let mut vectors = Self {
+            version,
+            users: HashMap::new(),
+            words: HashMap::new(),
+            total: 0,
+        };
+
+        let mut stmt = conn.prepare(&format!(
+            r#"
+            SELECT username, word, SUM(count) AS total
+            FROM {}
+            WHERE count > 0
+            GROUP BY username, word;
+            "#,
+            identity::source(raw)
+        ))?;
+
+        let mut rows = stmt.query([])?;
+
+        while let Some(row) = rows.next()? {
+            let username: String = row.get("username")?;
+            let word: String = row.get("word")?;
+            let count: u32 = row.get("total")?;
+
+            let global = vectors.words.entry(word.clone()).or_default();
+            global.0 += count;
+            global.1 += 1;
+
+            vectors
+                .users
+                .entry(username)
+                .or_default()
+                .insert(word, count);
+            vectors.total += count as u64;
+        }
+
+        Ok(vectors)
+    }
+
+    /// The word vector of a user, weighted by `count` or `tfidf`
+    pub fn vector(&self, username: &str, weighting: &str) -> Option<Vector> {
+        let counts = self.users.get(username)?;
+
+        let vector = counts
+            .iter()
+            .map(|(word, count)| {
+                let weight = match weighting {
+                    "tfidf" => vector::idf(
+                        self.users.len(),
+                        self.words.get(word).map_or(0, |(_, users)| *users as usize),
+                    ),
+                    _ => 1.0,
+                };
+
+                (word.to_owned(), *count as f64 * weight)
+            })
+            .collect();
+
+        Some(vector)
+    }
+}
+
+/// Gets the cached vectors, reloading them if counts were written since they were built
+///
+/// Usernames are resolved to their identity unless `raw`.
+pub fn get(state: &AppState, conn: &Connection, raw: bool) -> Result<Arc<Vectors>, Error> {
+    let version = state.counter_version.load(Ordering::Acquire);
+
+    if let Some(vectors) = state
+        .vectors
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&raw)
+        .filter(|vectors| vectors.version == version)
+    {
+        return Ok(vectors.clone());
+    }
+
+    let vectors = Arc::new(Vectors::load(conn, version, raw)?);
+
+    state
+        .vectors
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(raw, vectors.clone());
+
+    Ok(vectors)
+}
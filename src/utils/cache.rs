@@ -0,0 +1,178 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{
+        PoisonError, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// A value with when it was built, the version it was built at and when it was last used
+struct Entry<V> {
+    at: Instant,
+    version: u64,
+    used: AtomicU64,
+    value: V,
+}
+
+/// A map of values that expire after a time to live
+///
+/// Values are also tagged with the data version they were built from, so a value
+/// built while the data changed is never served. When the map is full the least
+/// recently used value makes room for the new one.
+pub struct Cache<K, V> {
+    ttl: Duration,
+    capacity: usize,
+    entries: RwLock<HashMap<K, Entry<V>>>,
+    clock: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Cache<K, V> {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: RwLock::new(HashMap::new()),
+            clock: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn fresh(&self, entry: &Entry<V>, version: u64) -> bool {
+        entry.version == version && entry.at.elapsed() < self.ttl
+    }
+
+    /// Gets a value that is still fresh and was built at the version
+    pub fn get(&self, key: &K, version: u64) -> Option<V> {
+        let value = self
+            .entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(key)
+            .filter(|entry| self.fresh(entry, version))
+            .map(|entry| {
+                let tick = self.clock.fetch_add(1, Ordering::Relaxed);
+                entry.used.store(tick, Ordering::Relaxed);
+                entry.value.clone()
+            });
+
+        match value {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+
+        value
+    }
+
+    pub fn insert(&self, key: K, version: u64, value: V) {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+
+        // Drop the expired values so the map only grows with the live keys
+        entries.retain(|_, entry| self.fresh(entry, version));
+
+        // Make room by dropping the least recently used value
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used.load(Ordering::Relaxed))
+                .map(|(key, _)| key.clone());
+
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+
+        let tick = self.clock.fetch_add(1, Ordering::Relaxed);
+
+        entries.insert(
+            key,
+            Entry {
+                at: Instant::now(),
+                version,
+                used: AtomicU64::new(tick),
+                value,
+            },
+        );
+    }
+
+    pub fn clear(&self) {
+        self.entries
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_test() {
+        let cache = Cache::new(Duration::from_secs(60), 8);
+
+        assert_eq!(cache.get(&"a", 0), None);
+
+        cache.insert("a", 0, 1);
+        assert_eq!(cache.get(&"a", 0), Some(1));
+        assert_eq!(cache.get(&"a", 1), None);
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+
+        cache.clear();
+        assert_eq!(cache.get(&"a", 0), None);
+        assert_eq!(cache.len(), 0);
+
+        let expired = Cache::new(Duration::ZERO, 8);
+
+        expired.insert("a", 0, 1);
+        assert_eq!(expired.get(&"a", 0), None);
+    }
+
+    #[test]
+    fn eviction_test() {
+        let cache = Cache::new(Duration::from_secs(60), 2);
+
+        cache.insert("a", 0, 1);
+        cache.insert("b", 0, 2);
+        assert_eq!(cache.get(&"a", 0), Some(1));
+
+        // `b` is the least recently used
+        cache.insert("c", 0, 3);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"b", 0), None);
+        assert_eq!(cache.get(&"a", 0), Some(1));
+        assert_eq!(cache.get(&"c", 0), Some(3));
+
+        // Replacing a key doesn't evict another
+        cache.insert("c", 0, 4);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"a", 0), Some(1));
+    }
+}
@@ -0,0 +1,116 @@
+use regex::Regex;
+use std::sync::LazyLock;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A custom emote in the `<:name:id>` chat syntax, `<a:name:id>` when animated
+static RE_EMOTE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<a?:[a-zA-Z0-9_]{2,32}:[0-9]{1,20}>").unwrap());
+
+/// Gets the form an emoji or custom emote is stored in
+///
+/// Unicode emoji are stored fully qualified, so `❤` and `❤️` count as the same emoji.
+/// Sequences joined with zero width joiners, skin tones, flags and keycaps are one emoji.
+///
+/// Returns `None` unless the whole token is a single emoji or custom emote
+pub fn parse(token: &str) -> Option<String> {
+    if RE_EMOTE
+        .find(token)
+        .is_some_and(|emote| emote.len() == token.len())
+    {
+        return Some(token.to_owned());
+    }
+
+    emojis::get(token).map(|emoji| emoji.as_str().to_owned())
+}
+
+/// Whether a stored word is an emoji or custom emote rather than text
+pub fn is_emoji(word: &str) -> bool {
+    parse(word).is_some()
+}
+
+/// Whether a stored emoji is a custom emote
+pub fn is_emote(word: &str) -> bool {
+    word.starts_with('<')
+}
+
+/// Splits a token on the emoji and custom emotes in it, keeping the text between them
+///
+/// `"gg😂<:pog:123>"` splits into `"gg"`, `"😂"` and `"<:pog:123>"`.
+pub fn split(token: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut last = 0;
+
+    for emote in RE_EMOTE.find_iter(token) {
+        split_unicode(&token[last..emote.start()], &mut segments);
+        segments.push(emote.as_str());
+        last = emote.end();
+    }
+
+    split_unicode(&token[last..], &mut segments);
+
+    segments
+}
+
+/// Splits text on its Unicode emoji, reading each grapheme cluster as one character
+fn split_unicode<'a>(text: &'a str, segments: &mut Vec<&'a str>) {
+    let mut start = 0;
+
+    for (i, grapheme) in text.grapheme_indices(true) {
+        if emojis::get(grapheme).is_none() {
+            continue;
+        }
+
+        if start < i {
+            segments.push(&text[start..i]);
+        }
+
+        segments.push(grapheme);
+        start = i + grapheme.len();
+    }
+
+    if start < text.len() {
+        segments.push(&text[start..]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(parse("😂"), Some("😂".to_string()));
+        assert_eq!(parse("❤"), Some("❤\u{fe0f}".to_string()));
+        assert_eq!(parse("👍🏽"), Some("👍🏽".to_string()));
+        assert_eq!(
+            parse("👨\u{200d}👩\u{200d}👧"),
+            Some("👨\u{200d}👩\u{200d}👧".to_string())
+        );
+        assert_eq!(parse("🇺🇸"), Some("🇺🇸".to_string()));
+        assert_eq!(parse("<:pog:123>"), Some("<:pog:123>".to_string()));
+        assert_eq!(parse("<a:pog:123>"), Some("<a:pog:123>".to_string()));
+        assert_eq!(parse("gg<:pog:123>"), None);
+        assert_eq!(parse(":pog:"), None);
+        assert_eq!(parse("😂😂"), None);
+        assert_eq!(parse("hello"), None);
+        assert_eq!(parse("1"), None);
+    }
+
+    #[test]
+    fn split_test() {
+        assert_eq!(split("hello"), vec!["hello"]);
+        assert_eq!(split("gg😂<:pog:123>"), vec!["gg", "😂", "<:pog:123>"]);
+        assert_eq!(split("😂😂"), vec!["😂", "😂"]);
+        assert_eq!(
+            split("hi👨\u{200d}👩\u{200d}👧!"),
+            vec!["hi", "👨\u{200d}👩\u{200d}👧", "!"]
+        );
+        assert!(split("").is_empty());
+    }
+
+    #[test]
+    fn is_emote_test() {
+        assert!(is_emote("<:pog:123>"));
+        assert!(!is_emote("😂"));
+    }
+}
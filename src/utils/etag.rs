@@ -0,0 +1,43 @@
+use std::hash::{DefaultHasher, Hasher};
+
+/// Builds a weak entity tag from a response body
+pub fn weak(body: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(body);
+
+    format!("W/\"{:016x}\"", hasher.finish())
+}
+
+/// Checks an `If-None-Match` header against an entity tag using the weak comparison
+pub fn matches(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+    let etag = opaque(etag);
+
+    if_none_match
+        .split(',')
+        .any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weak_test() {
+        let etag = weak(b"{\"data\":[]}");
+
+        assert!(etag.starts_with("W/\"") && etag.ends_with('"'));
+        assert_eq!(etag, weak(b"{\"data\":[]}"));
+        assert_ne!(etag, weak(b"{\"data\":[1]}"));
+    }
+
+    #[test]
+    fn matches_test() {
+        assert!(matches("W/\"abc\"", "W/\"abc\""));
+        assert!(matches("\"abc\"", "W/\"abc\""));
+        assert!(matches("\"xyz\", W/\"abc\"", "W/\"abc\""));
+        assert!(matches("*", "W/\"abc\""));
+        assert!(!matches("W/\"xyz\"", "W/\"abc\""));
+        assert!(!matches("", "W/\"abc\""));
+    }
+}
@@ -1 +1,9 @@
+pub mod cache;
+pub mod emoji;
+pub mod etag;
+pub mod normalize;
+pub mod pagination;
+pub mod stats;
 pub mod string;
+pub mod time;
+pub mod vector;
@@ -0,0 +1,176 @@
+use crate::utils::emoji;
+
+use rust_stemmers::{Algorithm, Stemmer};
+use std::collections::BTreeSet;
+use unicode_normalization::UnicodeNormalization;
+
+const ENGLISH_STOPWORDS: &str = include_str!("../config/stopwords/english.txt");
+
+/// Turns raw tokens into the words stored in `counter`
+///
+/// Used at ingest and at query time so both sides agree on what a word is.
+pub struct Normalizer {
+    stemmer: Option<Stemmer>,
+    stopwords: BTreeSet<String>,
+    /// The most words counted together as a phrase, phrases aren't counted below 2
+    phrase_length: usize,
+}
+
+impl Normalizer {
+    pub fn new(stemming: bool, stopword_lists: &[&str]) -> Self {
+        let mut normalizer = Self {
+            stemmer: stemming.then(|| Stemmer::create(Algorithm::English)),
+            stopwords: BTreeSet::new(),
+            phrase_length: 0,
+        };
+
+        // Stopwords go through the same pipeline so they match the stored words
+        let stopwords = stopword_lists
+            .iter()
+            .flat_map(|list| list.lines())
+            .filter_map(|line| normalizer.normalize(line))
+            .collect();
+
+        normalizer.stopwords = stopwords;
+        normalizer
+    }
+
+    /// Reads the pipeline from the environment
+    ///
+    /// - `COUNTER_STEMMING`: Stem words with the English Snowball stemmer (true|false). Default `false`
+    /// - `COUNTER_STOPWORDS`: Comma separated stopword lists, either `english`, `none` or a path
+    ///   to a file with one word per line. Default `english`
+    /// - `COUNTER_PHRASE_LENGTH`: Count bigrams (2) or bigrams and trigrams (3) as phrases,
+    ///   `0` counts none. Default `0`
+    pub fn from_env() -> Self {
+        let stemming = std::env::var("COUNTER_STEMMING").is_ok_and(|s| s == "true");
+        let lists = std::env::var("COUNTER_STOPWORDS").unwrap_or("english".to_string());
+        let phrase_length = std::env::var("COUNTER_PHRASE_LENGTH")
+            .ok()
+            .and_then(|length| length.parse::<usize>().ok())
+            .unwrap_or(0);
+
+        let lists = lists
+            .split(',')
+            .map(str::trim)
+            .filter(|list| !list.is_empty() && *list != "none")
+            .map(|list| match list {
+                "english" => ENGLISH_STOPWORDS.to_string(),
+                path => std::fs::read_to_string(path)
+                    .unwrap_or_else(|e| panic!("Failed to read stopword list `{path}`: {e}")),
+            })
+            .collect::<Vec<_>>();
+
+        Self::new(
+            stemming,
+            &lists.iter().map(String::as_str).collect::<Vec<_>>(),
+        )
+        .with_phrase_length(phrase_length)
+    }
+
+    /// Counts phrases of up to `length` words, capped at trigrams
+    pub fn with_phrase_length(mut self, length: usize) -> Self {
+        self.phrase_length = if length < 2 { 0 } else { length.min(3) };
+        self
+    }
+
+    /// Case folds, strips punctuation and optionally stems a token
+    ///
+    /// Emoji and custom emotes are their own kind of token and are kept in the form
+    /// `emoji::parse` stores them in.
+    ///
+    /// Returns `None` when nothing is left of the token
+    pub fn normalize(&self, token: &str) -> Option<String> {
+        if let Some(emoji) = emoji::parse(token) {
+            return Some(emoji);
+        }
+
+        let folded = caseless::default_case_fold_str(&token.nfkc().collect::<String>());
+
+        let stripped = folded
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+            .collect::<String>();
+
+        let word = stripped.trim_matches(['-', '_']);
+
+        if word.is_empty() {
+            return None;
+        }
+
+        match self.stemmer {
+            Some(ref stemmer) => Some(stemmer.stem(word).into_owned()),
+            None => Some(word.to_owned()),
+        }
+    }
+
+    pub fn stopwords(&self) -> impl Iterator<Item = &String> {
+        self.stopwords.iter()
+    }
+
+    pub fn is_stopword(&self, word: &str) -> bool {
+        self.stopwords.contains(word)
+    }
+
+    /// The most words counted together as a phrase, `0` when phrases aren't counted
+    pub fn phrase_length(&self) -> usize {
+        self.phrase_length
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_test() {
+        let normalizer = Normalizer::new(false, &[]);
+        assert_eq!(normalizer.normalize("Hello"), Some("hello".to_string()));
+        assert_eq!(normalizer.normalize("hello!"), Some("hello".to_string()));
+        assert_eq!(
+            normalizer.normalize("\"Straße\""),
+            Some("strasse".to_string())
+        );
+        assert_eq!(normalizer.normalize("ﬁne"), Some("fine".to_string()));
+        assert_eq!(normalizer.normalize("re-do"), Some("re-do".to_string()));
+        assert_eq!(normalizer.normalize("..."), None);
+        assert_eq!(normalizer.normalize("--"), None);
+        assert_eq!(normalizer.normalize("❤"), Some("❤\u{fe0f}".to_string()));
+        assert_eq!(
+            normalizer.normalize("<:Pog:123>"),
+            Some("<:Pog:123>".to_string())
+        );
+    }
+
+    #[test]
+    fn stemming_test() {
+        let normalizer = Normalizer::new(true, &[]);
+        assert_eq!(normalizer.normalize("Running"), Some("run".to_string()));
+        assert_eq!(normalizer.normalize("cats"), Some("cat".to_string()));
+    }
+
+    #[test]
+    fn stopwords_test() {
+        let normalizer = Normalizer::new(false, &["The\nand\n\n", ENGLISH_STOPWORDS]);
+        let stopwords = normalizer.stopwords().collect::<Vec<_>>();
+        assert!(stopwords.contains(&&"the".to_string()));
+        assert!(stopwords.contains(&&"and".to_string()));
+        assert!(!stopwords.contains(&&"hello".to_string()));
+        assert!(!stopwords.iter().any(|word| word.is_empty()));
+        assert!(normalizer.is_stopword("the"));
+        assert!(!normalizer.is_stopword("hello"));
+    }
+
+    #[test]
+    fn phrase_length_test() {
+        let normalizer = Normalizer::new(false, &[]);
+        assert_eq!(normalizer.phrase_length(), 0);
+        assert_eq!(normalizer.with_phrase_length(1).phrase_length(), 0);
+
+        let normalizer = Normalizer::new(false, &[]);
+        assert_eq!(normalizer.with_phrase_length(2).phrase_length(), 2);
+
+        let normalizer = Normalizer::new(false, &[]);
+        assert_eq!(normalizer.with_phrase_length(5).phrase_length(), 3);
+    }
+}
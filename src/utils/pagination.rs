@@ -0,0 +1,39 @@
+/// Builds the link to a page of a list, keeping every other parameter of the query
+///
+/// `around` is dropped because it overrides `page`.
+pub fn link(path: &str, query: &str, page: u32, limit: u32) -> String {
+    let mut link = format!("{path}?page={page}&limit={limit}");
+
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let key = pair.split('=').next().unwrap_or_default();
+
+        if !matches!(key, "page" | "limit" | "around") {
+            link += "&";
+            link += pair;
+        }
+    }
+
+    link
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn link_test() {
+        assert_eq!(link("/counter", "", 1, 10), "/counter?page=1&limit=10");
+        assert_eq!(
+            link("/counter", "word=hi&page=3&order=asc&limit=5", 4, 5),
+            "/counter?page=4&limit=5&word=hi&order=asc"
+        );
+        assert_eq!(
+            link("/counter/users", "around=adits87&ranking=dense", 2, 10),
+            "/counter/users?page=2&limit=10&ranking=dense"
+        );
+        assert_eq!(
+            link("/counter/words/hi%20there", "excludeStopwords=true&", 1, 3),
+            "/counter/words/hi%20there?page=1&limit=3&excludeStopwords=true"
+        );
+    }
+}
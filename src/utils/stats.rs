@@ -0,0 +1,85 @@
+/// Least squares fit of `y = slope * x + intercept`
+///
+/// Returns the slope, intercept and coefficient of determination (R²)
+pub fn linear_regression(points: &[(f64, f64)]) -> (f64, f64, f64) {
+    let n = points.len() as f64;
+
+    if points.len() < 2 {
+        return (0.0, 0.0, 0.0);
+    }
+
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+    let ss_xy = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum::<f64>();
+    let ss_xx = points
+        .iter()
+        .map(|(x, _)| (x - mean_x).powi(2))
+        .sum::<f64>();
+    let ss_yy = points
+        .iter()
+        .map(|(_, y)| (y - mean_y).powi(2))
+        .sum::<f64>();
+
+    if ss_xx == 0.0 {
+        return (0.0, mean_y, 0.0);
+    }
+
+    let slope = ss_xy / ss_xx;
+    let intercept = mean_y - slope * mean_x;
+    let r2 = if ss_yy == 0.0 {
+        1.0
+    } else {
+        ss_xy.powi(2) / (ss_xx * ss_yy)
+    };
+
+    (slope, intercept, r2)
+}
+
+/// Fits Zipf's law to word frequencies by regressing log frequency on log rank
+///
+/// Returns the slope and R², natural language sits close to a slope of -1
+pub fn zipf(frequencies: &[u32]) -> (f64, f64) {
+    let mut frequencies = frequencies
+        .iter()
+        .filter(|f| **f > 0)
+        .copied()
+        .collect::<Vec<_>>();
+    frequencies.sort_unstable_by(|a, b| b.cmp(a));
+
+    let points = frequencies
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (((i + 1) as f64).ln(), (*f as f64).ln()))
+        .collect::<Vec<_>>();
+
+    let (slope, _, r2) = linear_regression(&points);
+
+    (slope, r2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_regression_test() {
+        let (slope, intercept, r2) = linear_regression(&[(0.0, 1.0), (1.0, 3.0), (2.0, 5.0)]);
+        assert!((slope - 2.0).abs() < 1e-9);
+        assert!((intercept - 1.0).abs() < 1e-9);
+        assert!((r2 - 1.0).abs() < 1e-9);
+
+        assert_eq!(linear_regression(&[(1.0, 1.0)]), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn zipf_test() {
+        // Frequencies of exactly 1 / rank
+        let (slope, r2) = zipf(&[10, 60, 20, 30, 15, 12]);
+        assert!((slope + 1.0).abs() < 1e-9);
+        assert!((r2 - 1.0).abs() < 1e-9);
+    }
+}
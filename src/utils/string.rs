@@ -1,6 +1,6 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 
-pub fn similarity(string1: &String, string2: &String) -> f32 {
+pub fn similarity(string1: &str, string2: &str) -> f32 {
     let string1 = string1.to_lowercase();
     let string2 = string2.to_lowercase();
 
@@ -26,7 +26,7 @@ pub fn similarity(string1: &String, string2: &String) -> f32 {
 
     let mut count: usize = 0;
 
-    while let Some(curr_char) = iter1.next() {
+    for curr_char in iter1 {
         let inserted = bigrams1.insert(last_char1.to_string() + &curr_char.to_string());
         last_char1 = curr_char;
         if inserted {
@@ -35,7 +35,7 @@ pub fn similarity(string1: &String, string2: &String) -> f32 {
     }
 
     let mut intersection: usize = 0;
-    while let Some(curr_char) = iter2.next() {
+    for curr_char in iter2 {
         let bigram = last_char2.to_string() + &curr_char.to_string();
         let is_seen = bigrams1.contains(&bigram);
         let inserted = bigrams2.insert(bigram);
@@ -51,7 +51,514 @@ pub fn similarity(string1: &String, string2: &String) -> f32 {
         }
     }
 
-    return 2f32 * (intersection as f32) / (count as f32);
+    2f32 * (intersection as f32) / (count as f32)
+}
+
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Splits CSV into records of fields, undoing the quoting done by [`csv_field`]
+pub fn csv_records(input: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records
+}
+
+/// Whether a name can be used as a counter username, matching the request validation
+pub fn valid_username(name: &str) -> bool {
+    (3..=32).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The distinct lowercase runs of `n` characters in a string
+pub fn ngrams(string: &str, n: usize) -> BTreeSet<String> {
+    let chars = lower_chars(string);
+
+    chars.windows(n).map(|w| w.iter().collect()).collect()
+}
+
+pub fn tokenize(message: &str) -> Vec<String> {
+    message.split_whitespace().map(str::to_owned).collect()
+}
+
+/// A way of scoring how alike two strings are, from `0.0` to `1.0`
+pub trait Similarity: Sync {
+    /// The name the algorithm is selected and reported by
+    fn name(&self) -> &'static str;
+
+    /// Whether searches only score the terms holding every character of the query in order,
+    /// otherwise every term is scored and kept when it is at least [`MIN_SIMILARITY`]
+    fn subsequence(&self) -> bool {
+        false
+    }
+
+    fn similarity(&self, string1: &str, string2: &str) -> f32;
+}
+
+/// The lowest score a term is found with when the algorithm doesn't search by subsequence
+pub const MIN_SIMILARITY: f32 = 0.5;
+
+/// Sørensen–Dice coefficient on character bigrams
+pub struct Dice;
+
+/// Levenshtein edit distance, relative to the longer string
+pub struct Levenshtein;
+
+/// Damerau–Levenshtein edit distance with adjacent transpositions, relative to the longer string
+pub struct Damerau;
+
+/// Jaro–Winkler similarity, favouring a shared prefix
+pub struct JaroWinkler;
+
+/// Jaccard index of padded character trigrams
+pub struct Trigram;
+
+/// How alike the strings sound, half the matching Soundex positions and half the
+/// Levenshtein similarity of the Metaphone keys
+pub struct Phonetic;
+
+impl Similarity for Dice {
+    fn name(&self) -> &'static str {
+        "dice"
+    }
+
+    fn subsequence(&self) -> bool {
+        true
+    }
+
+    fn similarity(&self, string1: &str, string2: &str) -> f32 {
+        similarity(string1, string2)
+    }
+}
+
+impl Similarity for Levenshtein {
+    fn name(&self) -> &'static str {
+        "levenshtein"
+    }
+
+    fn similarity(&self, string1: &str, string2: &str) -> f32 {
+        let (chars1, chars2) = (lower_chars(string1), lower_chars(string2));
+        relative(
+            levenshtein(&chars1, &chars2),
+            chars1.len().max(chars2.len()),
+        )
+    }
+}
+
+impl Similarity for Damerau {
+    fn name(&self) -> &'static str {
+        "damerau"
+    }
+
+    fn similarity(&self, string1: &str, string2: &str) -> f32 {
+        let (chars1, chars2) = (lower_chars(string1), lower_chars(string2));
+        relative(damerau(&chars1, &chars2), chars1.len().max(chars2.len()))
+    }
+}
+
+impl Similarity for JaroWinkler {
+    fn name(&self) -> &'static str {
+        "jaro"
+    }
+
+    fn similarity(&self, string1: &str, string2: &str) -> f32 {
+        jaro_winkler(&lower_chars(string1), &lower_chars(string2))
+    }
+}
+
+impl Similarity for Trigram {
+    fn name(&self) -> &'static str {
+        "trigram"
+    }
+
+    fn similarity(&self, string1: &str, string2: &str) -> f32 {
+        let trigrams1 = trigrams(string1);
+        let trigrams2 = trigrams(string2);
+        let union = trigrams1.union(&trigrams2).count();
+
+        if union == 0 {
+            return 0.0;
+        }
+
+        trigrams1.intersection(&trigrams2).count() as f32 / union as f32
+    }
+}
+
+impl Similarity for Phonetic {
+    fn name(&self) -> &'static str {
+        "phonetic"
+    }
+
+    fn similarity(&self, string1: &str, string2: &str) -> f32 {
+        let (soundex1, soundex2) = (soundex(string1), soundex(string2));
+        let (metaphone1, metaphone2) = (metaphone(string1), metaphone(string2));
+
+        if soundex1.is_empty() || soundex2.is_empty() {
+            return 0.0;
+        }
+
+        let soundex_score = soundex1
+            .chars()
+            .zip(soundex2.chars())
+            .filter(|(a, b)| a == b)
+            .count() as f32
+            / 4.0;
+
+        let metaphone1 = metaphone1.chars().collect::<Vec<_>>();
+        let metaphone2 = metaphone2.chars().collect::<Vec<_>>();
+        let metaphone_score = relative(
+            levenshtein(&metaphone1, &metaphone2),
+            metaphone1.len().max(metaphone2.len()),
+        );
+
+        (soundex_score + metaphone_score) / 2.0
+    }
+}
+
+/// Gets a similarity algorithm by the name it is selected with
+pub fn algorithm(name: &str) -> Option<&'static dyn Similarity> {
+    match name {
+        "dice" => Some(&Dice),
+        "levenshtein" => Some(&Levenshtein),
+        "damerau" => Some(&Damerau),
+        "jaro" => Some(&JaroWinkler),
+        "trigram" => Some(&Trigram),
+        "phonetic" => Some(&Phonetic),
+        _ => None,
+    }
+}
+
+fn lower_chars(string: &str) -> Vec<char> {
+    string.to_lowercase().chars().collect()
+}
+
+fn relative(distance: usize, length: usize) -> f32 {
+    if length == 0 {
+        return 1.0;
+    }
+
+    1.0 - distance as f32 / length as f32
+}
+
+pub fn levenshtein(chars1: &[char], chars2: &[char]) -> usize {
+    let mut previous = (0..=chars2.len()).collect::<Vec<_>>();
+
+    for (i, char1) in chars1.iter().enumerate() {
+        let mut current = vec![i + 1; chars2.len() + 1];
+
+        for (j, char2) in chars2.iter().enumerate() {
+            let cost = usize::from(char1 != char2);
+            current[j + 1] = (previous[j + 1] + 1)
+                .min(current[j] + 1)
+                .min(previous[j] + cost);
+        }
+
+        previous = current;
+    }
+
+    previous[chars2.len()]
+}
+
+/// The optimal string alignment distance, which also counts swapping two neighbours as one edit
+pub fn damerau(chars1: &[char], chars2: &[char]) -> usize {
+    let mut distances = vec![vec![0; chars2.len() + 1]; chars1.len() + 1];
+
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+
+    distances[0] = (0..=chars2.len()).collect();
+
+    for i in 1..=chars1.len() {
+        for j in 1..=chars2.len() {
+            let cost = usize::from(chars1[i - 1] != chars2[j - 1]);
+
+            distances[i][j] = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && chars1[i - 1] == chars2[j - 2] && chars1[i - 2] == chars2[j - 1] {
+                distances[i][j] = distances[i][j].min(distances[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    distances[chars1.len()][chars2.len()]
+}
+
+pub fn jaro_winkler(chars1: &[char], chars2: &[char]) -> f32 {
+    if chars1.is_empty() && chars2.is_empty() {
+        return 1.0;
+    }
+
+    if chars1.is_empty() || chars2.is_empty() {
+        return 0.0;
+    }
+
+    // Characters match if they are the same and not too far apart
+    let window = (chars1.len().max(chars2.len()) / 2).saturating_sub(1);
+    let mut matched1 = vec![false; chars1.len()];
+    let mut matched2 = vec![false; chars2.len()];
+    let mut matches = 0;
+
+    for (i, char1) in chars1.iter().enumerate() {
+        let start = i.saturating_sub(window);
+        let end = (i + window + 1).min(chars2.len());
+
+        for j in start..end {
+            if !matched2[j] && chars2[j] == *char1 {
+                matched1[i] = true;
+                matched2[j] = true;
+                matches += 1;
+                break;
+            }
+        }
+    }
+
+    if matches == 0 {
+        return 0.0;
+    }
+
+    // Count the matched characters that are out of order
+    let matched_chars2 = chars2
+        .iter()
+        .zip(&matched2)
+        .filter(|(_, matched)| **matched)
+        .map(|(char2, _)| char2);
+
+    let transpositions = chars1
+        .iter()
+        .zip(&matched1)
+        .filter(|(_, matched)| **matched)
+        .zip(matched_chars2)
+        .filter(|((char1, _), char2)| *char1 != *char2)
+        .count()
+        / 2;
+
+    let matches = matches as f32;
+    let jaro = (matches / chars1.len() as f32
+        + matches / chars2.len() as f32
+        + (matches - transpositions as f32) / matches)
+        / 3.0;
+
+    let prefix = chars1
+        .iter()
+        .zip(chars2)
+        .take(4)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    jaro + prefix as f32 * 0.1 * (1.0 - jaro)
+}
+
+fn trigrams(string: &str) -> HashSet<String> {
+    let chars = format!("  {} ", string.to_lowercase())
+        .chars()
+        .collect::<Vec<_>>();
+
+    chars.windows(3).map(|w| w.iter().collect()).collect()
+}
+
+/// The American Soundex code of a word, or an empty string if it has no letters
+pub fn soundex(word: &str) -> String {
+    let code = |c: char| match c {
+        'B' | 'F' | 'P' | 'V' => Some('1'),
+        'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some('2'),
+        'D' | 'T' => Some('3'),
+        'L' => Some('4'),
+        'M' | 'N' => Some('5'),
+        'R' => Some('6'),
+        _ => None,
+    };
+
+    let mut letters = word
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_uppercase());
+
+    let Some(first) = letters.next() else {
+        return String::new();
+    };
+
+    let mut result = first.to_string();
+    let mut last = code(first);
+
+    for c in letters {
+        let current = code(c);
+
+        if current.is_some() && current != last {
+            result.extend(current);
+        }
+
+        // H and W don't separate letters with the same code, vowels do
+        if c != 'H' && c != 'W' {
+            last = current;
+        }
+
+        if result.len() == 4 {
+            break;
+        }
+    }
+
+    format!("{result:0<4}")
+}
+
+/// The original Metaphone key of a word
+pub fn metaphone(word: &str) -> String {
+    let mut chars = word
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_uppercase())
+        .collect::<Vec<_>>();
+
+    // Letters that are silent or changed at the start of a word
+    match chars.as_slice() {
+        ['A', 'E', ..] | ['G', 'N', ..] | ['K', 'N', ..] | ['P', 'N', ..] | ['W', 'R', ..] => {
+            chars.remove(0);
+        }
+        ['X', ..] => chars[0] = 'S',
+        ['W', 'H', ..] => {
+            chars.remove(1);
+        }
+        _ => {}
+    }
+
+    let is_vowel = |c: Option<&char>| matches!(c, Some('A' | 'E' | 'I' | 'O' | 'U'));
+    let mut key = String::new();
+
+    for i in 0..chars.len() {
+        let c = chars[i];
+        let prev = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1);
+        let after = chars.get(i + 2);
+
+        // Doubled letters sound once, except C
+        if prev == Some(c) && c != 'C' {
+            continue;
+        }
+
+        match c {
+            'A' | 'E' | 'I' | 'O' | 'U' => {
+                if i == 0 {
+                    key.push(c);
+                }
+            }
+            'B' => {
+                if !(prev == Some('M') && next.is_none()) {
+                    key.push('B');
+                }
+            }
+            'C' => {
+                if next == Some(&'I') && after == Some(&'A') || next == Some(&'H') {
+                    key.push(if prev == Some('S') { 'K' } else { 'X' });
+                } else if matches!(next, Some('I' | 'E' | 'Y')) {
+                    if prev != Some('S') {
+                        key.push('S');
+                    }
+                } else {
+                    key.push('K');
+                }
+            }
+            'D' => {
+                if next == Some(&'G') && matches!(after, Some('E' | 'I' | 'Y')) {
+                    key.push('J');
+                } else {
+                    key.push('T');
+                }
+            }
+            'G' => {
+                let silent = (next == Some(&'H') && after.is_some() && !is_vowel(after))
+                    || (next == Some(&'N') && (after.is_none() || chars[i + 2..] == ['E', 'D']))
+                    || prev == Some('D') && matches!(next, Some('E' | 'I' | 'Y'));
+
+                if silent {
+                    continue;
+                }
+
+                if matches!(next, Some('I' | 'E' | 'Y')) && prev != Some('G') {
+                    key.push('J');
+                } else {
+                    key.push('K');
+                }
+            }
+            'H' => {
+                let after_vowel = is_vowel(prev.as_ref());
+                let changes = matches!(prev, Some('C' | 'S' | 'P' | 'T' | 'G'));
+
+                if !changes && (!after_vowel || is_vowel(next)) {
+                    key.push('H');
+                }
+            }
+            'K' => {
+                if prev != Some('C') {
+                    key.push('K');
+                }
+            }
+            'P' => key.push(if next == Some(&'H') { 'F' } else { 'P' }),
+            'Q' => key.push('K'),
+            'S' => {
+                if next == Some(&'H') || next == Some(&'I') && matches!(after, Some('O' | 'A')) {
+                    key.push('X');
+                } else {
+                    key.push('S');
+                }
+            }
+            'T' => {
+                if next == Some(&'I') && matches!(after, Some('O' | 'A')) {
+                    key.push('X');
+                } else if next == Some(&'H') {
+                    key.push('0');
+                } else if !(next == Some(&'C') && after == Some(&'H')) {
+                    key.push('T');
+                }
+            }
+            'V' => key.push('F'),
+            'W' | 'Y' => {
+                if is_vowel(next) {
+                    key.push(c);
+                }
+            }
+            'X' => key.push_str("KS"),
+            'Z' => key.push('S'),
+            _ => key.push(c),
+        }
+    }
+
+    key
 }
 
 #[cfg(test)]
@@ -69,4 +576,123 @@ mod tests {
         assert_eq!(result3, 1.0);
         assert_eq!(result4, 8.0 / 11.0);
     }
+
+    #[test]
+    fn csv_field_test() {
+        assert_eq!(csv_field("hello"), "hello");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn csv_records_test() {
+        assert_eq!(
+            csv_records("username,message\r\nadits87,\"hi, \"\"you\"\"\nthere\"\n"),
+            vec![
+                vec!["username", "message"],
+                vec!["adits87", "hi, \"you\"\nthere"]
+            ]
+        );
+        assert_eq!(csv_records("a,,b"), vec![vec!["a", "", "b"]]);
+        assert!(csv_records("").is_empty());
+    }
+
+    #[test]
+    fn valid_username_test() {
+        assert!(valid_username("adits_87"));
+        assert!(!valid_username("ad"));
+        assert!(!valid_username("adits.87"));
+    }
+
+    #[test]
+    fn ngrams_test() {
+        assert_eq!(
+            ngrams("Hello", 1),
+            BTreeSet::from(["e", "h", "l", "o"].map(String::from))
+        );
+        assert_eq!(
+            ngrams("hello", 2),
+            BTreeSet::from(["el", "he", "ll", "lo"].map(String::from))
+        );
+        assert!(ngrams("a", 2).is_empty());
+    }
+
+    #[test]
+    fn tokenize_test() {
+        assert_eq!(
+            tokenize("hello  world\nhello"),
+            vec!["hello", "world", "hello"]
+        );
+        assert!(tokenize("   ").is_empty());
+    }
+
+    #[test]
+    fn levenshtein_test() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+
+        assert_eq!(levenshtein(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(levenshtein(&chars(""), &chars("abc")), 3);
+        assert_eq!(levenshtein(&chars("ab"), &chars("ba")), 2);
+        assert_eq!(damerau(&chars("ab"), &chars("ba")), 1);
+        assert_eq!(damerau(&chars("ca"), &chars("abc")), 3);
+        assert_eq!(Levenshtein.similarity("Hello", "hello"), 1.0);
+        assert_eq!(Levenshtein.similarity("kitten", "sitting"), 1.0 - 3.0 / 7.0);
+    }
+
+    #[test]
+    fn jaro_winkler_test() {
+        let score = |a: &str, b: &str| (JaroWinkler.similarity(a, b) * 1000.0).round() / 1000.0;
+
+        assert_eq!(score("martha", "marhta"), 0.961);
+        assert_eq!(score("dixon", "dicksonx"), 0.813);
+        assert_eq!(score("abc", "xyz"), 0.0);
+        assert_eq!(score("same", "same"), 1.0);
+    }
+
+    #[test]
+    fn trigram_test() {
+        assert_eq!(Trigram.similarity("word", "word"), 1.0);
+        assert_eq!(Trigram.similarity("abc", "xyz"), 0.0);
+        assert_eq!(Trigram.similarity("cat", "cart"), 2.0 / 7.0);
+    }
+
+    #[test]
+    fn soundex_test() {
+        assert_eq!(soundex("Robert"), "R163");
+        assert_eq!(soundex("Rupert"), "R163");
+        assert_eq!(soundex("Ashcraft"), "A261");
+        assert_eq!(soundex("Tymczak"), "T522");
+        assert_eq!(soundex("Pfister"), "P236");
+        assert_eq!(soundex("Lee"), "L000");
+        assert_eq!(soundex("123"), "");
+    }
+
+    #[test]
+    fn metaphone_test() {
+        assert_eq!(metaphone("thumb"), "0M");
+        assert_eq!(metaphone("knight"), "NT");
+        assert_eq!(metaphone("phone"), "FN");
+        assert_eq!(metaphone("fone"), "FN");
+        assert_eq!(metaphone("school"), "SKL");
+        assert_eq!(metaphone("science"), "SNS");
+        assert_eq!(metaphone("Xavier"), "SFR");
+        assert_eq!(Phonetic.similarity("phone", "fone"), 0.875);
+        assert_eq!(Phonetic.similarity("phone", "123"), 0.0);
+    }
+
+    #[test]
+    fn algorithm_test() {
+        for name in [
+            "dice",
+            "levenshtein",
+            "damerau",
+            "jaro",
+            "trigram",
+            "phonetic",
+        ] {
+            assert_eq!(algorithm(name).map(|a| a.name()), Some(name));
+        }
+
+        assert!(algorithm("cosine").is_none());
+    }
 }
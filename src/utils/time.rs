@@ -0,0 +1,203 @@
+use chrono::{
+    DateTime, Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike,
+};
+
+pub fn hour(at: NaiveDateTime) -> String {
+    at.format("%Y-%m-%d %H:00:00").to_string()
+}
+
+/// Parses an RFC 3339 timestamp as UTC, or a plain date and time
+pub fn parse(timestamp: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|at| at.naive_utc())
+        .or_else(|_| NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S"))
+        .or_else(|_| NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S%.f"))
+        .ok()
+}
+
+/// Gets the range `[start, end)` a leaderboard window covers at `now`
+///
+/// `24h` covers the current hour and the 23 before it, `7d` and `30d` cover whole days up
+/// to and including today and `custom` covers `from` to `to`, which defaults to today.
+pub fn window(
+    name: &str,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    now: NaiveDateTime,
+) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let today = now.date();
+
+    let (from, to) = match name {
+        "24h" => {
+            let end = now.with_minute(0)?.with_second(0)?.with_nanosecond(0)? + TimeDelta::hours(1);
+            return Some((end - TimeDelta::hours(24), end));
+        }
+        "7d" => (today - Days::new(6), today),
+        "30d" => (today - Days::new(29), today),
+        "custom" => (from?, to.unwrap_or(today)),
+        _ => return None,
+    };
+
+    if from > to {
+        return None;
+    }
+
+    Some((
+        from.and_time(NaiveTime::MIN),
+        (to + Days::new(1)).and_time(NaiveTime::MIN),
+    ))
+}
+
+pub fn bucket_start(date: NaiveDate, interval: &str) -> NaiveDate {
+    match interval {
+        "week" => date - Days::new(date.weekday().num_days_from_monday().into()),
+        "month" => date.with_day(1).unwrap_or(date),
+        _ => date,
+    }
+}
+
+/// The most buckets a timeline may span per interval, about a year of days and ten years of months
+pub fn max_buckets(interval: &str) -> i64 {
+    match interval {
+        "week" => 260,
+        "month" => 120,
+        _ => 366,
+    }
+}
+
+/// Counts the buckets from `from` to `to` without creating them
+pub fn bucket_count(from: NaiveDate, to: NaiveDate, interval: &str) -> i64 {
+    let start = bucket_start(from, interval);
+
+    if start > to {
+        return 0;
+    }
+
+    match interval {
+        "week" => (to - start).num_days() / 7 + 1,
+        "month" => {
+            i64::from(to.year() - start.year()) * 12 + i64::from(to.month())
+                - i64::from(start.month())
+                + 1
+        }
+        _ => (to - start).num_days() + 1,
+    }
+}
+
+pub fn buckets(from: NaiveDate, to: NaiveDate, interval: &str) -> Vec<NaiveDate> {
+    let mut buckets = Vec::new();
+    let mut curr = bucket_start(from, interval);
+
+    while curr <= to {
+        buckets.push(curr);
+        curr = match interval {
+            "week" => curr + Days::new(7),
+            "month" => curr + Months::new(1),
+            _ => curr + Days::new(1),
+        };
+    }
+
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn hour_test() {
+        let at = NaiveDateTime::parse_from_str("2025-06-04 13:45:12", "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(hour(at), "2025-06-04 13:00:00");
+    }
+
+    #[test]
+    fn parse_test() {
+        let at = NaiveDateTime::parse_from_str("2025-06-04 13:45:12", "%Y-%m-%d %H:%M:%S").unwrap();
+
+        assert_eq!(
+            parse("2025-06-04T15:45:12.345+02:00").map(hour),
+            Some(hour(at))
+        );
+        assert_eq!(parse("2025-06-04 13:45:12"), Some(at));
+        assert_eq!(parse("2025-06-04T13:45:12"), Some(at));
+        assert_eq!(parse("yesterday"), None);
+    }
+
+    #[test]
+    fn window_test() {
+        let now =
+            NaiveDateTime::parse_from_str("2025-06-04 13:45:12", "%Y-%m-%d %H:%M:%S").unwrap();
+        let at = |s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+
+        assert_eq!(
+            window("24h", None, None, now),
+            Some((at("2025-06-03 14:00:00"), at("2025-06-04 14:00:00")))
+        );
+        assert_eq!(
+            window("7d", None, None, now),
+            Some((at("2025-05-29 00:00:00"), at("2025-06-05 00:00:00")))
+        );
+        assert_eq!(
+            window("custom", Some(date("2025-06-01")), None, now),
+            Some((at("2025-06-01 00:00:00"), at("2025-06-05 00:00:00")))
+        );
+        assert_eq!(window("custom", None, Some(date("2025-06-01")), now), None);
+        assert_eq!(
+            window(
+                "custom",
+                Some(date("2025-06-02")),
+                Some(date("2025-06-01")),
+                now
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn bucket_start_test() {
+        assert_eq!(bucket_start(date("2025-06-04"), "day"), date("2025-06-04"));
+        assert_eq!(bucket_start(date("2025-06-04"), "week"), date("2025-06-02"));
+        assert_eq!(bucket_start(date("2025-06-02"), "week"), date("2025-06-02"));
+        assert_eq!(
+            bucket_start(date("2025-06-04"), "month"),
+            date("2025-06-01")
+        );
+    }
+
+    #[test]
+    fn buckets_test() {
+        assert_eq!(
+            buckets(date("2025-06-01"), date("2025-06-03"), "day").len(),
+            3
+        );
+        assert_eq!(
+            buckets(date("2025-06-04"), date("2025-06-16"), "week"),
+            vec![date("2025-06-02"), date("2025-06-09"), date("2025-06-16")]
+        );
+        assert_eq!(
+            buckets(date("2025-01-31"), date("2025-03-01"), "month"),
+            vec![date("2025-01-01"), date("2025-02-01"), date("2025-03-01")]
+        );
+        assert!(buckets(date("2025-06-02"), date("2025-06-01"), "day").is_empty());
+    }
+
+    #[test]
+    fn bucket_count_test() {
+        for (from, to, interval) in [
+            ("2025-06-01", "2025-06-03", "day"),
+            ("2025-06-04", "2025-06-16", "week"),
+            ("2025-01-31", "2025-03-01", "month"),
+            ("2024-11-15", "2026-02-01", "month"),
+            ("2025-06-02", "2025-06-01", "day"),
+        ] {
+            assert_eq!(
+                bucket_count(date(from), date(to), interval),
+                buckets(date(from), date(to), interval).len() as i64
+            );
+        }
+    }
+}
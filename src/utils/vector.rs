@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+pub type Vector = HashMap<String, f64>;
+
+pub fn cosine(vector1: &Vector, vector2: &Vector) -> f64 {
+    // Iterate over the smaller vector, only shared keys add to the dot product
+    let (small, large) = if vector1.len() <= vector2.len() {
+        (vector1, vector2)
+    } else {
+        (vector2, vector1)
+    };
+
+    let dot: f64 = small
+        .iter()
+        .filter_map(|(key, value)| large.get(key).map(|other| value * other))
+        .sum();
+
+    let norm1 = vector1.values().map(|v| v * v).sum::<f64>().sqrt();
+    let norm2 = vector2.values().map(|v| v * v).sum::<f64>().sqrt();
+
+    if norm1 == 0.0 || norm2 == 0.0 {
+        return 0.0;
+    }
+
+    dot / (norm1 * norm2)
+}
+
+/// Inverse document frequency, terms found in every document weigh nothing
+pub fn idf(documents: usize, frequency: usize) -> f64 {
+    if frequency == 0 {
+        return 0.0;
+    }
+
+    (documents as f64 / frequency as f64).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector(pairs: &[(&str, f64)]) -> Vector {
+        pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+    }
+
+    #[test]
+    fn cosine_test() {
+        let a = vector(&[("hello", 1.0), ("world", 1.0)]);
+        let b = vector(&[("hello", 2.0), ("world", 2.0)]);
+        let c = vector(&[("rust", 3.0)]);
+        let d = vector(&[("hello", 1.0)]);
+
+        assert!((cosine(&a, &b) - 1.0).abs() < 1e-9);
+        assert_eq!(cosine(&a, &c), 0.0);
+        assert!((cosine(&a, &d) - 1.0 / 2f64.sqrt()).abs() < 1e-9);
+        assert_eq!(cosine(&a, &Vector::new()), 0.0);
+    }
+
+    #[test]
+    fn idf_test() {
+        assert_eq!(idf(4, 4), 0.0);
+        assert!((idf(4, 1) - 4f64.ln()).abs() < 1e-9);
+        assert_eq!(idf(4, 0), 0.0);
+    }
+}